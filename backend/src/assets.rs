@@ -0,0 +1,113 @@
+//! The web UI's files, from the assets directory or, with the
+//! `embed-frontend` feature, built into the binary.
+//!
+//! Embedded files go out with an ETag and `Cache-Control: no-cache`, so
+//! browsers keep them but check back, since `pkg` doesn't have hashes in its
+//! file names.
+
+use std::path::Path;
+
+use anyhow::Result;
+#[cfg(feature = "embed-frontend")]
+use tide::{http::Mime, Request, Response, StatusCode};
+
+/// URLs and the files under the assets directory they're served from.
+const FILES: &[(&str, &str)] = &[
+    ("/", "index.html"),
+    ("/style.css", "static/style.css"),
+    ("/bulma-list.css", "static/bulma-list.css"),
+];
+
+/// URL prefixes and the directories under the assets directory they're
+/// served from.
+const DIRS: &[(&str, &str)] = &[
+    ("/pkg", "pkg"),
+    ("/mdc", "static/mdc"),
+    ("/vendor", "static/vendor"),
+];
+
+/// Whether `path` is one of the frontend's files, which anyone can fetch.
+pub(crate) fn is_asset(path: &str) -> bool {
+    FILES.iter().any(|(url, _)| *url == path)
+        || DIRS.iter().any(|(url, _)| {
+            path.strip_prefix(url)
+                .map_or(false, |rest| rest.starts_with('/'))
+        })
+}
+
+#[cfg(feature = "embed-frontend")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "../frontend/"]
+#[include = "index.html"]
+#[include = "pkg/*"]
+#[include = "static/**/*"]
+struct Frontend;
+
+/// Serve the frontend from `dir`.
+#[cfg(not(feature = "embed-frontend"))]
+pub(crate) fn serve<S>(app: &mut tide::Server<S>, dir: &Path) -> Result<()>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    for (url, file) in FILES {
+        app.at(url).serve_file(dir.join(file))?;
+    }
+    for (url, sub) in DIRS {
+        app.at(url).serve_dir(dir.join(sub))?;
+    }
+    Ok(())
+}
+
+/// Serve the frontend built into the binary, `dir` is only there to match
+/// serving from disk.
+#[cfg(feature = "embed-frontend")]
+pub(crate) fn serve<S>(app: &mut tide::Server<S>, _dir: &Path) -> Result<()>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if Frontend::get("index.html").is_none() {
+        anyhow::bail!("The frontend wasn't built before the backend, there's nothing to embed");
+    }
+    for (url, file) in FILES {
+        app.at(url)
+            .get(move |req: Request<S>| async move { embedded(&req, file) });
+    }
+    for (url, sub) in DIRS {
+        app.at(&format!("{}/*path", url))
+            .get(move |req: Request<S>| async move {
+                let path = format!("{}/{}", sub, req.param("path")?);
+                embedded(&req, &path)
+            });
+    }
+    Ok(())
+}
+
+#[cfg(feature = "embed-frontend")]
+fn embedded<S>(req: &Request<S>, path: &str) -> tide::Result {
+    let Some(file) = Frontend::get(path) else {
+        return Ok(Response::new(StatusCode::NotFound));
+    };
+    let etag = format!(
+        "\"{}\"",
+        file.metadata
+            .sha256_hash()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+    let fresh = req
+        .header("If-None-Match")
+        .map_or(false, |tags| tags.iter().any(|tag| tag.as_str() == etag));
+    let resp = if fresh {
+        Response::builder(StatusCode::NotModified)
+    } else {
+        let mime: Mime = file.metadata.mimetype().parse()?;
+        Response::builder(StatusCode::Ok)
+            .body(file.data.into_owned())
+            .content_type(mime)
+    };
+    Ok(resp
+        .header("ETag", etag)
+        .header("Cache-Control", "no-cache")
+        .build())
+}
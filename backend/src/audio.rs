@@ -0,0 +1,239 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Cursor, Read},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use lights::effects::{self, AudioAnalyzer, AudioLevels};
+
+/// Samples per read, about 12ms at 44.1kHz.
+const CHUNK: usize = 512;
+
+/// Raw PCM without a WAV header is assumed to be this, mono.
+const RAW_SAMPLE_RATE: u32 = 44_100;
+
+/// Something to capture audio from.
+///
+/// `audio.source` in the config takes one of:
+///
+/// * `alsa` or `alsa:<device>`, to capture from an ALSA device (needs the
+///   `alsa` feature),
+/// * `-` or `stdin`, to read PCM from a pipe, like `arecord -t raw -f S16_LE`,
+/// * anything else is a path to a WAV or raw PCM file, which is played in a
+///   loop at its own pace.
+///
+/// Files and pipes can be WAV with 16 bit samples, or headerless signed 16 bit
+/// little endian mono at 44.1kHz.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioSource {
+    Alsa(String),
+    Stdin,
+    File(PathBuf),
+}
+
+impl FromStr for AudioSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "alsa" => Self::Alsa("default".to_owned()),
+            "-" | "stdin" => Self::Stdin,
+            _ => match s.strip_prefix("alsa:") {
+                Some(device) => Self::Alsa(device.to_owned()),
+                None => Self::File(s.into()),
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PcmFormat {
+    sample_rate: u32,
+    channels: u16,
+}
+
+/// Start analysing `source` on its own thread, publishing to
+/// `effects::set_audio_levels` until `term` is set.
+pub fn spawn_capture(source: AudioSource, term: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("Capturing audio from {:?}", source);
+        if let Err(e) = capture(&source, &term) {
+            log::error!("Audio capture from {:?} stopped: {}", source, e);
+        }
+        effects::set_audio_levels(AudioLevels::default(), Utc::now());
+    })
+}
+
+fn capture(source: &AudioSource, term: &AtomicBool) -> Result<()> {
+    match source {
+        AudioSource::Alsa(device) => capture_alsa(device, term),
+        AudioSource::Stdin => {
+            let (format, reader) = open_pcm(io::stdin().lock())?;
+            pump(reader, format, false, term)?;
+            Ok(())
+        }
+        AudioSource::File(path) => {
+            while !term.load(Ordering::Relaxed) {
+                let file = BufReader::new(File::open(path)?);
+                let (format, reader) = open_pcm(file)?;
+                // Otherwise it'd be reopened as fast as it can be read.
+                if pump(reader, format, true, term)? == 0 {
+                    bail!("{} is shorter than one chunk of audio", path.display());
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Read a WAV header if there is one, otherwise treat the stream as raw PCM.
+fn open_pcm(mut reader: impl Read + 'static) -> Result<(PcmFormat, Box<dyn Read>)> {
+    let mut magic = [0_u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != b"RIFF" {
+        let format = PcmFormat {
+            sample_rate: RAW_SAMPLE_RATE,
+            channels: 1,
+        };
+        return Ok((format, Box::new(Cursor::new(magic).chain(reader))));
+    }
+
+    let mut header = [0_u8; 8];
+    reader.read_exact(&mut header)?;
+    if &header[4..] != b"WAVE" {
+        bail!("RIFF file isn't a WAV");
+    }
+
+    let mut format = None;
+    loop {
+        let mut chunk = [0_u8; 8];
+        reader.read_exact(&mut chunk)?;
+        let size = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as usize;
+        match &chunk[..4] {
+            b"fmt " => {
+                if size < 16 {
+                    bail!("WAV format chunk is too short");
+                }
+                let mut fmt = vec![0_u8; size + size % 2];
+                reader.read_exact(&mut fmt)?;
+                let tag = u16::from_le_bytes([fmt[0], fmt[1]]);
+                let bits = u16::from_le_bytes([fmt[14], fmt[15]]);
+                if tag != 1 || bits != 16 {
+                    bail!("Only 16 bit PCM WAVs are supported");
+                }
+                let sample_rate = u32::from_le_bytes([fmt[4], fmt[5], fmt[6], fmt[7]]);
+                if sample_rate == 0 {
+                    bail!("WAV sample rate is 0");
+                }
+                format = Some(PcmFormat {
+                    channels: u16::from_le_bytes([fmt[2], fmt[3]]).max(1),
+                    sample_rate,
+                });
+            }
+            b"data" => {
+                let format = format.ok_or_else(|| anyhow!("WAV data before its format"))?;
+                return Ok((format, Box::new(reader.take(size as u64))));
+            }
+            _ => {
+                io::copy(&mut (&mut reader).take((size + size % 2) as u64), &mut io::sink())?;
+            }
+        }
+    }
+}
+
+/// Feed `reader` through the analysis until it runs out, returning how many
+/// chunks it had. Files are `paced` to real time, pipes and devices already
+/// are.
+fn pump(mut reader: impl Read, format: PcmFormat, paced: bool, term: &AtomicBool) -> Result<usize> {
+    let channels = format.channels as usize;
+    let mut analyzer = AudioAnalyzer::new(format.sample_rate);
+    let mut buffer = vec![0_u8; CHUNK * channels * 2];
+    let mut samples = Vec::with_capacity(CHUNK);
+    let chunk_time = Duration::from_secs_f64(CHUNK as f64 / format.sample_rate as f64);
+    let mut next = Instant::now();
+    let mut chunks = 0;
+
+    while !term.load(Ordering::Relaxed) {
+        match reader.read_exact(&mut buffer) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(chunks),
+            r => r?,
+        }
+        chunks += 1;
+        // Mix down to mono.
+        samples.clear();
+        samples.extend(buffer.chunks_exact(channels * 2).map(|frame| {
+            frame
+                .chunks_exact(2)
+                .map(|s| i16::from_le_bytes([s[0], s[1]]) as f32 / i16::MAX as f32)
+                .sum::<f32>()
+                / channels as f32
+        }));
+        let now = Utc::now();
+        if let Some(levels) = analyzer.push(&samples, now) {
+            effects::set_audio_levels(levels, now);
+        }
+
+        if paced {
+            next += chunk_time;
+            thread::sleep(next.saturating_duration_since(Instant::now()));
+        }
+    }
+    Ok(chunks)
+}
+
+#[cfg(feature = "alsa")]
+fn capture_alsa(device: &str, term: &AtomicBool) -> Result<()> {
+    use alsa::{
+        pcm::{Access, Format, HwParams, PCM},
+        Direction, ValueOr,
+    };
+
+    let pcm = PCM::new(device, Direction::Capture, false)?;
+    {
+        let params = HwParams::any(&pcm)?;
+        params.set_channels(1)?;
+        params.set_rate(RAW_SAMPLE_RATE, ValueOr::Nearest)?;
+        params.set_format(Format::s16())?;
+        params.set_access(Access::RWInterleaved)?;
+        pcm.hw_params(&params)?;
+    }
+    let sample_rate = pcm.hw_params_current()?.get_rate()?;
+    let io = pcm.io_i16()?;
+    pcm.start()?;
+
+    let mut analyzer = AudioAnalyzer::new(sample_rate);
+    let mut buffer = [0_i16; CHUNK];
+    while !term.load(Ordering::Relaxed) {
+        let read = match io.readi(&mut buffer) {
+            Ok(read) => read,
+            Err(e) => {
+                // Overruns happen when we fall behind, just pick up again.
+                pcm.try_recover(e, true)?;
+                continue;
+            }
+        };
+        let samples = buffer[..read]
+            .iter()
+            .map(|&s| s as f32 / i16::MAX as f32)
+            .collect::<Vec<_>>();
+        let now = Utc::now();
+        if let Some(levels) = analyzer.push(&samples, now) {
+            effects::set_audio_levels(levels, now);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "alsa"))]
+fn capture_alsa(_device: &str, _term: &AtomicBool) -> Result<()> {
+    bail!("built without ALSA support, enable the `alsa` feature")
+}
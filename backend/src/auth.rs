@@ -0,0 +1,359 @@
+//! Optional logins for the web API, set up in the `[auth]` config.
+//!
+//! The web UI logs in with a password at `/login` and gets a session cookie,
+//! automation sends `Authorization: Bearer <token>`. Either gives a `Role`:
+//! read-only can look at everything, admin can also change it. Without any
+//! passwords or tokens configured everyone is an admin.
+//!
+//! After a few wrong passwords from one address `/login` turns that address
+//! away for a while, twice as long each time it keeps trying.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tide::{
+    http::{cookies::SameSite, mime, Method},
+    prelude::*,
+    sessions::{MemoryStore, SessionMiddleware},
+    utils::async_trait,
+    Middleware, Next, Request, Response, StatusCode,
+};
+
+use crate::{assets, config::Auth};
+
+const ROLE_KEY: &str = "role";
+
+/// Routes anyone can use, so there's a way to log in.
+const OPEN: &[&str] = &["/login", "/logout", "/auth"];
+
+/// Routes that are posted to without changing anything.
+const READ_ONLY_POSTS: &[&str] = &["/compile", "/export"];
+
+/// Wrong passwords an address gets before it has to wait.
+const FREE_ATTEMPTS: u32 = 5;
+
+/// The first wait, doubled for each wrong password after that.
+const FIRST_LOCKOUT: Duration = Duration::from_secs(1);
+
+/// The longest an address is made to wait.
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Wrong passwords older than this are forgotten.
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Role {
+    ReadOnly,
+    Admin,
+}
+
+impl Auth {
+    fn enabled(&self) -> bool {
+        self.password.is_some()
+            || self.read_only_password.is_some()
+            || !self.tokens.is_empty()
+            || !self.read_only_tokens.is_empty()
+    }
+
+    fn password_role(&self, password: &str) -> Option<Role> {
+        if self
+            .password
+            .as_deref()
+            .map_or(false, |p| same(p, password))
+        {
+            Some(Role::Admin)
+        } else if self
+            .read_only_password
+            .as_deref()
+            .map_or(false, |p| same(p, password))
+        {
+            Some(Role::ReadOnly)
+        } else {
+            None
+        }
+    }
+
+    fn token_role(&self, token: &str) -> Option<Role> {
+        if self.tokens.iter().any(|t| same(t, token)) {
+            Some(Role::Admin)
+        } else if self.read_only_tokens.iter().any(|t| same(t, token)) {
+            Some(Role::ReadOnly)
+        } else {
+            None
+        }
+    }
+
+    /// The role `req` has, `Err` if it has a bearer token that's wrong.
+    fn role<S>(&self, req: &Request<S>) -> Result<Option<Role>, ()> {
+        if !self.enabled() {
+            return Ok(Some(Role::Admin));
+        }
+        match req.header("Authorization") {
+            Some(header) => {
+                let token = header.as_str().strip_prefix("Bearer ").ok_or(())?;
+                self.token_role(token.trim()).map(Some).ok_or(())
+            }
+            None => Ok(req.session().get(ROLE_KEY)),
+        }
+    }
+}
+
+/// Compare secrets in the same time however much of them matches.
+fn same(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// The least a request needs to be let through.
+fn needed(method: Method, path: &str) -> Role {
+    match method {
+        Method::Get | Method::Head | Method::Options => Role::ReadOnly,
+        Method::Post if READ_ONLY_POSTS.contains(&path) => Role::ReadOnly,
+        _ => Role::Admin,
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    Response::builder(status)
+        .body(json!({ "error": message }))
+        .content_type(mime::JSON)
+        .build()
+}
+
+struct RequireRole {
+    auth: Arc<Auth>,
+}
+
+#[async_trait]
+impl<S: Clone + Send + Sync + 'static> Middleware<S> for RequireRole {
+    async fn handle(&self, req: Request<S>, next: Next<'_, S>) -> tide::Result {
+        let path = req.url().path().to_owned();
+        if OPEN.contains(&path.as_str()) || assets::is_asset(&path) {
+            return Ok(next.run(req).await);
+        }
+        let resp = match self.auth.role(&req) {
+            Ok(Some(role)) if role >= needed(req.method(), &path) => next.run(req).await,
+            Ok(Some(_)) => error(
+                StatusCode::Forbidden,
+                "Read-only users can't change anything",
+            ),
+            Ok(None) => error(StatusCode::Unauthorized, "Log in first"),
+            Err(()) => error(StatusCode::Unauthorized, "Bad bearer token"),
+        };
+        Ok(resp)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+impl Failures {
+    /// When the address can try again.
+    fn locked_until(&self) -> Instant {
+        let Some(over) = self.count.checked_sub(FREE_ATTEMPTS) else {
+            return self.last;
+        };
+        let lockout = FIRST_LOCKOUT
+            .checked_mul(1 << over.min(16))
+            .map_or(MAX_LOCKOUT, |lockout| lockout.min(MAX_LOCKOUT));
+        self.last + lockout
+    }
+}
+
+/// Wrong passwords by address, to slow down guessing.
+#[derive(Debug, Default)]
+struct Throttle {
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl Throttle {
+    /// How long `ip` has to wait before it can try a password.
+    fn wait(&self, ip: IpAddr, now: Instant) -> Option<Duration> {
+        let failures = self.failures.lock().unwrap();
+        let until = failures.get(&ip)?.locked_until();
+        (until > now).then(|| until - now)
+    }
+
+    fn failed(&self, ip: IpAddr, now: Instant) {
+        let mut failures = self.failures.lock().unwrap();
+        // Forget addresses that gave up, so the map doesn't keep growing.
+        failures.retain(|_, f| now.saturating_duration_since(f.last) < FORGET_AFTER);
+        let entry = failures.entry(ip).or_insert(Failures {
+            count: 0,
+            last: now,
+        });
+        entry.count += 1;
+        entry.last = now;
+    }
+
+    fn succeeded(&self, ip: IpAddr) {
+        self.failures.lock().unwrap().remove(&ip);
+    }
+}
+
+/// The address the request came straight from. Forwarded headers aren't
+/// used, since anyone could set them to dodge the throttle.
+fn peer_ip<S>(req: &Request<S>) -> Option<IpAddr> {
+    req.peer_addr()?
+        .parse::<SocketAddr>()
+        .ok()
+        .map(|addr| addr.ip())
+}
+
+#[derive(Debug, Deserialize)]
+struct Login {
+    password: String,
+}
+
+/// Check every request against `auth`, and add the routes to log in and out.
+pub(crate) fn serve<S>(app: &mut tide::Server<S>, auth: Auth) -> Result<()>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let secret = match &auth.session_secret {
+        Some(secret) => secret.as_bytes().to_vec(),
+        None => rand::random::<[u8; 32]>().to_vec(),
+    };
+    app.with(
+        SessionMiddleware::new(MemoryStore::new(), &secret).with_same_site_policy(SameSite::Strict),
+    );
+    let auth = Arc::new(auth);
+    app.with(RequireRole {
+        auth: Arc::clone(&auth),
+    });
+
+    let login = Arc::clone(&auth);
+    let throttle = Arc::new(Throttle::default());
+    app.at("/login").post(move |mut req: Request<S>| {
+        let auth = Arc::clone(&login);
+        let throttle = Arc::clone(&throttle);
+        async move {
+            let ip = peer_ip(&req);
+            if let Some(wait) = ip.and_then(|ip| throttle.wait(ip, Instant::now())) {
+                let seconds = wait.as_secs() + 1;
+                let mut resp = error(
+                    StatusCode::TooManyRequests,
+                    &format!("Too many wrong passwords, try again in {} seconds", seconds),
+                );
+                resp.insert_header("Retry-After", seconds.to_string());
+                return Ok(resp);
+            }
+            let Login { password } = req.body_json().await?;
+            let Some(role) = auth.password_role(&password) else {
+                if let Some(ip) = ip {
+                    throttle.failed(ip, Instant::now());
+                }
+                return Ok(error(StatusCode::Unauthorized, "Wrong password"));
+            };
+            if let Some(ip) = ip {
+                throttle.succeeded(ip);
+            }
+            let session = req.session_mut();
+            // A new session id, so one from before logging in can't be
+            // reused.
+            session.regenerate();
+            session.insert(ROLE_KEY, role)?;
+            Ok(Response::builder(200)
+                .body(json!({ "role": role }))
+                .content_type(mime::JSON)
+                .build())
+        }
+    });
+    app.at("/logout").post(|mut req: Request<S>| async move {
+        req.session_mut().destroy();
+        Ok(Response::new(200))
+    });
+    app.at("/auth").get(move |req: Request<S>| {
+        let auth = Arc::clone(&auth);
+        async move {
+            let role = auth.role(&req).unwrap_or(None);
+            Ok(Response::builder(200)
+                .body(json!({ "required": auth.enabled(), "role": role }))
+                .content_type(mime::JSON)
+                .build())
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_outranks_read_only() {
+        assert!(Role::Admin > Role::ReadOnly);
+        assert!(Role::Admin >= needed(Method::Post, "/details"));
+        assert!(Role::ReadOnly < needed(Method::Post, "/details"));
+    }
+
+    #[test]
+    fn reads_need_read_only() {
+        assert_eq!(needed(Method::Get, "/details"), Role::ReadOnly);
+        assert_eq!(needed(Method::Head, "/details"), Role::ReadOnly);
+        assert_eq!(needed(Method::Options, "/details"), Role::ReadOnly);
+        assert_eq!(needed(Method::Post, "/compile"), Role::ReadOnly);
+        assert_eq!(needed(Method::Post, "/export"), Role::ReadOnly);
+    }
+
+    #[test]
+    fn changes_need_admin() {
+        assert_eq!(needed(Method::Post, "/details"), Role::Admin);
+        assert_eq!(needed(Method::Put, "/export"), Role::Admin);
+        assert_eq!(needed(Method::Delete, "/presets/a"), Role::Admin);
+        assert_eq!(needed(Method::Post, "/export/"), Role::Admin);
+    }
+
+    #[test]
+    fn same_compares_whole_strings() {
+        assert!(same("", ""));
+        assert!(same("secret", "secret"));
+        assert!(!same("secret", "Secret"));
+        assert!(!same("secret", "secre"));
+        assert!(!same("secret", "secrets"));
+        assert!(!same("", "secret"));
+    }
+
+    #[test]
+    fn throttle_locks_out_after_free_attempts() {
+        let throttle = Throttle::default();
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        let other: IpAddr = "192.0.2.2".parse().unwrap();
+        let now = Instant::now();
+        for _ in 0..FREE_ATTEMPTS {
+            assert_eq!(throttle.wait(ip, now), None);
+            throttle.failed(ip, now);
+        }
+        assert_eq!(throttle.wait(ip, now), Some(FIRST_LOCKOUT));
+        throttle.failed(ip, now);
+        assert_eq!(throttle.wait(ip, now), Some(FIRST_LOCKOUT * 2));
+        assert_eq!(throttle.wait(ip, now + FIRST_LOCKOUT * 2), None);
+        assert_eq!(throttle.wait(other, now), None);
+        throttle.succeeded(ip);
+        assert_eq!(throttle.wait(ip, now), None);
+    }
+
+    #[test]
+    fn throttle_lockout_is_capped() {
+        let throttle = Throttle::default();
+        let ip: IpAddr = "2001:db8::1".parse().unwrap();
+        let now = Instant::now();
+        for _ in 0..100 {
+            throttle.failed(ip, now);
+        }
+        assert_eq!(throttle.wait(ip, now), Some(MAX_LOCKOUT));
+    }
+}
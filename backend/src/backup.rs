@@ -0,0 +1,150 @@
+//! Backups of the whole store, on request and every so often.
+//!
+//! A backup is a `Snapshot` of every database as MsgPack. Details are kept as
+//! the bytes they're stored as, so restoring an older backup migrates them
+//! like any other old details.
+//!
+//! Periodic backups are off unless the config has a `[backup]` section. Then
+//! a backup is written to its `dir` at startup and every `hours` (24 by
+//! default), keeping the newest `keep` (7 by default).
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use lights::{effects, palettes::Palette};
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+use crate::{
+    config::Backup,
+    storage::{self, Storage},
+};
+
+/// Bumped if the layout of `Snapshot` changes.
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub(crate) version: u32,
+    /// Stored details by name, `Versioned` MsgPack.
+    pub(crate) details: BTreeMap<String, ByteBuf>,
+    pub(crate) scripts: BTreeMap<String, String>,
+    pub(crate) plugins: BTreeMap<String, ByteBuf>,
+    pub(crate) palettes: BTreeMap<String, Palette>,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            details: Default::default(),
+            scripts: Default::default(),
+            plugins: Default::default(),
+            palettes: Default::default(),
+        }
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(self)?)
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let snapshot: Self = rmp_serde::from_slice(bytes)?;
+        if snapshot.version > SNAPSHOT_VERSION {
+            bail!(
+                "Backup version {} is newer than this build knows about ({})",
+                snapshot.version,
+                SNAPSHOT_VERSION
+            );
+        }
+        Ok(snapshot)
+    }
+
+    /// Everything in the snapshot that wouldn't load, so a bad backup can be
+    /// turned away before it replaces anything.
+    pub(crate) fn problems(&self) -> Vec<String> {
+        let details = self.details.iter().filter_map(|(name, bytes)| {
+            if name != storage::MAIN && storage::is_reserved(name) {
+                return Some(format!("details {:?}: that name is reserved", name));
+            }
+            let e = storage::decode_details(bytes).err()?;
+            Some(format!("details {:?}: {}", name, e))
+        });
+        let plugins = self.plugins.iter().filter_map(|(name, bytes)| {
+            let e = effects::check_plugin_module(bytes).err()?;
+            Some(format!("plugin {:?}: {}", name, e))
+        });
+        details.chain(plugins).collect()
+    }
+}
+
+/// Back up to `config.dir` now and every `config.hours` until `term` is set.
+pub(crate) fn spawn_periodic(
+    storage: Storage,
+    config: Backup,
+    term: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut next = Instant::now();
+        while !term.load(Ordering::Relaxed) {
+            if Instant::now() >= next {
+                match write_backup(&storage, &config) {
+                    Ok(path) => log::info!("Backed up to {}", path.display()),
+                    Err(e) => log::error!("Backup failed: {}", e),
+                }
+                next += config.interval();
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    })
+}
+
+fn write_backup(storage: &Storage, config: &Backup) -> Result<PathBuf> {
+    fs::create_dir_all(&config.dir)?;
+    let bytes = storage.snapshot()?.to_bytes()?;
+    let path = config.dir.join(format!(
+        "backup-{}.msgpack",
+        Utc::now().format("%Y%m%dT%H%M%S")
+    ));
+    // Write it whole before it takes a backup's name, so a crash halfway
+    // doesn't leave a broken backup to rotate the good ones out.
+    let partial = path.with_extension("partial");
+    fs::write(&partial, bytes)?;
+    fs::rename(&partial, &path)?;
+    rotate(&config.dir, config.keep)?;
+    Ok(path)
+}
+
+/// Delete all but the newest `keep` backups in `dir`.
+fn rotate(dir: &Path, keep: usize) -> Result<()> {
+    let mut backups: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map_or(false, |n| {
+                    n.starts_with("backup-") && n.ends_with(".msgpack")
+                })
+        })
+        .collect();
+    // The timestamps sort oldest first.
+    backups.sort();
+    let old = backups.len().saturating_sub(keep);
+    for path in &backups[..old] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,435 @@
+//! The daemon's configuration, from a TOML file and the command line.
+//!
+//! Anything missing from the file has a default, and flags on the command
+//! line win over the file. Relative paths are from the working directory,
+//! which is `daemon.working_dir` once daemonized.
+//!
+//! ```toml
+//! listen = "0.0.0.0:8000"
+//! db = "./db/effects.db"
+//! assets = "./frontend"
+//! foreground = false
+//! log_level = "info"
+//!
+//! [daemon]
+//! working_dir = "/home/pi"
+//! pid_file = "/home/pi/raspylights.pid"
+//!
+//! [hardware]
+//! pin = 18
+//! dma = 10
+//! frequency = 800000
+//! color_order = "rbg"
+//!
+//! [auth]
+//! password = "change me"
+//! read_only_tokens = ["a long random string"]
+//!
+//! [tls]
+//! listen = "0.0.0.0:8443"
+//! redirect_http = true
+//!
+//! [backup]
+//! dir = "/home/pi/backups"
+//! hours = 24
+//! keep = 7
+//!
+//! [audio]
+//! source = "alsa:hw:1,0"
+//!
+//! [mqtt]
+//! host = "localhost"
+//! port = 1883
+//!
+//! [[schedule]]
+//! signal = "night"
+//! at = "22:00"
+//! value = true
+//! ```
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use lights::signals::Scheduled;
+use log::LevelFilter;
+use serde::Deserialize;
+
+/// Looked for in the working directory when there's no `--config`.
+const DEFAULT_FILE: &str = "raspylights.toml";
+
+/// GPIO pins the ws281x driver can drive a strip from: PWM, PCM or SPI.
+const PINS: &[i32] = &[10, 12, 13, 18, 19, 21];
+
+/// DMA channels that are safe to use, others are taken by the GPU or the SD
+/// card.
+const DMA_CHANNELS: &[i32] = &[5, 8, 9, 10, 11, 12, 13, 14];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    pub(crate) listen: String,
+    pub(crate) db: PathBuf,
+    /// Holds `index.html`, `pkg` and `static`. Not used when the frontend
+    /// is embedded.
+    pub(crate) assets: PathBuf,
+    /// Stay attached to the terminal rather than daemonizing. Only the Pi
+    /// daemonizes anyway.
+    pub(crate) foreground: bool,
+    pub(crate) log_level: String,
+    pub(crate) daemon: Daemon,
+    pub(crate) hardware: Hardware,
+    pub(crate) auth: Auth,
+    /// Serve HTTPS too, if there's a `[tls]` section.
+    pub(crate) tls: Option<Tls>,
+    /// Back up every so often, if there's a `[backup]` section.
+    pub(crate) backup: Option<Backup>,
+    /// Listen for the audio effects, if there's an `[audio]` section.
+    pub(crate) audio: Option<Audio>,
+    /// Take signals from an MQTT broker, if there's an `[mqtt]` section. Needs
+    /// the `mqtt` feature.
+    #[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+    pub(crate) mqtt: Option<Mqtt>,
+    /// Signals to set at times of day, one `[[schedule]]` each.
+    pub(crate) schedule: Vec<Scheduled>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen: "0.0.0.0:8000".to_owned(),
+            db: "./db/effects.db".into(),
+            assets: "./frontend".into(),
+            foreground: false,
+            log_level: "info".to_owned(),
+            daemon: Default::default(),
+            hardware: Default::default(),
+            auth: Default::default(),
+            tls: None,
+            backup: None,
+            audio: None,
+            mqtt: None,
+            schedule: vec![],
+        }
+    }
+}
+
+/// Where the daemon keeps its files, all in the home directory by default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Daemon {
+    pub(crate) working_dir: Option<PathBuf>,
+    pub(crate) pid_file: Option<PathBuf>,
+    pub(crate) stdout: Option<PathBuf>,
+    pub(crate) stderr: Option<PathBuf>,
+}
+
+/// How the strip is wired to the Pi.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Hardware {
+    /// The GPIO pin the strip's data line is on.
+    pub(crate) pin: i32,
+    pub(crate) dma: i32,
+    /// Data rate in Hz.
+    pub(crate) frequency: u32,
+    #[cfg_attr(not(target_arch = "arm"), allow(dead_code))]
+    pub(crate) color_order: ColorOrder,
+}
+
+impl Default for Hardware {
+    fn default() -> Self {
+        Self {
+            pin: 18,
+            dma: 10,
+            frequency: 800_000,
+            color_order: ColorOrder::Rbg,
+        }
+    }
+}
+
+/// Who can use the web API. With nothing set anyone can do anything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Auth {
+    /// Logs in to the web UI able to change things.
+    pub(crate) password: Option<String>,
+    /// Logs in to the web UI able to look but not change anything.
+    pub(crate) read_only_password: Option<String>,
+    /// Bearer tokens for automation, able to change things.
+    pub(crate) tokens: Vec<String>,
+    pub(crate) read_only_tokens: Vec<String>,
+    /// Signs session cookies, at least 32 bytes. A random one is made each
+    /// start if it's left out, which logs everyone out on a restart.
+    pub(crate) session_secret: Option<String>,
+}
+
+/// HTTPS, alongside or instead of HTTP on `listen`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Tls {
+    pub(crate) listen: String,
+    /// PEM certificate chain.
+    pub(crate) cert: PathBuf,
+    /// PEM private key.
+    pub(crate) key: PathBuf,
+    /// Make a self-signed certificate if there isn't one at `cert` and `key`.
+    pub(crate) self_signed: bool,
+    /// Names the self-signed certificate is for, `localhost` and this
+    /// machine's hostname if it's left empty.
+    pub(crate) hostnames: Vec<String>,
+    /// Send HTTP on `listen` to HTTPS rather than serving both.
+    pub(crate) redirect_http: bool,
+}
+
+impl Default for Tls {
+    fn default() -> Self {
+        Self {
+            listen: "0.0.0.0:8443".to_owned(),
+            cert: "./tls/cert.pem".into(),
+            key: "./tls/key.pem".into(),
+            self_signed: true,
+            hostnames: vec![],
+            redirect_http: true,
+        }
+    }
+}
+
+/// Periodic backups, see `backup`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Backup {
+    pub(crate) dir: PathBuf,
+    /// How often to back up, the first one is at startup.
+    pub(crate) hours: f64,
+    /// How many of the newest backups to keep.
+    pub(crate) keep: usize,
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        Self {
+            dir: "./backups".into(),
+            hours: 24.0,
+            keep: 7,
+        }
+    }
+}
+
+impl Backup {
+    pub(crate) fn interval(&self) -> Duration {
+        Duration::from_secs_f64(self.hours * 60.0 * 60.0)
+    }
+}
+
+/// Where the audio effects listen, see `audio::AudioSource`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Audio {
+    /// `alsa`, `alsa:<device>`, `-` for stdin, or a file to loop.
+    pub(crate) source: String,
+}
+
+/// The broker signals come from, see `mqtt`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[cfg_attr(not(feature = "mqtt"), allow(dead_code))]
+pub(crate) struct Mqtt {
+    pub(crate) host: String,
+    pub(crate) port: u16,
+}
+
+impl Default for Mqtt {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_owned(),
+            port: 1883,
+        }
+    }
+}
+
+/// The order a ws2811 strip takes its colors in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ColorOrder {
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+/// Flags that override the config file.
+#[derive(Debug, Args)]
+pub(crate) struct Overrides {
+    /// TOML config file, defaults to `raspylights.toml` if there is one.
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+    /// Address to serve the web UI on, like `0.0.0.0:8000`.
+    #[arg(long, global = true)]
+    listen: Option<String>,
+    /// The LMDB database directory.
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+    /// Directory with the built frontend.
+    #[arg(long, global = true)]
+    assets: Option<PathBuf>,
+    /// Don't daemonize.
+    #[arg(long, global = true)]
+    foreground: bool,
+    /// off, error, warn, info, debug or trace.
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+}
+
+impl Config {
+    /// The config file, if any, with `overrides` on top, checked over.
+    pub(crate) fn load(overrides: &Overrides) -> Result<Self> {
+        let path = overrides.config.clone().or_else(|| {
+            let path = PathBuf::from(DEFAULT_FILE);
+            path.exists().then_some(path)
+        });
+        let mut config = match path {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+        if let Some(listen) = &overrides.listen {
+            config.listen = listen.clone();
+        }
+        if let Some(db) = &overrides.db {
+            config.db = db.clone();
+        }
+        if let Some(assets) = &overrides.assets {
+            config.assets = assets.clone();
+        }
+        config.foreground |= overrides.foreground;
+        if let Some(log_level) = &overrides.log_level {
+            config.log_level = log_level.clone();
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Bad config file {}", path.display()))
+    }
+
+    /// Report everything wrong at once, rather than one thing per restart.
+    fn validate(&self) -> Result<()> {
+        let mut problems = vec![];
+        if let Err(e) = self.listen.parse::<SocketAddr>() {
+            problems.push(format!("listen {:?}: {}", self.listen, e));
+        }
+        if LevelFilter::from_str(&self.log_level).is_err() {
+            problems.push(format!(
+                "log_level {:?}: should be off, error, warn, info, debug or trace",
+                self.log_level
+            ));
+        }
+        if self.assets.exists() && !self.assets.join("index.html").exists() {
+            problems.push(format!(
+                "assets {}: there's no index.html in it",
+                self.assets.display()
+            ));
+        }
+        let hardware = &self.hardware;
+        if !PINS.contains(&hardware.pin) {
+            problems.push(format!(
+                "hardware.pin {}: the strip has to be on one of GPIO {:?}",
+                hardware.pin, PINS
+            ));
+        }
+        if !DMA_CHANNELS.contains(&hardware.dma) {
+            problems.push(format!(
+                "hardware.dma {}: use one of {:?}",
+                hardware.dma, DMA_CHANNELS
+            ));
+        }
+        if !(400_000..=800_000).contains(&hardware.frequency) {
+            problems.push(format!(
+                "hardware.frequency {}: ws281x strips run at 400000 to 800000 Hz",
+                hardware.frequency
+            ));
+        }
+        let auth = &self.auth;
+        let empty = auth
+            .password
+            .iter()
+            .chain(&auth.read_only_password)
+            .chain(&auth.tokens)
+            .chain(&auth.read_only_tokens)
+            .any(String::is_empty);
+        if empty {
+            problems.push("auth: passwords and tokens can't be empty".to_owned());
+        }
+        if let Some(secret) = &auth.session_secret {
+            if secret.len() < 32 {
+                problems.push(format!(
+                    "auth.session_secret: has to be at least 32 bytes, not {}",
+                    secret.len()
+                ));
+            }
+        }
+        if let Some(tls) = &self.tls {
+            match tls.listen.parse::<SocketAddr>() {
+                Ok(addr) if self.listen.parse::<SocketAddr>().ok() == Some(addr) => {
+                    problems.push(format!(
+                        "tls.listen {:?}: has to be different from listen",
+                        tls.listen
+                    ));
+                }
+                Ok(_) => {}
+                Err(e) => problems.push(format!("tls.listen {:?}: {}", tls.listen, e)),
+            }
+        }
+        if let Some(backup) = &self.backup {
+            // Anything over a few years is as good as never, and too big for
+            // a `Duration`.
+            if !(backup.hours > 0.0 && backup.hours <= 100_000.0) {
+                problems.push(format!(
+                    "backup.hours {}: has to be more than 0 and at most 100000",
+                    backup.hours
+                ));
+            }
+            if backup.keep == 0 {
+                problems.push("backup.keep: has to keep at least 1 backup".to_owned());
+            }
+        }
+        if let Some(audio) = &self.audio {
+            if audio.source.is_empty() {
+                problems.push("audio.source: say where to listen".to_owned());
+            }
+        }
+        if let Some(mqtt) = &self.mqtt {
+            if mqtt.host.is_empty() {
+                problems.push("mqtt.host: say which broker to use".to_owned());
+            }
+            if !cfg!(feature = "mqtt") {
+                problems.push("mqtt: this build doesn't have the mqtt feature".to_owned());
+            }
+        }
+        for entry in &self.schedule {
+            if entry.signal.is_empty() {
+                problems.push(format!("schedule at {}: say which signal to set", entry.at));
+            }
+        }
+        if !problems.is_empty() {
+            bail!("Bad config:\n  {}", problems.join("\n  "));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn log_level(&self) -> LevelFilter {
+        LevelFilter::from_str(&self.log_level).expect("validated")
+    }
+}
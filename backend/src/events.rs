@@ -0,0 +1,64 @@
+//! Changes to the strip's state, pushed to every open web UI as server-sent
+//! events.
+//!
+//! `/events` starts each stream with how things are now, then sends
+//! `details`, `power` and `presets` events, each JSON, as they change.
+
+use std::sync::Mutex;
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use lights::details::Details;
+use serde_json::json;
+
+/// How many events a stream can fall behind by before it's dropped. Its
+/// browser reconnects and starts again from how things are now.
+const BACKLOG: usize = 32;
+
+static SUBSCRIBERS: Mutex<Vec<Sender<Event>>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone)]
+pub(crate) enum Event {
+    /// The details `render_main` is showing.
+    Details(Box<Details>),
+    Power(bool),
+    /// The names of the saved presets.
+    Presets(Vec<String>),
+}
+
+impl Event {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Event::Details(_) => "details",
+            Event::Power(_) => "power",
+            Event::Presets(_) => "presets",
+        }
+    }
+
+    pub(crate) fn data(&self) -> String {
+        match self {
+            Event::Details(details) => json!(details),
+            Event::Power(active) => json!({ "active": active }),
+            Event::Presets(names) => json!(names),
+        }
+        .to_string()
+    }
+}
+
+/// Send `event` to every stream.
+pub(crate) fn publish(event: Event) {
+    let mut subscribers = SUBSCRIBERS.lock().expect("not poisoned");
+    subscribers.retain(|subscriber| match subscriber.try_send(event.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            log::warn!("Dropping an event stream that fell behind");
+            false
+        }
+        Err(TrySendError::Closed(_)) => false,
+    });
+}
+
+pub(crate) fn subscribe() -> Receiver<Event> {
+    let (sender, receiver) = channel::bounded(BACKLOG);
+    SUBSCRIBERS.lock().expect("not poisoned").push(sender);
+    receiver
+}
@@ -0,0 +1,234 @@
+//! Turn an effect into an image, for sharing presets where there's no strip.
+//!
+//! The effect is run offline on a made up clock, a frame every `1 / fps`
+//! seconds, so a minute long export takes however long rendering takes rather
+//! than a minute. Frames can come out as:
+//!
+//! * `strip`, a PNG with the strip as each row, top to bottom over time,
+//! * `gif`, an animated GIF,
+//! * `apng`, an animated PNG, which keeps the exact colors GIF can't.
+
+use std::{fmt, io::Write, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, TimeZone, Utc};
+use lights::effects::EffectType;
+use palette::LinSrgb;
+use serde::{Deserialize, Serialize};
+
+/// Longest export we'll render, to keep requests from tying up the backend.
+const MAX_FRAMES: usize = 3000;
+
+/// Most LEDs in an exported strip.
+const MAX_LENGTH: usize = 5000;
+
+/// Most image pixels each LED can be drawn as, each way.
+const MAX_SCALE: u32 = 32;
+
+/// Most raw RGB an export can come to across all its frames, since `/export`
+/// is open to read-only users.
+const MAX_RGB_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Strip,
+    #[default]
+    Gif,
+    Apng,
+}
+
+impl Format {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Format::Strip | Format::Apng => "image/png",
+            Format::Gif => "image/gif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Strip | Format::Apng => "png",
+            Format::Gif => "gif",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "strip" => Self::Strip,
+            "gif" => Self::Gif,
+            "apng" => Self::Apng,
+            _ => bail!("Unknown export format {:?}, try strip, gif or apng", s),
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Strip => "strip",
+            Format::Gif => "gif",
+            Format::Apng => "apng",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Export {
+    pub effect: EffectType,
+    /// Pixels in the strip.
+    pub length: usize,
+    pub seconds: f64,
+    pub fps: u32,
+    pub format: Format,
+    /// How many image pixels wide (and tall) each LED is drawn.
+    pub scale: u32,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self {
+            effect: Default::default(),
+            length: 100,
+            seconds: 5.0,
+            fps: 25,
+            format: Default::default(),
+            scale: 4,
+        }
+    }
+}
+
+impl Export {
+    /// Render and encode the whole export into `out`.
+    pub fn write(&self, out: impl Write) -> Result<()> {
+        let frames = self.frames()?;
+        match self.format {
+            Format::Strip => self.write_strip(out, &frames),
+            Format::Gif => self.write_gif(out, &frames),
+            Format::Apng => self.write_apng(out, &frames),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Run the effect, keeping the strip every frame.
+    fn frames(&self) -> Result<Vec<Vec<LinSrgb<u8>>>> {
+        if self.length == 0 || self.fps == 0 || self.scale == 0 {
+            bail!("Length, fps and scale all need to be more than 0");
+        }
+        if self.length > MAX_LENGTH || self.scale > MAX_SCALE {
+            bail!(
+                "Exports can be at most {} long with a scale of at most {}",
+                MAX_LENGTH,
+                MAX_SCALE
+            );
+        }
+        let count = (self.seconds * self.fps as f64).ceil() as usize;
+        if count == 0 || count > MAX_FRAMES {
+            bail!("Exports need between 1 and {} frames", MAX_FRAMES);
+        }
+        let scale = self.scale as usize;
+        let bytes = count
+            .checked_mul(self.length)
+            .and_then(|n| n.checked_mul(scale * scale * 3))
+            .filter(|&n| n <= MAX_RGB_BYTES);
+        if bytes.is_none() {
+            bail!(
+                "That export is too big, make it shorter, smaller or lower fps ({} MiB at most)",
+                MAX_RGB_BYTES / 1024 / 1024
+            );
+        }
+        let mut effect = self.effect.clone().try_into_inner()?;
+        // A fixed start so exporting the same effect twice gives the same image.
+        let start = Utc.timestamp_opt(0, 0).unwrap();
+        let frame = Duration::microseconds(1_000_000 / self.fps as i64);
+        let mut pixels = vec![LinSrgb::new(0, 0, 0); self.length];
+        (0..count)
+            .map(|i| {
+                pixels.fill(LinSrgb::new(0, 0, 0));
+                effect.render(&mut pixels, start + frame * i as i32)?;
+                Ok(pixels.clone())
+            })
+            .collect()
+    }
+
+    /// The image width, the strip drawn at `scale`.
+    fn width(&self) -> Result<u32> {
+        u32::try_from(self.length)
+            .ok()
+            .and_then(|length| length.checked_mul(self.scale))
+            .ok_or_else(|| anyhow!("Too wide for an image"))
+    }
+
+    /// One frame as RGB bytes, each pixel a `scale` sized square.
+    fn rgb_rows(&self, pixels: &[LinSrgb<u8>]) -> Vec<u8> {
+        let scale = self.scale as usize;
+        let row: Vec<u8> = pixels
+            .iter()
+            .flat_map(|p| {
+                let (r, g, b) = p.into_components();
+                [r, g, b].repeat(scale)
+            })
+            .collect();
+        row.repeat(scale)
+    }
+
+    fn write_strip(&self, out: impl Write, frames: &[Vec<LinSrgb<u8>>]) -> Result<()> {
+        let width = self.width()?;
+        let height = u32::try_from(frames.len())
+            .ok()
+            .and_then(|count| count.checked_mul(self.scale))
+            .ok_or_else(|| anyhow!("Too tall for an image"))?;
+        let mut encoder = png::Encoder::new(out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        let data: Vec<u8> = frames.iter().flat_map(|f| self.rgb_rows(f)).collect();
+        writer.write_image_data(&data)?;
+        Ok(writer.finish()?)
+    }
+
+    fn write_gif(&self, out: impl Write, frames: &[Vec<LinSrgb<u8>>]) -> Result<()> {
+        let width: u16 = self
+            .width()?
+            .try_into()
+            .map_err(|_| anyhow!("Too wide for a GIF"))?;
+        let height: u16 = self
+            .scale
+            .try_into()
+            .map_err(|_| anyhow!("Too tall for a GIF"))?;
+        let mut encoder = gif::Encoder::new(out, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        // GIF delays are in hundredths of a second.
+        let delay = (100 / self.fps).max(1) as u16;
+        for pixels in frames {
+            let mut frame = gif::Frame::from_rgb(width, height, &self.rgb_rows(pixels));
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    fn write_apng(&self, out: impl Write, frames: &[Vec<LinSrgb<u8>>]) -> Result<()> {
+        let width = self.width()?;
+        let mut encoder = png::Encoder::new(out, width, self.scale);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames.len() as u32, 0)?;
+        encoder.set_frame_delay(1, self.fps.try_into().unwrap_or(u16::MAX))?;
+        let mut writer = encoder.write_header()?;
+        for pixels in frames {
+            writer.write_image_data(&self.rgb_rows(pixels))?;
+        }
+        Ok(writer.finish()?)
+    }
+}
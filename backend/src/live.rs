@@ -0,0 +1,71 @@
+//! What the strip is showing, streamed to the web UI as server-sent events.
+//!
+//! The render loop publishes every frame to `Frames`, and each `/live`
+//! stream sends the newest one at most `fps` times a second as a `frame`
+//! event, the pixels as hex RGB.
+
+use std::{sync::Mutex, time::Duration};
+
+use async_std::task;
+use palette::LinSrgb;
+use tide::sse::Sender;
+
+/// The most frames a second a stream can ask for.
+const MAX_FPS: u32 = 60;
+
+pub(crate) const DEFAULT_FPS: u32 = 20;
+
+#[derive(Debug, Default)]
+pub(crate) struct Frames {
+    latest: Mutex<Frame>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Frame {
+    /// Counts up each frame, so streams know when there's a new one.
+    seq: u64,
+    /// Kept as they are, most frames are never streamed so encoding is left
+    /// to the streams.
+    pixels: Vec<LinSrgb<u8>>,
+}
+
+impl Frame {
+    fn hex(&self) -> String {
+        self.pixels
+            .iter()
+            .map(|pixel| {
+                let (r, g, b) = pixel.into_components();
+                format!("{:02x}{:02x}{:02x}", r, g, b)
+            })
+            .collect()
+    }
+}
+
+impl Frames {
+    pub(crate) fn publish(&self, pixels: &[LinSrgb<u8>]) {
+        let mut latest = self.latest.lock().expect("not poisoned");
+        latest.seq += 1;
+        latest.pixels.clear();
+        latest.pixels.extend_from_slice(pixels);
+    }
+
+    fn latest(&self) -> Frame {
+        self.latest.lock().expect("not poisoned").clone()
+    }
+}
+
+/// Send frames to `sender` until the other end goes away.
+pub(crate) async fn stream(frames: &Frames, fps: u32, sender: Sender) -> tide::Result<()> {
+    let interval = Duration::from_secs_f64(1.0 / fps.clamp(1, MAX_FPS) as f64);
+    let mut seq = 0;
+    loop {
+        let frame = frames.latest();
+        if frame.seq != seq {
+            seq = frame.seq;
+            if sender.send("frame", frame.hex(), None).await.is_err() {
+                return Ok(());
+            }
+        }
+        task::sleep(interval).await;
+    }
+}
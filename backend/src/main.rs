@@ -1,12 +1,27 @@
 #![feature(negative_impls)]
 
+mod assets;
+mod audio;
+mod auth;
+mod backup;
+mod config;
+mod events;
+mod export;
+mod live;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod presets;
+mod schedule;
 mod storage;
 mod strip;
+mod tls;
 
 #[cfg(target_arch = "arm")]
 use std::fs::File;
 
 use std::{
+    fs, io,
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -22,16 +37,34 @@ use async_std::{
     task,
 };
 use chrono::Utc;
+use clap::{Parser, Subcommand};
 #[cfg(target_arch = "arm")]
 use daemonize::Daemonize;
 #[cfg(target_arch = "arm")]
 use homedir::get_my_home;
-use lights::{details::Details, effects::RuneScript, error::Error};
+use lights::{
+    details::Details,
+    effects::{self, EffectType, ParamValues, RuneScript, SourceCode},
+    error::Error,
+    palettes::{self, Palette},
+    signals::{self, Signal},
+};
+use serde::Deserialize;
 use serde_json::Value;
 use signal_hook::consts;
-use tide::{http::mime, prelude::*, Request, Response};
+use tide::{http::mime, listener::ConcurrentListener, prelude::*, Request, Response};
 
-use crate::{storage::Storage, strip::LedStrip};
+use crate::{
+    audio::AudioSource,
+    backup::Snapshot,
+    config::{Config, Hardware, Overrides},
+    events::Event,
+    export::{Export, Format},
+    live::Frames,
+    presets::Conflict,
+    storage::Storage,
+    strip::LedStrip,
+};
 
 async fn render_main(
     receiver: Receiver<Details>,
@@ -39,8 +72,10 @@ async fn render_main(
     power: Arc<AtomicBool>,
     term: Arc<AtomicBool>,
     storage: Storage,
+    hardware: Hardware,
+    frames: Arc<Frames>,
 ) -> Result<()> {
-    let mut strip = LedStrip::new(details.read().await.clone())?;
+    let mut strip = LedStrip::new(details.read().await.clone(), hardware)?;
     strip.set_effect(details.read().await.effect.clone())?;
 
     // let script = RuneScript::default();
@@ -61,18 +96,35 @@ async fn render_main(
             strip.set_brightness(deets.brightness)?;
             *details.write().await = deets.clone();
 
-            storage.store(&deets.name, deets).await.map_err(|_| Error::HeedError)?;
-            storage.store("__main__", deets).await.map_err(|_| Error::HeedError)?;
+            if storage::is_reserved(&deets.name) {
+                log::warn!("Not saving a preset as reserved name {:?}", deets.name);
+            } else {
+                storage
+                    .store(&deets.name, deets.clone())
+                    .await
+                    .map_err(|_| Error::HeedError)?;
+            }
+            storage
+                .store_main(deets.clone())
+                .await
+                .map_err(|_| Error::HeedError)?;
+            events::publish(Event::Details(Box::new(deets)));
+            publish_presets(&storage);
         }
 
         if !power.load(Ordering::Relaxed) {
             // Power is off, lets render black every 100 ms
             task::sleep(std::time::Duration::from_millis(100)).await;
             strip.render()?;
+            frames.publish(strip.pixels());
             continue;
         }
-        let d = strip.update(start)?;
+        let d = strip.update(start);
         strip.render()?;
+        frames.publish(strip.pixels());
+        for line in effects::drain_script_output().lines() {
+            log::info!("script: {}", line);
+        }
 
         task::sleep(std::time::Duration::from_millis(
             (d - start.signed_duration_since(Utc::now()))
@@ -93,6 +145,8 @@ struct State {
     details: Arc<RwLock<Details>>,
     sender: Sender<Details>,
     power: Arc<AtomicBool>,
+    storage: Storage,
+    frames: Arc<Frames>,
 }
 
 async fn get_details(req: Request<State>) -> tide::Result {
@@ -105,6 +159,22 @@ async fn get_details(req: Request<State>) -> tide::Result {
 
 async fn post_details(mut req: Request<State>) -> tide::Result {
     let details: Details = req.body_json().await?;
+    if storage::is_reserved(&details.name) {
+        let resp = Response::builder(400)
+            .body(json!({ "error": format!("`{}` is a reserved name", details.name) }))
+            .content_type(mime::JSON)
+            .build();
+        return Ok(resp.into());
+    }
+    if let EffectType::WasmPlugin(name) = &details.effect {
+        if !effects::plugin_names().contains(name) {
+            let resp = Response::builder(400)
+                .body(json!({ "error": format!("No plugin named `{}`", name) }))
+                .content_type(mime::JSON)
+                .build();
+            return Ok(resp.into());
+        }
+    }
     let state = req.state();
     state.sender.send(details.clone()).await?;
     let resp = Response::builder(200)
@@ -130,6 +200,7 @@ async fn post_power(mut req: Request<State>) -> tide::Result {
     let json: Value = req.body_json().await?;
     let power: bool = json.get("active").and_then(Value::as_bool).unwrap_or(false);
     req.state().power.store(power, Ordering::Relaxed);
+    events::publish(Event::Power(power));
     let resp = Response::builder(200)
         .body(json!({ "active": power }))
         .content_type(mime::JSON)
@@ -137,11 +208,438 @@ async fn post_power(mut req: Request<State>) -> tide::Result {
     Ok(resp.into())
 }
 
+#[derive(Debug, Deserialize)]
+struct ScriptBody {
+    source: String,
+}
+
+async fn get_scripts(_req: Request<State>) -> tide::Result {
+    let resp = Response::builder(200)
+        .body(json!(effects::library_names()))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn get_script(req: Request<State>) -> tide::Result {
+    let name = req.param("name")?;
+    let resp = match effects::library_module(name) {
+        Some(source) => Response::builder(200)
+            .body(json!({ "name": name, "source": source }))
+            .content_type(mime::JSON)
+            .build(),
+        None => Response::new(404),
+    };
+    Ok(resp.into())
+}
+
+async fn post_script(mut req: Request<State>) -> tide::Result {
+    let ScriptBody { source } = req.body_json().await?;
+    let name = req.param("name")?.to_owned();
+    req.state()
+        .storage
+        .store_script(name.clone(), source.clone())
+        .await
+        .map_err(|_| Error::HeedError)?;
+    effects::set_library_module(name.clone(), source.clone());
+    let resp = Response::builder(200)
+        .body(json!({ "name": name, "source": source }))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn delete_script(req: Request<State>) -> tide::Result {
+    let name = req.param("name")?.to_owned();
+    let deleted = req
+        .state()
+        .storage
+        .delete_script(name.clone())
+        .await
+        .map_err(|_| Error::HeedError)?;
+    effects::remove_library_module(&name);
+    Ok(Response::new(if deleted { 200 } else { 404 }))
+}
+
+async fn get_plugins(_req: Request<State>) -> tide::Result {
+    let resp = Response::builder(200)
+        .body(json!(effects::plugin_names()))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn post_plugin(mut req: Request<State>) -> tide::Result {
+    let bytes = req.body_bytes().await?;
+    let name = req.param("name")?.to_owned();
+    // This compiles the module, so it doubles as validation.
+    if let Err(e) = effects::set_plugin_module(name.clone(), &bytes) {
+        let resp = Response::builder(400)
+            .body(json!({ "error": e.to_string() }))
+            .content_type(mime::JSON)
+            .build();
+        return Ok(resp.into());
+    }
+    req.state()
+        .storage
+        .store_plugin(name.clone(), bytes)
+        .await
+        .map_err(|_| Error::HeedError)?;
+    let resp = Response::builder(200)
+        .body(json!({ "name": name }))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn delete_plugin(req: Request<State>) -> tide::Result {
+    let name = req.param("name")?.to_owned();
+    let deleted = req
+        .state()
+        .storage
+        .delete_plugin(name.clone())
+        .await
+        .map_err(|_| Error::HeedError)?;
+    effects::remove_plugin_module(&name);
+    Ok(Response::new(if deleted { 200 } else { 404 }))
+}
+
+async fn get_presets(req: Request<State>) -> tide::Result {
+    let names = req.state().storage.preset_names()?;
+    let resp = Response::builder(200)
+        .body(json!(names))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+#[derive(Debug, Deserialize)]
+struct LiveQuery {
+    #[serde(default = "default_fps")]
+    fps: u32,
+}
+
+fn default_fps() -> u32 {
+    live::DEFAULT_FPS
+}
+
+/// What the strip is showing, as it shows it.
+async fn get_live(req: Request<State>, sender: tide::sse::Sender) -> tide::Result<()> {
+    let LiveQuery { fps } = req.query()?;
+    live::stream(&req.state().frames, fps, sender).await
+}
+
+/// How things are now, then whatever changes, see `events`.
+async fn get_events(req: Request<State>, sender: tide::sse::Sender) -> tide::Result<()> {
+    // Subscribe first so nothing is missed between the two.
+    let events = events::subscribe();
+    let state = req.state();
+    let now = [
+        Event::Details(Box::new(state.details.read().await.clone())),
+        Event::Power(state.power.load(Ordering::Relaxed)),
+        Event::Presets(state.storage.preset_names()?),
+    ];
+    for event in now {
+        sender.send(event.name(), event.data(), None).await?;
+    }
+    while let Ok(event) = events.recv().await {
+        if sender.send(event.name(), event.data(), None).await.is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Tell everyone the saved presets, after they might have changed.
+fn publish_presets(storage: &Storage) {
+    match storage.preset_names() {
+        Ok(names) => events::publish(Event::Presets(names)),
+        Err(e) => log::error!("Couldn't list presets: {}", e),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BundleQuery {
+    format: presets::Format,
+    conflict: Conflict,
+}
+
+/// Every preset as a bundle to download.
+async fn get_presets_export(req: Request<State>) -> tide::Result {
+    let BundleQuery { format, .. } = req.query()?;
+    bundle_response(&req.state().storage, None, format, "presets")
+}
+
+async fn get_preset_export(req: Request<State>) -> tide::Result {
+    let BundleQuery { format, .. } = req.query()?;
+    let name = req.param("name")?.to_owned();
+    bundle_response(&req.state().storage, Some(&[name.clone()]), format, &name)
+}
+
+fn bundle_response(
+    storage: &Storage,
+    names: Option<&[String]>,
+    format: presets::Format,
+    file: &str,
+) -> tide::Result {
+    let bytes = presets::export(storage, names, format)?;
+    let resp = Response::builder(200)
+        .body(bytes)
+        .content_type(format.mime())
+        .header(
+            "Content-Disposition",
+            content_disposition(&format!("{}.{}", file, format.extension())),
+        )
+        .build();
+    Ok(resp.into())
+}
+
+/// An attachment called `file`. Preset names can be anything, so there's a
+/// plain ASCII name for old browsers and the real one percent-encoded.
+fn content_disposition(file: &str) -> String {
+    let ascii: String = file
+        .chars()
+        .map(|c| match c {
+            ' ' | '-' | '.' | '(' | ')' => c,
+            c if c.is_ascii_alphanumeric() => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = file
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+        ascii, encoded
+    )
+}
+
+/// Import a bundle, JSON or MsgPack going by the content type.
+async fn post_presets_import(mut req: Request<State>) -> tide::Result {
+    let BundleQuery { conflict, .. } = req.query()?;
+    let format = presets::Format::from_mime(
+        req.content_type()
+            .as_ref()
+            .map_or("", |mime| mime.essence()),
+    );
+    let bytes = req.body_bytes().await?;
+    let mut storage = req.state().storage.clone();
+    let resp = match presets::import(&mut storage, &bytes, format, conflict).await {
+        Ok(report) => Response::builder(200)
+            .body(json!(report))
+            .content_type(mime::JSON)
+            .build(),
+        Err(e) => Response::builder(400)
+            .body(json!({ "error": e.to_string() }))
+            .content_type(mime::JSON)
+            .build(),
+    };
+    // Some may have been imported before an error.
+    publish_presets(&storage);
+    Ok(resp.into())
+}
+
+/// Everything in storage, read in one transaction.
+async fn get_backup(req: Request<State>) -> tide::Result {
+    let bytes = req.state().storage.snapshot()?.to_bytes()?;
+    let file = format!("raspylights-{}.msgpack", Utc::now().format("%Y%m%dT%H%M%S"));
+    let resp = Response::builder(200)
+        .body(bytes)
+        .content_type("application/msgpack")
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file),
+        )
+        .build();
+    Ok(resp.into())
+}
+
+/// Replace the whole store with a backup, if everything in it loads.
+async fn post_restore(mut req: Request<State>) -> tide::Result {
+    let bytes = req.body_bytes().await?;
+    let problems = match Snapshot::from_bytes(&bytes) {
+        Ok(snapshot) => {
+            let problems = snapshot.problems();
+            if problems.is_empty() {
+                let storage = &req.state().storage;
+                storage.restore(&snapshot)?;
+                for (name, e) in storage.migrate()? {
+                    log::error!("Couldn't migrate restored details {:?}: {}", name, e);
+                }
+                reload_library(storage)?;
+                publish_presets(storage);
+                let details = req.state().storage.clone().load(storage::MAIN)?;
+                if let Some(details) = details {
+                    req.state().sender.send(details).await?;
+                }
+            }
+            problems
+        }
+        Err(e) => vec![e.to_string()],
+    };
+    let resp = if problems.is_empty() {
+        Response::new(200)
+    } else {
+        Response::builder(400)
+            .body(json!({ "error": "Backup didn't validate", "problems": problems }))
+            .content_type(mime::JSON)
+            .build()
+    };
+    Ok(resp.into())
+}
+
+/// The user palettes, the built-in ones are in every copy of `lights`.
+async fn get_palettes(_req: Request<State>) -> tide::Result {
+    let resp = Response::builder(200)
+        .body(json!(palettes::user_palettes()))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn get_palette(req: Request<State>) -> tide::Result {
+    let resp = match palettes::palette(req.param("name")?) {
+        Some(palette) => Response::builder(200)
+            .body(json!(palette))
+            .content_type(mime::JSON)
+            .build(),
+        None => Response::new(404),
+    };
+    Ok(resp.into())
+}
+
+async fn post_palette(mut req: Request<State>) -> tide::Result {
+    let palette: Palette = req.body_json().await?;
+    let name = req.param("name")?.to_owned();
+    if palettes::is_builtin(&name) {
+        let resp = Response::builder(400)
+            .body(json!({ "error": format!("{} is a built-in palette", name) }))
+            .content_type(mime::JSON)
+            .build();
+        return Ok(resp.into());
+    }
+    req.state()
+        .storage
+        .store_palette(name.clone(), palette.clone())
+        .await
+        .map_err(|_| Error::HeedError)?;
+    palettes::set_user_palette(name, palette.clone());
+    let resp = Response::builder(200)
+        .body(json!(palette))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn delete_palette(req: Request<State>) -> tide::Result {
+    let name = req.param("name")?.to_owned();
+    let deleted = req
+        .state()
+        .storage
+        .delete_palette(name.clone())
+        .await
+        .map_err(|_| Error::HeedError)?;
+    palettes::remove_user_palette(&name);
+    Ok(Response::new(if deleted { 200 } else { 404 }))
+}
+
+async fn get_signals(_req: Request<State>) -> tide::Result {
+    let resp = Response::builder(200)
+        .body(json!(signals::signals()))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn get_signal(req: Request<State>) -> tide::Result {
+    let resp = match signals::signal(req.param("name")?) {
+        Some(state) => Response::builder(200)
+            .body(json!(state))
+            .content_type(mime::JSON)
+            .build(),
+        None => Response::new(404),
+    };
+    Ok(resp.into())
+}
+
+async fn post_signal(mut req: Request<State>) -> tide::Result {
+    let value: Signal = req.body_json().await?;
+    let name = req.param("name")?;
+    signals::set_signal(name, value, Utc::now());
+    let resp = Response::builder(200)
+        .body(json!(signals::signal(name)))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+/// Sets the signal to `true`, for things like doorbells that only ever fire.
+async fn post_trigger(req: Request<State>) -> tide::Result {
+    let name = req.param("name")?;
+    signals::set_signal(name, Signal::Bool(true), Utc::now());
+    let resp = Response::builder(200)
+        .body(json!(signals::signal(name)))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn delete_signal(req: Request<State>) -> tide::Result {
+    let removed = signals::remove_signal(req.param("name")?);
+    Ok(Response::new(if removed.is_some() { 200 } else { 404 }))
+}
+
+#[derive(Debug, Deserialize)]
+struct CompileBody {
+    source: String,
+    #[serde(default)]
+    params: ParamValues,
+}
+
+async fn post_compile(mut req: Request<State>) -> tide::Result {
+    let CompileBody { source, params } = req.body_json().await?;
+    // Checking a script runs its `init`, so keep it off the executor.
+    let report =
+        task::spawn_blocking(move || RuneScript::check(SourceCode::Source(source), params)).await;
+    let resp = Response::builder(200)
+        .body(json!(report))
+        .content_type(mime::JSON)
+        .build();
+    Ok(resp.into())
+}
+
+async fn post_export(mut req: Request<State>) -> tide::Result {
+    let export: Export = req.body_json().await?;
+    let format = export.format;
+    // Rendering a few thousand frames and quantizing a GIF takes a while.
+    let resp = match task::spawn_blocking(move || export.to_bytes()).await {
+        Ok(bytes) => Response::builder(200)
+            .body(bytes)
+            .content_type(format.mime())
+            .build(),
+        Err(e) => Response::builder(400)
+            .body(json!({ "error": e.to_string() }))
+            .content_type(mime::JSON)
+            .build(),
+    };
+    Ok(resp.into())
+}
+
 async fn web_main(
     sender: Sender<Details>,
     details: Arc<RwLock<Details>>,
     term: Arc<AtomicBool>,
     power: Arc<AtomicBool>,
+    storage: Storage,
+    config: Config,
+    frames: Arc<Frames>,
 ) -> Result<()> {
     let die = async {
         loop {
@@ -157,38 +655,187 @@ async fn web_main(
         details,
         sender,
         power,
+        storage,
+        frames,
     });
-    app.at("/").serve_file("./frontend/index.html")?;
     app.at("/details").get(get_details);
     app.at("/details").post(post_details);
     app.at("/power").get(get_power);
     app.at("/power").post(post_power);
-    app.at("/pkg").serve_dir("./frontend/pkg/")?;
-    app.at("/mdc").serve_dir("./frontend/static/mdc/")?;
-    app.at("/style.css")
-        .serve_file("./frontend/static/style.css")?;
-    app.at("/bulma-list.css")
-        .serve_file("./frontend/static/bulma-list.css")?;
-    app.listen("0.0.0.0:8000").race(die).await?;
+    app.at("/live").get(tide::sse::endpoint(get_live));
+    app.at("/events").get(tide::sse::endpoint(get_events));
+    app.at("/scripts").get(get_scripts);
+    app.at("/scripts/:name").get(get_script);
+    app.at("/scripts/:name").post(post_script);
+    app.at("/scripts/:name").delete(delete_script);
+    app.at("/compile").post(post_compile);
+    app.at("/export").post(post_export);
+    app.at("/plugins").get(get_plugins);
+    app.at("/plugins/:name").post(post_plugin);
+    app.at("/plugins/:name").delete(delete_plugin);
+    app.at("/backup").get(get_backup);
+    app.at("/restore").post(post_restore);
+    app.at("/presets").get(get_presets);
+    app.at("/presets/export").get(get_presets_export);
+    app.at("/presets/import").post(post_presets_import);
+    app.at("/presets/:name/export").get(get_preset_export);
+    app.at("/palettes").get(get_palettes);
+    app.at("/palettes/:name").get(get_palette);
+    app.at("/palettes/:name").post(post_palette);
+    app.at("/palettes/:name").delete(delete_palette);
+    app.at("/signals").get(get_signals);
+    app.at("/signals/:name").get(get_signal);
+    app.at("/signals/:name").post(post_signal);
+    app.at("/signals/:name").delete(delete_signal);
+    app.at("/signals/:name/trigger").post(post_trigger);
+    auth::serve(&mut app, config.auth.clone())?;
+    assets::serve(&mut app, &config.assets)?;
+    match &config.tls {
+        None => app.listen(config.listen).race(die).await?,
+        Some(tls) if tls.redirect_http => {
+            let redirect = tls::redirect(tls).listen(config.listen.clone());
+            app.listen(tls::listener(tls)?)
+                .race(redirect)
+                .race(die)
+                .await?
+        }
+        Some(tls) => {
+            let mut listener = ConcurrentListener::new();
+            listener.add(config.listen.clone())?;
+            listener.add(tls::listener(tls)?)?;
+            app.listen(listener).race(die).await?
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Parser)]
+#[command(about = "Drive a strip of LEDs, with a web UI to pick the effect")]
+struct Args {
+    #[command(flatten)]
+    overrides: Overrides,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Render an effect to an image instead of running the strip.
+    Export {
+        /// JSON with the effect, or details like `/details` gives, `-` for
+        /// stdin.
+        effect: PathBuf,
+        /// Where to write the image, defaults to `effect.<format>`.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// strip, gif or apng.
+        #[arg(short, long, default_value_t = Format::Gif)]
+        format: Format,
+        /// Pixels in the strip, defaults to the length in the details.
+        #[arg(short, long)]
+        length: Option<usize>,
+        #[arg(short, long, default_value_t = 5.0)]
+        seconds: f64,
+        #[arg(long, default_value_t = 25)]
+        fps: u32,
+        /// Image pixels per LED.
+        #[arg(long, default_value_t = 4)]
+        scale: u32,
+    },
+}
+
+/// Put the scripts, plugins and palettes from `storage` where effects can
+/// find them.
+fn load_library(storage: &Storage) -> Result<()> {
+    for (name, source) in storage.load_scripts().map_err(|_| Error::HeedError)? {
+        effects::set_library_module(name, source);
+    }
+    for (name, bytes) in storage.load_plugins().map_err(|_| Error::HeedError)? {
+        if let Err(e) = effects::set_plugin_module(name.clone(), &bytes) {
+            log::error!("Couldn't load plugin {}: {}", name, e);
+        }
+    }
+    for (name, palette) in storage.load_palettes().map_err(|_| Error::HeedError)? {
+        palettes::set_user_palette(name, palette);
+    }
+    Ok(())
+}
+
+/// Replace what's in the library with what's in `storage`, after a restore.
+fn reload_library(storage: &Storage) -> Result<()> {
+    for name in effects::library_names() {
+        effects::remove_library_module(&name);
+    }
+    for name in effects::plugin_names() {
+        effects::remove_plugin_module(&name);
+    }
+    for name in palettes::user_palettes().keys() {
+        palettes::remove_user_palette(name);
+    }
+    load_library(storage)
+}
+
+fn export_main(command: Command, config: &Config) -> Result<()> {
+    let Command::Export {
+        effect,
+        output,
+        format,
+        length,
+        seconds,
+        fps,
+        scale,
+    } = command;
+    let (effect, details_length) = Details::read_effect(&effect)?;
+    // Named palettes and library scripts live in the database.
+    let storage = Storage::open(&config.db).map_err(|_| Error::HeedError)?;
+    load_library(&storage)?;
+    let export = Export {
+        effect,
+        length: length
+            .or(details_length)
+            .unwrap_or(Export::default().length),
+        seconds,
+        fps,
+        format,
+        scale,
+    };
+    let output = output.unwrap_or_else(|| format!("effect.{}", format.extension()).into());
+    export.write(io::BufWriter::new(fs::File::create(&output)?))?;
+    log::info!("Wrote {}", output.display());
     Ok(())
 }
 
 fn main() -> Result<()> {
-    tide::log::start();
+    let args = Args::parse();
+    let config = Config::load(&args.overrides)?;
+    tide::log::with_level(config.log_level());
+    if let Some(command) = args.command {
+        return export_main(command, &config);
+    }
 
     #[cfg(target_arch = "arm")]
-    {
+    if !config.foreground {
         let home = get_my_home()?.unwrap();
-        let stdout = home.clone().push("raspylights.out");
-        let stderr = home.clone().push("raspylights.err");
+        let daemon = &config.daemon;
+        let stdout = daemon
+            .stdout
+            .clone()
+            .unwrap_or_else(|| home.join("raspylights.out"));
+        let stderr = daemon
+            .stderr
+            .clone()
+            .unwrap_or_else(|| home.join("raspylights.err"));
 
-        let stdout = File::create(stdout).unwrap();
-        let stderr = File::create(stderr).unwrap();
+        let stdout = File::create(stdout)?;
+        let stderr = File::create(stderr)?;
 
-        let pidfile = home.clone().push("raspylights.pid");
+        let pidfile = daemon
+            .pid_file
+            .clone()
+            .unwrap_or_else(|| home.join("raspylights.pid"));
         let daemonize = Daemonize::new()
             .pid_file(pidfile)
-            .working_directory(home)
+            .working_directory(daemon.working_dir.clone().unwrap_or(home))
             .stdout(stdout)
             .stderr(stderr);
 
@@ -207,28 +854,57 @@ fn main() -> Result<()> {
         signal_hook::flag::register(*sig, Arc::clone(&term))?;
     }
 
-    let mut storage = Storage::open("./db/effects.db").map_err(|_| Error::HeedError)?;
+    if let Some(audio) = &config.audio {
+        audio::spawn_capture(audio.source.parse::<AudioSource>()?, Arc::clone(&term));
+    }
+    #[cfg(feature = "mqtt")]
+    if let Some(broker) = &config.mqtt {
+        mqtt::spawn_listener(broker.clone(), Arc::clone(&term));
+    }
+    if !config.schedule.is_empty() {
+        schedule::spawn_schedule(config.schedule.clone(), Arc::clone(&term));
+    }
+
+    if let Some(tls) = &config.tls {
+        tls::ensure_cert(tls)?;
+    }
+    let mut storage = Storage::open(&config.db).map_err(|_| Error::HeedError)?;
+    load_library(&storage)?;
+    // After the library, Glow migrations look up named palettes.
+    for (name, e) in storage.migrate()? {
+        log::error!("Couldn't migrate details {:?}, leaving them: {}", name, e);
+    }
+    if let Some(backup) = &config.backup {
+        backup::spawn_periodic(storage.clone(), backup.clone(), Arc::clone(&term));
+    }
     let (sender, receiver) = channel::bounded(1);
-    let details = storage
-        .load("__main__")
-        .expect("load failed")
-        .unwrap_or_default();
+    let details = match storage.load(storage::MAIN) {
+        Ok(details) => details.unwrap_or_default(),
+        // Already reported by the migration, and still there to fix by hand.
+        Err(_) => Default::default(),
+    };
     log::info!("Details loaded: {:#?}", details);
     let details = Arc::new(RwLock::new(details));
     let power = Arc::new(AtomicBool::new(true));
     let power2 = Arc::clone(&power);
     let details2 = Arc::clone(&details);
     let term2 = Arc::clone(&term);
+    let storage2 = storage.clone();
+    let hardware = config.hardware.clone();
+    let frames = Arc::new(Frames::default());
+    let frames2 = Arc::clone(&frames);
     let render = thread::spawn(move || {
         task::block_on(async {
-            render_main(receiver, details, power2, term2, storage)
+            render_main(receiver, details, power2, term2, storage, hardware, frames2)
                 .await
                 .unwrap();
         });
     });
     let task = thread::spawn(move || {
-        task::block_on(async { web_main(sender, details2, term, power).await })
-            .expect("block should work")
+        task::block_on(async {
+            web_main(sender, details2, term, power, storage2, config, frames).await
+        })
+        .expect("block should work")
     });
     render.join().expect("Rendering stopped");
     task.join().expect("task completed");
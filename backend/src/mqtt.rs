@@ -0,0 +1,68 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::Utc;
+use lights::signals::{self, Signal};
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS, RecvTimeoutError};
+
+use crate::config::Mqtt;
+
+/// Every message under here sets the signal named by the rest of the topic.
+/// The payload is the same JSON as `POST /signals/:name`, and an empty
+/// payload is a trigger.
+const TOPIC_PREFIX: &str = "lights/signals/";
+
+/// How long to wait for a message before checking whether to stop.
+const POLL: Duration = Duration::from_secs(1);
+
+pub fn spawn_listener(broker: Mqtt, term: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut options = MqttOptions::new("raspylights", broker.host, broker.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        let (client, mut connection) = Client::new(options, 16);
+        if let Err(e) = client.subscribe(format!("{TOPIC_PREFIX}#"), QoS::AtMostOnce) {
+            log::error!("Couldn't subscribe to signals: {}", e);
+            return;
+        }
+
+        while !term.load(Ordering::Relaxed) {
+            let notification = match connection.recv_timeout(POLL) {
+                Ok(notification) => notification,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            match notification {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let Some(name) = publish.topic.strip_prefix(TOPIC_PREFIX) else {
+                        continue;
+                    };
+                    let value = if publish.payload.is_empty() {
+                        Signal::Bool(true)
+                    } else {
+                        match serde_json::from_slice(&publish.payload) {
+                            Ok(value) => value,
+                            Err(e) => {
+                                log::warn!("Bad payload for signal {}: {}", name, e);
+                                continue;
+                            }
+                        }
+                    };
+                    signals::set_signal(name, value, Utc::now());
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // The connection retries on the next poll, don't spin.
+                    log::warn!("MQTT connection error: {}", e);
+                    thread::sleep(Duration::from_secs(5));
+                }
+            }
+        }
+        let _ = client.disconnect();
+    })
+}
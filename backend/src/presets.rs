@@ -0,0 +1,369 @@
+//! Exporting presets to bundles and importing them back, see
+//! `lights::bundle`.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use lights::{
+    bundle::{self, Bundle},
+    details::Details,
+    effects, migrations, palettes,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::storage::{self, Storage};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Format {
+    #[default]
+    Json,
+    MsgPack,
+}
+
+impl Format {
+    pub(crate) fn mime(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::MsgPack => "application/msgpack",
+        }
+    }
+
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::MsgPack => "msgpack",
+        }
+    }
+
+    /// The format of a request body, anything but MsgPack is taken as JSON.
+    pub(crate) fn from_mime(mime: &str) -> Self {
+        match mime {
+            "application/msgpack" | "application/x-msgpack" => Format::MsgPack,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// What to do with a preset, script or palette that already exists with
+/// something else in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Conflict {
+    /// Keep ours.
+    #[default]
+    Skip,
+    /// Take theirs.
+    Overwrite,
+    /// Keep both, with theirs under a new name. Modules loaded with `mod`
+    /// are skipped instead.
+    Rename,
+}
+
+/// What happened to each kind of thing in a bundle.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Report {
+    presets: Outcome,
+    scripts: Outcome,
+    palettes: Outcome,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Outcome {
+    imported: Vec<String>,
+    skipped: Vec<String>,
+    /// From the name in the bundle to the name it was imported as.
+    renamed: BTreeMap<String, String>,
+    failed: BTreeMap<String, String>,
+}
+
+/// The saved presets in `names`, or all of them, as a bundle.
+pub(crate) fn export(
+    storage: &Storage,
+    names: Option<&[String]>,
+    format: Format,
+) -> Result<Vec<u8>> {
+    let presets = storage
+        .load_presets()?
+        .into_iter()
+        .filter(|(name, _)| names.map_or(true, |names| names.contains(name)))
+        .filter_map(|(name, details)| match details {
+            Ok(details) => Some(details),
+            Err(e) => {
+                log::error!("Leaving {:?} out of the bundle: {}", name, e);
+                None
+            }
+        })
+        .collect();
+    let bundle = Bundle::new(presets);
+    Ok(match format {
+        Format::Json => serde_json::to_vec_pretty(&bundle)?,
+        Format::MsgPack => rmp_serde::to_vec_named(&bundle)?,
+    })
+}
+
+pub(crate) async fn import(
+    storage: &mut Storage,
+    bytes: &[u8],
+    format: Format,
+    conflict: Conflict,
+) -> Result<Report> {
+    // Presets are kept as values until they've been migrated.
+    let bundle: Bundle<Value> = match format {
+        Format::Json => serde_json::from_slice(bytes)?,
+        Format::MsgPack => rmp_serde::from_slice(bytes)?,
+    };
+    let mut report = Report::default();
+
+    let presets: Vec<_> = bundle
+        .presets
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let label = value
+                .get("name")
+                .and_then(Value::as_str)
+                .map_or_else(|| format!("#{}", idx), ToOwned::to_owned);
+            (label, decode_preset(value, bundle.version, format))
+        })
+        .collect();
+
+    // A module something loads with `mod` has to keep its name, so when it
+    // clashes it's skipped along with the presets that need it.
+    let declared = bundle::declared_modules(
+        &bundle.scripts,
+        presets
+            .iter()
+            .filter_map(|(_, details)| Some(&details.as_ref().ok()?.effect)),
+    );
+    let mut refused = BTreeSet::new();
+    for (name, source) in &bundle.scripts {
+        let existing = effects::library_module(name);
+        let pinned = conflict == Conflict::Rename && declared.contains(name);
+        let Some(imported) = resolve(
+            name,
+            existing.as_ref() == Some(source),
+            existing.is_some(),
+            if pinned { Conflict::Skip } else { conflict },
+            &mut report.scripts,
+            |n| effects::library_module(n).is_some(),
+            |name, n| format!("{}_{}", name, n),
+        ) else {
+            if pinned {
+                refused.insert(name.clone());
+            }
+            continue;
+        };
+        storage
+            .store_script(imported.clone(), source.clone())
+            .await
+            .map_err(|e| anyhow!("{}", e))?;
+        effects::set_library_module(imported, source.clone());
+    }
+
+    for (name, palette) in bundle.palettes {
+        let existing = palettes::palette(&name);
+        // Built-in palettes can't be overwritten.
+        let conflict = match conflict {
+            Conflict::Overwrite if palettes::is_builtin(&name) => Conflict::Skip,
+            conflict => conflict,
+        };
+        let Some(name) = resolve(
+            &name,
+            existing.as_ref() == Some(&palette),
+            existing.is_some(),
+            conflict,
+            &mut report.palettes,
+            |n| palettes::palette(n).is_some(),
+            numbered,
+        ) else {
+            continue;
+        };
+        storage
+            .store_palette(name.clone(), palette.clone())
+            .await
+            .map_err(|e| anyhow!("{}", e))?;
+        palettes::set_user_palette(name, palette);
+    }
+
+    let mut existing: BTreeMap<String, Details> = storage
+        .load_presets()?
+        .into_iter()
+        .filter_map(|(name, details)| Some((name, details.ok()?)))
+        .collect();
+    for (label, details) in presets {
+        let mut details = match details {
+            Ok(details) => details,
+            Err(e) => {
+                report.presets.failed.insert(label, e.to_string());
+                continue;
+            }
+        };
+        if storage::is_reserved(&details.name) {
+            report
+                .presets
+                .failed
+                .insert(label, format!("`{}` is a reserved name", details.name));
+            continue;
+        }
+        if !bundle::needed_modules(&details.effect, &bundle.scripts).is_disjoint(&refused) {
+            report.presets.skipped.push(details.name);
+            continue;
+        }
+        if let Err(e) = bundle::rename_references(
+            &mut details.effect,
+            &report.scripts.renamed,
+            &report.palettes.renamed,
+        ) {
+            report.presets.failed.insert(label, e.to_string());
+            continue;
+        }
+        let current = existing.get(&details.name);
+        let Some(name) = resolve(
+            &details.name,
+            current == Some(&details),
+            current.is_some(),
+            conflict,
+            &mut report.presets,
+            |n| existing.contains_key(n),
+            numbered,
+        ) else {
+            continue;
+        };
+        details.name = name.clone();
+        storage
+            .store(&name, details.clone())
+            .await
+            .map_err(|e| anyhow!("{}", e))?;
+        existing.insert(name, details);
+    }
+    Ok(report)
+}
+
+/// `name (n)`, for presets and palettes. Scripts are named like Rune
+/// modules, so they get `name_n` instead.
+fn numbered(name: &str, n: usize) -> String {
+    format!("{} ({})", name, n)
+}
+
+/// Work out what name to import `name` as, `None` to not import it at all,
+/// and note it in `outcome`.
+fn resolve(
+    name: &str,
+    same: bool,
+    exists: bool,
+    conflict: Conflict,
+    outcome: &mut Outcome,
+    taken: impl Fn(&str) -> bool,
+    numbered: impl Fn(&str, usize) -> String,
+) -> Option<String> {
+    if !exists || same {
+        outcome.imported.push(name.to_owned());
+        return Some(name.to_owned());
+    }
+    match conflict {
+        Conflict::Skip => {
+            outcome.skipped.push(name.to_owned());
+            None
+        }
+        Conflict::Overwrite => {
+            outcome.imported.push(name.to_owned());
+            Some(name.to_owned())
+        }
+        Conflict::Rename => {
+            let renamed = (2..)
+                .map(|n| numbered(name, n))
+                .find(|n| !taken(n))
+                .expect("some name is free");
+            outcome.renamed.insert(name.to_owned(), renamed.clone());
+            Some(renamed)
+        }
+    }
+}
+
+/// A preset from a bundle written at `version`, brought up to date.
+fn decode_preset(value: Value, version: u32, format: Format) -> Result<Details> {
+    let value = if version > 0 {
+        json!({ "version": version, "details": value })
+    } else {
+        value
+    };
+    let value = migrations::migrate(value)?;
+    Ok(match format {
+        Format::Json => serde_json::from_value(value)?,
+        // MsgPack has its own idea of some things, like unit structs, so
+        // go back through it.
+        Format::MsgPack => rmp_serde::from_slice(&rmp_serde::to_vec_named(&value)?)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lights::effects::{EffectType, ParamValues, SourceCode};
+
+    use super::*;
+
+    fn script(source: SourceCode, name: &str) -> Details {
+        Details {
+            effect: EffectType::RuneScript(source, ParamValues::default()),
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rename_keeps_modules_that_are_loaded_with_mod() {
+        let dir = std::env::temp_dir().join(format!("lights-import-{}", std::process::id()));
+        let mut storage = Storage::open(&dir).unwrap();
+        effects::set_library_module("import_helpers", "pub fn ours() {}");
+        effects::set_library_module("import_show", "pub fn main() {}");
+
+        let show = "mod import_helpers;\npub fn main() {}";
+        let bundle = Bundle {
+            version: migrations::VERSION,
+            presets: vec![
+                script(
+                    SourceCode::Library("import_uses".to_owned()),
+                    "uses helpers",
+                ),
+                script(
+                    SourceCode::Source("mod import_helpers;".to_owned()),
+                    "inline helpers",
+                ),
+                script(SourceCode::Library("import_show".to_owned()), "show"),
+            ],
+            scripts: BTreeMap::from([
+                ("import_helpers".to_owned(), "pub fn theirs() {}".to_owned()),
+                ("import_uses".to_owned(), show.to_owned()),
+                ("import_show".to_owned(), "pub fn main() { }".to_owned()),
+            ]),
+            palettes: BTreeMap::new(),
+        };
+        let bytes = serde_json::to_vec(&bundle).unwrap();
+        let report =
+            async_std::task::block_on(import(&mut storage, &bytes, Format::Json, Conflict::Rename))
+                .unwrap();
+
+        assert_eq!(report.scripts.skipped, ["import_helpers"]);
+        assert_eq!(
+            report.scripts.renamed,
+            BTreeMap::from([("import_show".to_owned(), "import_show_2".to_owned())])
+        );
+        assert_eq!(
+            effects::library_module("import_helpers").as_deref(),
+            Some("pub fn ours() {}")
+        );
+        assert_eq!(report.presets.skipped, ["uses helpers", "inline helpers"]);
+        assert_eq!(report.presets.imported, ["show"]);
+        let show = storage.load("show").unwrap().unwrap();
+        assert_eq!(
+            show.effect,
+            EffectType::RuneScript(
+                SourceCode::Library("import_show_2".to_owned()),
+                ParamValues::default()
+            )
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,39 @@
+//! Signals set at times of day, from the `[[schedule]]` entries in the
+//! config.
+//!
+//! Times are local to the Pi. At startup each scheduled signal is set to what
+//! its latest entry says, so a restart in the night still has "night" on.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{Local, Utc};
+use lights::signals::{self, Scheduled};
+
+/// Set `schedule`'s signals as their times come up until `term` is set.
+pub(crate) fn spawn_schedule(
+    schedule: Vec<Scheduled>,
+    term: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut last = Local::now().time();
+        for entry in signals::current(&schedule, last) {
+            signals::set_signal(&entry.signal, entry.value, Utc::now());
+        }
+        while !term.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_secs(1));
+            let now = Local::now().time();
+            for entry in signals::due(&schedule, last, now) {
+                log::debug!("Scheduled signal {} at {}", entry.signal, entry.at);
+                signals::set_signal(&entry.signal, entry.value, Utc::now());
+            }
+            last = now;
+        }
+    })
+}
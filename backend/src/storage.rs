@@ -1,11 +1,21 @@
 use std::{borrow::Cow, error::Error, path::Path};
 
 use async_std::task;
-use heed::{types::Str, BytesDecode, BytesEncode, Env, EnvOpenOptions};
-use lights::details::Details;
+use heed::{
+    types::{ByteSlice, Str},
+    BytesDecode, BytesEncode, Env, EnvOpenOptions,
+};
+use lights::{
+    details::Details,
+    migrations::{self, Versioned},
+    palettes::Palette,
+};
 use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
 use std::fs;
 
+use crate::backup::Snapshot;
+
 /// Get this working with MsgPack so the types are more stable than bincode.
 struct SerdeMsgPack<T>(std::marker::PhantomData<T>);
 
@@ -35,51 +45,412 @@ unsafe impl<T> Send for SerdeMsgPack<T> {}
 
 unsafe impl<T> Sync for SerdeMsgPack<T> {}
 
+const SCRIPT_DATABASE: &str = "scripts";
+const PLUGIN_DATABASE: &str = "plugins";
+const PALETTE_DATABASE: &str = "palettes";
+
+/// LMDB keeps named databases as entries in the unnamed one, alongside the
+/// details.
+const NAMED_DATABASES: &[&str] = &[SCRIPT_DATABASE, PLUGIN_DATABASE, PALETTE_DATABASE];
+
+/// Where the details on the strip are kept, to show again after a restart.
+pub(crate) const MAIN: &str = "__main__";
+
+/// Names a preset can't have, because something else is kept under them.
+pub(crate) fn is_reserved(name: &str) -> bool {
+    name == MAIN || NAMED_DATABASES.contains(&name)
+}
+
+#[derive(Clone)]
 pub(crate) struct Storage {
     env: Env,
-    effect_database: heed::Database<Str, SerdeMsgPack<Details>>,
+    /// `Versioned` details as MsgPack, by name. They're decoded by hand so
+    /// older versions can be migrated.
+    effect_database: heed::Database<Str, ByteSlice>,
+    /// Rune modules for the script library, by name.
+    script_database: heed::Database<Str, Str>,
+    /// WASM effect plugin modules, by name.
+    plugin_database: heed::Database<Str, ByteSlice>,
+    /// User palettes, by name.
+    palette_database: heed::Database<Str, SerdeMsgPack<Palette>>,
 }
 
 impl Storage {
     pub(crate) fn open(path: impl AsRef<Path>) -> Result<Self, heed::Error> {
         fs::create_dir_all(&path)?;
-        let env = EnvOpenOptions::new().open(path)?;
+        let env = EnvOpenOptions::new().max_dbs(8).open(path)?;
         let effect_database = env.create_database(None)?;
+        let script_database = env.create_database(Some(SCRIPT_DATABASE))?;
+        let plugin_database = env.create_database(Some(PLUGIN_DATABASE))?;
+        let palette_database = env.create_database(Some(PALETTE_DATABASE))?;
         Ok(Self {
             env,
             effect_database,
+            script_database,
+            plugin_database,
+            palette_database,
         })
     }
 
-    pub(crate) fn load<S>(&mut self, key: S) -> Result<Option<Details>, heed::Error>
+    pub(crate) fn load<S>(&mut self, key: S) -> Result<Option<Details>, lights::error::Error>
     where
         S: AsRef<str>,
     {
-        let txn = self.env.read_txn()?;
-        let val = self
-            .effect_database
+        let txn = self
+            .env
+            .read_txn()
+            .map_err(|_| lights::error::Error::HeedError)?;
+        self.effect_database
             .get(&txn, key.as_ref())
-            // If we can't load it for some reason, don't fret, it is probably
-            // just an issue with a new version of the program...
-            .unwrap_or(Default::default());
-        Ok(val)
+            .map_err(|_| lights::error::Error::HeedError)?
+            .map(|bytes| decode_details(bytes).map(|(details, _)| details))
+            .transpose()
     }
 
+    /// Save `details` as the preset `key`.
     pub(crate) async fn store(
         &mut self,
         key: &str,
         details: Details,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if is_reserved(key) {
+            return Err(format!("`{}` is reserved, it can't be a preset's name", key).into());
+        }
+        self.put(key, details).await
+    }
+
+    /// Save `details` as what's on the strip.
+    pub(crate) async fn store_main(
+        &mut self,
+        details: Details,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.put(MAIN, details).await
+    }
+
+    async fn put(
+        &mut self,
+        key: &str,
+        details: Details,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         let env = self.env.clone();
         let db = self.effect_database.clone();
+        let key = key.to_owned();
+        let bytes = rmp_serde::to_vec_named(&Versioned::new(details))?;
         task::spawn_blocking(move || {
             let mut txn = env
                 .write_txn()
                 .map_err(|_| lights::error::Error::HeedError)?;
-            db.put(&mut txn, key, &details)
+            db.put(&mut txn, &key, &bytes)
                 .map_err(|_| lights::error::Error::HeedError)?;
             Ok(txn.commit().map_err(|_| lights::error::Error::HeedError)?)
         })
         .await
     }
+
+    /// Every saved preset, leaving out the details currently on the strip.
+    pub(crate) fn load_presets(
+        &self,
+    ) -> Result<Vec<(String, Result<Details, lights::error::Error>)>, lights::error::Error> {
+        use lights::error::Error::HeedError;
+
+        let txn = self.env.read_txn().map_err(|_| HeedError)?;
+        let mut presets = vec![];
+        for entry in self.effect_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, bytes) = entry.map_err(|_| HeedError)?;
+            if is_reserved(name) {
+                continue;
+            }
+            let details = decode_details(bytes).map(|(details, _)| details);
+            presets.push((name.to_owned(), details));
+        }
+        Ok(presets)
+    }
+
+    /// The names of the saved presets, without loading them.
+    pub(crate) fn preset_names(&self) -> Result<Vec<String>, lights::error::Error> {
+        use lights::error::Error::HeedError;
+
+        let txn = self.env.read_txn().map_err(|_| HeedError)?;
+        let mut names = vec![];
+        for entry in self.effect_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, _) = entry.map_err(|_| HeedError)?;
+            if !is_reserved(name) {
+                names.push(name.to_owned());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Bring every stored details up to the current version. The ones that
+    /// can't be are left as they are and returned with why, rather than lost.
+    pub(crate) fn migrate(
+        &self,
+    ) -> Result<Vec<(String, lights::error::Error)>, lights::error::Error> {
+        use lights::error::Error::{HeedError, Migration};
+
+        let mut txn = self.env.write_txn().map_err(|_| HeedError)?;
+        let mut migrated = vec![];
+        let mut failed = vec![];
+        for entry in self.effect_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, bytes) = entry.map_err(|_| HeedError)?;
+            if NAMED_DATABASES.contains(&name) {
+                continue;
+            }
+            match decode_details(bytes) {
+                Ok((_, version)) if version == migrations::VERSION => {}
+                Ok((details, _)) => migrated.push((name.to_owned(), details)),
+                Err(e) => failed.push((name.to_owned(), e)),
+            }
+        }
+        for (name, details) in &migrated {
+            let bytes = rmp_serde::to_vec_named(&Versioned::new(details.clone()))
+                .map_err(|e| Migration(e.to_string()))?;
+            self.effect_database
+                .put(&mut txn, name, &bytes)
+                .map_err(|_| HeedError)?;
+        }
+        txn.commit().map_err(|_| HeedError)?;
+        if !migrated.is_empty() {
+            log::info!(
+                "Migrated {} stored details to version {}",
+                migrated.len(),
+                migrations::VERSION
+            );
+        }
+        Ok(failed)
+    }
+
+    /// Everything in the store, read in one transaction so it's consistent.
+    pub(crate) fn snapshot(&self) -> Result<Snapshot, lights::error::Error> {
+        use lights::error::Error::HeedError;
+
+        let txn = self.env.read_txn().map_err(|_| HeedError)?;
+        let mut snapshot = Snapshot::default();
+        for entry in self.effect_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, bytes) = entry.map_err(|_| HeedError)?;
+            if !NAMED_DATABASES.contains(&name) {
+                snapshot
+                    .details
+                    .insert(name.to_owned(), ByteBuf::from(bytes));
+            }
+        }
+        for entry in self.script_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, source) = entry.map_err(|_| HeedError)?;
+            snapshot.scripts.insert(name.to_owned(), source.to_owned());
+        }
+        for entry in self.plugin_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, bytes) = entry.map_err(|_| HeedError)?;
+            snapshot
+                .plugins
+                .insert(name.to_owned(), ByteBuf::from(bytes));
+        }
+        for entry in self.palette_database.iter(&txn).map_err(|_| HeedError)? {
+            let (name, palette) = entry.map_err(|_| HeedError)?;
+            snapshot.palettes.insert(name.to_owned(), palette);
+        }
+        Ok(snapshot)
+    }
+
+    /// Replace everything in the store with `snapshot`, all at once.
+    pub(crate) fn restore(&self, snapshot: &Snapshot) -> Result<(), lights::error::Error> {
+        use lights::error::Error::HeedError;
+
+        let mut txn = self.env.write_txn().map_err(|_| HeedError)?;
+        // Clearing the unnamed database would take the named ones with it.
+        let names: Vec<String> = self
+            .effect_database
+            .iter(&txn)
+            .map_err(|_| HeedError)?
+            .filter_map(|entry| entry.ok().map(|(name, _)| name.to_owned()))
+            .filter(|name| !NAMED_DATABASES.contains(&name.as_str()))
+            .collect();
+        for name in names {
+            self.effect_database
+                .delete(&mut txn, &name)
+                .map_err(|_| HeedError)?;
+        }
+        self.script_database
+            .clear(&mut txn)
+            .map_err(|_| HeedError)?;
+        self.plugin_database
+            .clear(&mut txn)
+            .map_err(|_| HeedError)?;
+        self.palette_database
+            .clear(&mut txn)
+            .map_err(|_| HeedError)?;
+        for (name, bytes) in &snapshot.details {
+            self.effect_database
+                .put(&mut txn, name, &bytes[..])
+                .map_err(|_| HeedError)?;
+        }
+        for (name, source) in &snapshot.scripts {
+            self.script_database
+                .put(&mut txn, name, source)
+                .map_err(|_| HeedError)?;
+        }
+        for (name, bytes) in &snapshot.plugins {
+            self.plugin_database
+                .put(&mut txn, name, &bytes[..])
+                .map_err(|_| HeedError)?;
+        }
+        for (name, palette) in &snapshot.palettes {
+            self.palette_database
+                .put(&mut txn, name, palette)
+                .map_err(|_| HeedError)?;
+        }
+        txn.commit().map_err(|_| HeedError)
+    }
+
+    pub(crate) fn load_scripts(&self) -> Result<Vec<(String, String)>, heed::Error> {
+        let txn = self.env.read_txn()?;
+        self.script_database
+            .iter(&txn)?
+            .map(|entry| entry.map(|(name, source)| (name.to_owned(), source.to_owned())))
+            .collect()
+    }
+
+    pub(crate) async fn store_script(
+        &self,
+        name: String,
+        source: String,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let env = self.env.clone();
+        let db = self.script_database.clone();
+        task::spawn_blocking(move || {
+            let mut txn = env
+                .write_txn()
+                .map_err(|_| lights::error::Error::HeedError)?;
+            db.put(&mut txn, &name, &source)
+                .map_err(|_| lights::error::Error::HeedError)?;
+            Ok(txn.commit().map_err(|_| lights::error::Error::HeedError)?)
+        })
+        .await
+    }
+
+    pub(crate) async fn delete_script(
+        &self,
+        name: String,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let env = self.env.clone();
+        let db = self.script_database.clone();
+        task::spawn_blocking(move || {
+            let mut txn = env
+                .write_txn()
+                .map_err(|_| lights::error::Error::HeedError)?;
+            let deleted = db
+                .delete(&mut txn, &name)
+                .map_err(|_| lights::error::Error::HeedError)?;
+            txn.commit().map_err(|_| lights::error::Error::HeedError)?;
+            Ok(deleted)
+        })
+        .await
+    }
+
+    pub(crate) fn load_plugins(&self) -> Result<Vec<(String, Vec<u8>)>, heed::Error> {
+        let txn = self.env.read_txn()?;
+        self.plugin_database
+            .iter(&txn)?
+            .map(|entry| entry.map(|(name, bytes)| (name.to_owned(), bytes.to_vec())))
+            .collect()
+    }
+
+    pub(crate) async fn store_plugin(
+        &self,
+        name: String,
+        bytes: Vec<u8>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let env = self.env.clone();
+        let db = self.plugin_database.clone();
+        task::spawn_blocking(move || {
+            let mut txn = env
+                .write_txn()
+                .map_err(|_| lights::error::Error::HeedError)?;
+            db.put(&mut txn, &name, &bytes)
+                .map_err(|_| lights::error::Error::HeedError)?;
+            Ok(txn.commit().map_err(|_| lights::error::Error::HeedError)?)
+        })
+        .await
+    }
+
+    pub(crate) async fn delete_plugin(
+        &self,
+        name: String,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let env = self.env.clone();
+        let db = self.plugin_database.clone();
+        task::spawn_blocking(move || {
+            let mut txn = env
+                .write_txn()
+                .map_err(|_| lights::error::Error::HeedError)?;
+            let deleted = db
+                .delete(&mut txn, &name)
+                .map_err(|_| lights::error::Error::HeedError)?;
+            txn.commit().map_err(|_| lights::error::Error::HeedError)?;
+            Ok(deleted)
+        })
+        .await
+    }
+
+    pub(crate) fn load_palettes(&self) -> Result<Vec<(String, Palette)>, heed::Error> {
+        let txn = self.env.read_txn()?;
+        self.palette_database
+            .iter(&txn)?
+            .map(|entry| entry.map(|(name, palette)| (name.to_owned(), palette)))
+            .collect()
+    }
+
+    pub(crate) async fn store_palette(
+        &self,
+        name: String,
+        palette: Palette,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let env = self.env.clone();
+        let db = self.palette_database.clone();
+        task::spawn_blocking(move || {
+            let mut txn = env
+                .write_txn()
+                .map_err(|_| lights::error::Error::HeedError)?;
+            db.put(&mut txn, &name, &palette)
+                .map_err(|_| lights::error::Error::HeedError)?;
+            Ok(txn.commit().map_err(|_| lights::error::Error::HeedError)?)
+        })
+        .await
+    }
+
+    pub(crate) async fn delete_palette(
+        &self,
+        name: String,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let env = self.env.clone();
+        let db = self.palette_database.clone();
+        task::spawn_blocking(move || {
+            let mut txn = env
+                .write_txn()
+                .map_err(|_| lights::error::Error::HeedError)?;
+            let deleted = db
+                .delete(&mut txn, &name)
+                .map_err(|_| lights::error::Error::HeedError)?;
+            txn.commit().map_err(|_| lights::error::Error::HeedError)?;
+            Ok(deleted)
+        })
+        .await
+    }
+}
+
+/// Stored details and the version they were stored at.
+pub(crate) fn decode_details(bytes: &[u8]) -> Result<(Details, u32), lights::error::Error> {
+    // Most of the time they're current, and don't need to go through JSON.
+    if let Ok(Versioned { version, details }) = rmp_serde::from_slice(bytes) {
+        if version == migrations::VERSION {
+            return Ok((details, version));
+        }
+    }
+    let value: serde_json::Value =
+        rmp_serde::from_slice(bytes).map_err(|e| lights::error::Error::Migration(e.to_string()))?;
+    let version = migrations::version_of(&value)?;
+    let migrated = rmp_serde::to_vec_named(&migrations::migrate(value)?)
+        .map_err(|e| lights::error::Error::Migration(e.to_string()))?;
+    let details = rmp_serde::from_slice(&migrated)
+        .map_err(|e| lights::error::Error::Migration(e.to_string()))?;
+    Ok((details, version))
 }
@@ -8,9 +8,15 @@ use palette::LinSrgb;
 #[cfg(target_arch = "arm")]
 use rs_ws281x::{ChannelBuilder, Controller, ControllerBuilder, StripType};
 
+#[cfg(target_arch = "arm")]
+use crate::config::ColorOrder;
+use crate::config::Hardware;
+
 pub struct LedStrip {
     #[cfg(target_arch = "arm")]
     cont: Option<Controller>,
+    #[cfg_attr(not(target_arch = "arm"), allow(unused))]
+    hardware: Hardware,
 
     pixels: Vec<LinSrgb<u8>>,
     details: Details,
@@ -21,19 +27,20 @@ pub struct LedStrip {
 impl !Send for LedStrip {}
 
 impl LedStrip {
-    pub fn new(details: Details) -> Result<Self> {
-        // TODO: Get this customizable more
+    pub fn new(details: Details, hardware: Hardware) -> Result<Self> {
         #[cfg(target_arch = "arm")]
         let cont = Some(Self::construct_controller(
+            &hardware,
             details.length,
             details.brightness,
         )?);
         let pixels = vec![LinSrgb::new(0, 0, 0); details.length];
-        let effect = details.effect.clone().into_inner();
+        let effect = Self::build(details.effect.clone());
 
         Ok(Self {
             #[cfg(target_arch = "arm")]
             cont,
+            hardware,
             pixels,
 
             details,
@@ -42,16 +49,28 @@ impl LedStrip {
     }
 
     #[cfg(target_arch = "arm")]
-    pub fn construct_controller(length: usize, brightness: u8) -> Result<Controller> {
+    pub fn construct_controller(
+        hardware: &Hardware,
+        length: usize,
+        brightness: u8,
+    ) -> Result<Controller> {
+        let strip_type = match hardware.color_order {
+            ColorOrder::Rgb => StripType::Ws2811Rgb,
+            ColorOrder::Rbg => StripType::Ws2811Rbg,
+            ColorOrder::Grb => StripType::Ws2811Grb,
+            ColorOrder::Gbr => StripType::Ws2811Gbr,
+            ColorOrder::Brg => StripType::Ws2811Brg,
+            ColorOrder::Bgr => StripType::Ws2811Bgr,
+        };
         Ok(ControllerBuilder::new()
-            .freq(800_000)
-            .dma(10)
+            .freq(hardware.frequency)
+            .dma(hardware.dma)
             .channel(
                 0,
                 ChannelBuilder::new()
-                    .pin(18)
+                    .pin(hardware.pin)
                     .count(length as i32)
-                    .strip_type(StripType::Ws2811Rbg)
+                    .strip_type(strip_type)
                     .brightness(brightness)
                     .build(),
             )
@@ -63,6 +82,10 @@ impl LedStrip {
         self.pixels.len()
     }
 
+    pub fn pixels(&self) -> &[LinSrgb<u8>] {
+        &self.pixels
+    }
+
     pub fn clear(&mut self) -> Result<()> {
         self.clear_color(LinSrgb::new(0, 0, 0))
     }
@@ -74,11 +97,23 @@ impl LedStrip {
         Ok(())
     }
 
-    pub fn update(
-        &mut self,
-        now: DateTime<Utc>,
-    ) -> std::result::Result<Duration, lights::error::Error> {
-        self.effect.render(&mut self.pixels, now)
+    /// Draw the next frame. An effect that fails, like a script that throws,
+    /// is logged and swapped for `Empty` rather than stopping the strip.
+    pub fn update(&mut self, now: DateTime<Utc>) -> Duration {
+        match self.effect.render(&mut self.pixels, now) {
+            Ok(d) => d,
+            Err(e) => {
+                log::error!(
+                    "{} failed, showing nothing instead: {}",
+                    self.details.effect.name(),
+                    e
+                );
+                self.details.effect = EffectType::Empty(Empty);
+                self.effect = Box::new(Empty);
+                self.clear_color(LinSrgb::new(0, 0, 0)).ok();
+                Duration::milliseconds(100)
+            }
+        }
     }
 
     #[cfg(target_arch = "arm")]
@@ -101,10 +136,20 @@ impl LedStrip {
 
     pub fn set_effect(&mut self, effect: EffectType) -> Result<()> {
         self.details.effect = effect.clone();
-        self.effect = effect.into_inner();
+        self.effect = Self::build(effect);
         Ok(())
     }
 
+    /// `effect`, or `Empty` if it can't be made, like a script that doesn't
+    /// compile or a plugin that's gone.
+    fn build(effect: EffectType) -> Box<dyn Effect> {
+        let name = effect.name();
+        effect.try_into_inner().unwrap_or_else(|e| {
+            log::error!("Couldn't load {}, showing nothing instead: {}", name, e);
+            Box::new(Empty)
+        })
+    }
+
     pub fn set_length(&mut self, length: usize) -> Result<()> {
         if self.details.length == length {
             return Ok(());
@@ -114,7 +159,11 @@ impl LedStrip {
         #[cfg(target_arch = "arm")]
         {
             let _ = self.cont.take();
-            self.cont = Some(Self::construct_controller(length, self.details.brightness)?);
+            self.cont = Some(Self::construct_controller(
+                &self.hardware,
+                length,
+                self.details.brightness,
+            )?);
         }
         Ok(())
     }
@@ -129,7 +178,11 @@ impl LedStrip {
             // Explicityly drop the controller, to clean up pointers
             let old_cont = self.cont.take();
             drop(old_cont);
-            self.cont = Some(Self::construct_controller(self.details.length, brightness)?);
+            self.cont = Some(Self::construct_controller(
+                &self.hardware,
+                self.details.length,
+                brightness,
+            )?);
         }
         Ok(())
     }
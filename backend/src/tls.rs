@@ -0,0 +1,124 @@
+//! HTTPS for the web UI, see `config::Tls`.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    net::SocketAddr,
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use tide::{Redirect, Request};
+use tide_rustls::TlsListener;
+
+use crate::config::Tls;
+
+/// Make sure there's a certificate and key, making a self-signed pair if
+/// there aren't any and that's allowed.
+pub(crate) fn ensure_cert(tls: &Tls) -> Result<()> {
+    match (tls.cert.exists(), tls.key.exists()) {
+        (true, true) => return Ok(()),
+        (false, false) if tls.self_signed => {}
+        _ if tls.self_signed => bail!(
+            "Only one of {} and {} exists, delete it to make a new self-signed pair",
+            tls.cert.display(),
+            tls.key.display()
+        ),
+        _ => bail!(
+            "There's no certificate at {} and key at {}, and self_signed is off",
+            tls.cert.display(),
+            tls.key.display()
+        ),
+    }
+
+    let mut hostnames = tls.hostnames.clone();
+    if hostnames.is_empty() {
+        hostnames.push("localhost".to_owned());
+        if let Ok(hostname) = fs::read_to_string("/etc/hostname") {
+            let hostname = hostname.trim();
+            if !hostname.is_empty() {
+                hostnames.push(hostname.to_owned());
+                hostnames.push(format!("{}.local", hostname));
+            }
+        }
+    }
+    let cert = rcgen::generate_simple_self_signed(hostnames.clone())?;
+    write_new(&tls.cert, cert.serialize_pem()?.as_bytes(), 0o644)?;
+    write_new(&tls.key, cert.serialize_private_key_pem().as_bytes(), 0o600)?;
+    log::info!(
+        "Made a self-signed certificate for {} at {}",
+        hostnames.join(", "),
+        tls.cert.display()
+    );
+    Ok(())
+}
+
+fn write_new(path: &Path, contents: &[u8], mode: u32) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .with_context(|| format!("Couldn't write {}", path.display()))
+}
+
+pub(crate) fn listener<S>(tls: &Tls) -> io::Result<TlsListener<S>>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    TlsListener::build()
+        .addrs(tls.listen.clone())
+        .cert(&tls.cert)
+        .key(&tls.key)
+        .finish()
+}
+
+/// A server that sends everything to the same place over HTTPS.
+pub(crate) fn redirect(tls: &Tls) -> tide::Server<()> {
+    let port = tls.listen.parse::<SocketAddr>().expect("validated").port();
+    let to_https = move |req: Request<()>| async move {
+        let url = req.url();
+        let host = req
+            .host()
+            .or_else(|| url.host_str())
+            .map_or("localhost", without_port)
+            .to_owned();
+        let port = if port == 443 {
+            String::new()
+        } else {
+            format!(":{}", port)
+        };
+        let query = url.query().map(|q| format!("?{}", q)).unwrap_or_default();
+        Ok(Redirect::permanent(format!(
+            "https://{}{}{}{}",
+            host,
+            port,
+            url.path(),
+            query
+        )))
+    };
+    let mut app = tide::new();
+    app.at("/").all(to_https);
+    app.at("*").all(to_https);
+    app
+}
+
+/// `host` from a Host header, without the HTTP port.
+fn without_port(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        // A bare IPv6 address has colons but no port.
+        Some((name, port))
+            if !port.is_empty()
+                && port.bytes().all(|b| b.is_ascii_digit())
+                && (name.starts_with('[') || !name.contains(':')) =>
+        {
+            name
+        }
+        _ => host,
+    }
+}
@@ -7,8 +7,12 @@ use yew::{platform::spawn_local, prelude::*};
 
 use crate::{
     components::{self, ApplyForm, HistoryList},
+    events::EventStream,
     utils::{
-        view_ball, view_balls, view_composite, view_empty, view_glow, view_rainbow, view_runescript,
+        view_ball, view_balls, view_beat_flash, view_breathing, view_color_wipe, view_composite,
+        view_empty, view_fire, view_glow, view_larson, view_particles, view_plasma, view_plugin,
+        view_rainbow, view_runescript, view_signal_flash, view_spectrum, view_strobe,
+        view_theater_chase, view_twinkle, view_vu_meter,
     },
 };
 
@@ -17,17 +21,30 @@ const LAST_EFFECT_KEY: &str = "org.favil.raspylights.effect.last";
 
 pub struct App {
     model: Model,
+    /// Changes made from other browsers, and by anything else.
+    _events: Option<EventStream>,
 }
 
 #[derive(Debug)]
 struct Model {
     details: Details,
+    /// The effect has been changed here and not applied yet.
+    edited: bool,
+    /// An effect applied from elsewhere while this one was being edited,
+    /// kept until it's asked for so the edits aren't lost.
+    pushed: Option<EffectType>,
+    power: bool,
+    presets: Vec<String>,
 }
 
 impl Default for Model {
     fn default() -> Self {
         Self {
             details: Default::default(),
+            edited: false,
+            pushed: None,
+            power: true,
+            presets: vec![],
         }
     }
 }
@@ -37,8 +54,11 @@ pub enum Msg {
     EffectName(&'static str),
     Length(usize),
     Brightness(u8),
-    FetchDetails(usize, u8),
     PostStatus(Details),
+    /// An event from the backend, its name and JSON.
+    Pushed(&'static str, String),
+    /// Swap the effect being edited for the one pushed from elsewhere.
+    TakePushed,
     Save(String),
     Reset,
 }
@@ -49,9 +69,16 @@ impl Component for App {
     type Properties = ();
 
     fn create(ctx: &Context<Self>) -> Self {
-        let model = Self::load_model(ctx).unwrap_or_default();
+        let model = Self::load_model().unwrap_or_default();
+        let callback = ctx.link().callback(|(name, data)| Msg::Pushed(name, data));
+        let events = EventStream::open("/events", &["details", "power", "presets"], callback)
+            .map_err(|e| log::error!("Couldn't listen for changes: {:?}", e))
+            .ok();
 
-        App { model }
+        App {
+            model,
+            _events: events,
+        }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
@@ -60,20 +87,34 @@ impl Component for App {
             Msg::Type(t) => {
                 self.store_last_effect(&t);
                 self.model.details.effect = t;
+                self.model.edited = true;
                 false
             }
             Msg::EffectName(name) => {
                 // Used for when we click the title
                 self.model.details.effect = self.load_last_effect(name);
-                false
-            }
-            Msg::FetchDetails(l, b) => {
-                self.model.details.length = l;
-                self.model.details.brightness = b;
+                self.model.edited = true;
                 false
             }
             Msg::PostStatus(details) => {
                 self.model.details = details;
+                self.model.edited = false;
+                self.model.pushed = None;
+                false
+            }
+            Msg::Pushed(name, data) => {
+                let pushed = match name {
+                    "details" => serde_json::from_str::<Details>(&data).map(|d| self.pushed(d)),
+                    "power" => serde_json::from_str::<serde_json::Value>(&data)
+                        .map(|v| self.model.power = v["active"].as_bool().unwrap_or(true)),
+                    "presets" => {
+                        serde_json::from_str::<Vec<String>>(&data).map(|p| self.model.presets = p)
+                    }
+                    _ => Ok(()),
+                };
+                if let Err(e) = pushed {
+                    log::error!("Bad {} event: {}", name, e);
+                }
                 false
             }
             Msg::Length(l) => {
@@ -88,8 +129,16 @@ impl Component for App {
                 self.model.details.name = name;
                 true
             }
+            Msg::TakePushed => {
+                if let Some(effect) = self.model.pushed.take() {
+                    self.model.details.effect = effect;
+                    self.model.edited = false;
+                }
+                false
+            }
             Msg::Reset => {
-                self.model = Self::load_model(ctx).unwrap_or_default();
+                self.model.details = Self::load_model().unwrap_or_default().details;
+                self.model.edited = false;
                 false
             }
         };
@@ -110,6 +159,18 @@ impl Component for App {
             <>
                 // The layer of details
                 <ybc::Box classes={ classes!("strip_details") }>
+                    if !self.model.power {
+                        <span class="tag is-warning">{ "The strip is off" }</span>
+                    }
+                    if self.model.pushed.is_some() {
+                        <span class="tag is-info">
+                            { "The strip was changed elsewhere" }
+                            <button class="button is-small is-ghost"
+                                onclick={ ctx.link().callback(|_| Msg::TakePushed) }>
+                                { "Load it" }
+                            </button>
+                        </span>
+                    }
                     <label for="strip_length">{ "Number of LEDs" }</label>
                     <input type="number"
                         name="strip_length"
@@ -141,6 +202,7 @@ impl Component for App {
                 </ybc::Box>
                 <ybc::Columns>
                     <ybc::Column classes={ classes!("is-one-quarter") }>
+                      <components::Login />
                       // The history and apply sidebar
                       <ybc::Box classes={ classes!("form") }>
                         <ApplyForm
@@ -162,6 +224,9 @@ impl Component for App {
                             selected={ self.model.details.name.clone() }
                         />
                       </ybc::Box>
+                      <ybc::Box classes={ classes!("presets") }>
+                        <components::Presets names={ self.model.presets.clone() } />
+                      </ybc::Box>
                     </ybc::Column>
                     <ybc::Column>
                         // The preview and effect selector
@@ -172,6 +237,9 @@ impl Component for App {
                             { selector }
                             <div class="effect">{ effect }</div>
                         </ybc::Box>
+                        <ybc::Box classes={ classes!("palettes") }>
+                            <components::Palettes />
+                        </ybc::Box>
                     </ybc::Column>
                 </ybc::Columns>
                 // The effects
@@ -181,30 +249,37 @@ impl Component for App {
 }
 
 impl App {
-    fn load_model(ctx: &Context<Self>) -> Result<Model, anyhow::Error> {
+    /// Details applied from elsewhere. What's on the strip is always taken,
+    /// but an effect being edited here is only swapped out when asked to.
+    fn pushed(&mut self, details: Details) {
+        let Details {
+            effect,
+            length,
+            brightness,
+            name,
+        } = details;
+        self.model.details.length = length;
+        self.model.details.brightness = brightness;
+        self.model.details.name = name;
+        if !self.model.edited {
+            self.model.details.effect = effect;
+            self.model.pushed = None;
+        } else {
+            self.model.pushed = (effect != self.model.details.effect).then_some(effect);
+        }
+    }
+
+    /// The last effect from this browser. The rest of the details, and
+    /// what's really on the strip, come from the backend's events.
+    fn load_model() -> Result<Model, anyhow::Error> {
         let effect = LocalStorage::get::<EffectType>(EFFECT_KEY).unwrap_or(EffectType::default());
-        let callback = ctx.link().callback(move |response: String| {
-            let data = serde_json::from_str::<Details>(&response);
-            log::info!("Details: {:#?}", data);
-            let (l, b) = data.map(|d| (d.length, d.brightness)).unwrap_or((100, 150));
-            Msg::FetchDetails(l, b)
-        });
-        spawn_local(async move {
-            let req = Request::get("/details")
-                .send()
-                .await
-                .expect("Need to get a length response")
-                .text()
-                .await
-                .expect("Needed to get body");
-            callback.emit(req);
-        });
 
         Ok(Model {
             details: Details {
                 effect,
                 ..Details::default()
             },
+            ..Model::default()
         })
     }
 
@@ -217,15 +292,18 @@ impl App {
             Msg::PostStatus(details)
         });
         spawn_local(async move {
-            let req = Request::post("details")
+            let resp = Request::post("details")
                 .json(&details)
                 .expect("json serialized properly")
                 .send()
                 .await
-                .expect("Json of effect_type")
-                .text()
-                .await
-                .expect("Need to get body");
+                .expect("Json of effect_type");
+            // Not logged in, or read-only, keep what's on screen.
+            if !resp.ok() {
+                log::error!("Couldn't apply details: {}", resp.status_text());
+                return;
+            }
+            let req = resp.text().await.expect("Need to get body");
             callback.emit(req);
         })
     }
@@ -239,7 +317,24 @@ impl App {
             "Glow" => EffectType::Glow(lights::effects::Glow::default()),
             "Rainbow" => EffectType::Rainbow(lights::effects::Rainbow::default()),
             "Composite" => EffectType::Composite(lights::effects::Composite::default()),
-            "Rune Script" => EffectType::RuneScript(lights::effects::SourceCode::default()),
+            "Spectrum" => EffectType::SpectrumBars(lights::effects::SpectrumBars::default()),
+            "VU Meter" => EffectType::VuMeter(lights::effects::VuMeter::default()),
+            "Beat Flash" => EffectType::BeatFlash(lights::effects::BeatFlash::default()),
+            "Signal Flash" => EffectType::SignalFlash(lights::effects::SignalFlash::default()),
+            "Particles" => EffectType::Particles(lights::effects::Particles::default()),
+            "Fire" => EffectType::Fire(lights::effects::Fire::default()),
+            "Twinkle" => EffectType::Twinkle(lights::effects::Twinkle::default()),
+            "Theater Chase" => EffectType::TheaterChase(lights::effects::TheaterChase::default()),
+            "Color Wipe" => EffectType::ColorWipe(lights::effects::ColorWipe::default()),
+            "Larson Scanner" => EffectType::Larson(lights::effects::Larson::default()),
+            "Breathing" => EffectType::Breathing(lights::effects::Breathing::default()),
+            "Strobe" => EffectType::Strobe(lights::effects::Strobe::default()),
+            "Plasma" => EffectType::Plasma(lights::effects::Plasma::default()),
+            "Rune Script" => EffectType::RuneScript(
+                lights::effects::SourceCode::default(),
+                lights::effects::ParamValues::default(),
+            ),
+            "Wasm Plugin" => EffectType::WasmPlugin(String::new()),
             _ => panic!(),
         });
         effect
@@ -281,8 +376,28 @@ impl App {
             EffectType::Glow(g) => view_glow(&g, &ctx.link(), |g| Msg::Type(g.into())),
             EffectType::Composite(c) => view_composite(&c, &ctx.link(), |c| Msg::Type(c.into())),
             EffectType::Rainbow(r) => view_rainbow(&r, &ctx.link(), |r| Msg::Type(r.into())),
-            EffectType::RuneScript(s) => {
-                view_runescript(&s, &ctx.link(), |s| Msg::Type(EffectType::RuneScript(s)))
+            EffectType::SpectrumBars(s) => view_spectrum(&s, &ctx.link(), |s| Msg::Type(s.into())),
+            EffectType::VuMeter(v) => view_vu_meter(&v, &ctx.link(), |v| Msg::Type(v.into())),
+            EffectType::BeatFlash(b) => view_beat_flash(&b, &ctx.link(), |b| Msg::Type(b.into())),
+            EffectType::SignalFlash(f) => {
+                view_signal_flash(&f, &ctx.link(), |f| Msg::Type(f.into()))
+            }
+            EffectType::Particles(p) => view_particles(&p, &ctx.link(), |p| Msg::Type(p.into())),
+            EffectType::Fire(f) => view_fire(&f, &ctx.link(), |f| Msg::Type(f.into())),
+            EffectType::Twinkle(t) => view_twinkle(&t, &ctx.link(), |t| Msg::Type(t.into())),
+            EffectType::TheaterChase(c) => {
+                view_theater_chase(&c, &ctx.link(), |c| Msg::Type(c.into()))
+            }
+            EffectType::ColorWipe(w) => view_color_wipe(&w, &ctx.link(), |w| Msg::Type(w.into())),
+            EffectType::Larson(l) => view_larson(&l, &ctx.link(), |l| Msg::Type(l.into())),
+            EffectType::Breathing(b) => view_breathing(&b, &ctx.link(), |b| Msg::Type(b.into())),
+            EffectType::Strobe(s) => view_strobe(&s, &ctx.link(), |s| Msg::Type(s.into())),
+            EffectType::Plasma(p) => view_plasma(&p, &ctx.link(), |p| Msg::Type(p.into())),
+            EffectType::RuneScript(s, p) => view_runescript(&s, &p, &ctx.link(), |(s, p)| {
+                Msg::Type(EffectType::RuneScript(s, p))
+            }),
+            EffectType::WasmPlugin(name) => {
+                view_plugin(&name, &ctx.link(), |name| Msg::Type(EffectType::WasmPlugin(name)))
             }
         }
     }
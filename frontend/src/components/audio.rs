@@ -0,0 +1,197 @@
+use chrono::Duration;
+use palette::LinSrgb;
+
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils::view_color_field;
+
+fn silent_note() -> Html {
+    html! {
+        <p class="help">{ "Follows the audio the lights are listening to, so the preview stays dark." }</p>
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SpectrumBars {
+    effect: lights::effects::SpectrumBars,
+}
+
+pub(crate) enum SpectrumMsg {
+    Low(LinSrgb<u8>),
+    High(LinSrgb<u8>),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct SpectrumProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::SpectrumBars>>,
+    #[prop_or_default]
+    pub spectrum: lights::effects::SpectrumBars,
+}
+
+impl Component for SpectrumBars {
+    type Message = SpectrumMsg;
+
+    type Properties = SpectrumProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().spectrum.clone(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            SpectrumMsg::Low(c) => self.effect.low = c,
+            SpectrumMsg::High(c) => self.effect.high = c,
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { silent_note() }
+                { view_color_field("Bottom of the bars: ", self.effect.low, link.callback(SpectrumMsg::Low)) }
+                { view_color_field("Top of the bars: ", self.effect.high, link.callback(SpectrumMsg::High)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct VuMeter {
+    effect: lights::effects::VuMeter,
+}
+
+pub(crate) enum VuMsg {
+    Low(LinSrgb<u8>),
+    Mid(LinSrgb<u8>),
+    High(LinSrgb<u8>),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct VuProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::VuMeter>>,
+    #[prop_or_default]
+    pub meter: lights::effects::VuMeter,
+}
+
+impl Component for VuMeter {
+    type Message = VuMsg;
+
+    type Properties = VuProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().meter.clone(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            VuMsg::Low(c) => self.effect.low = c,
+            VuMsg::Mid(c) => self.effect.mid = c,
+            VuMsg::High(c) => self.effect.high = c,
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { silent_note() }
+                { view_color_field("Quiet: ", self.effect.low, link.callback(VuMsg::Low)) }
+                { view_color_field("Loud: ", self.effect.mid, link.callback(VuMsg::Mid)) }
+                { view_color_field("Peak: ", self.effect.high, link.callback(VuMsg::High)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct BeatFlash {
+    effect: lights::effects::BeatFlash,
+}
+
+pub(crate) enum BeatMsg {
+    Color(LinSrgb<u8>),
+    Decay(i64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct BeatProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::BeatFlash>>,
+    #[prop_or_default]
+    pub flash: lights::effects::BeatFlash,
+}
+
+impl Component for BeatFlash {
+    type Message = BeatMsg;
+
+    type Properties = BeatProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().flash.clone(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            BeatMsg::Color(c) => self.effect.color = c,
+            BeatMsg::Decay(d) => self.effect.decay = Duration::milliseconds(d),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { silent_note() }
+                { view_color_field("Flash color: ", self.effect.color, link.callback(BeatMsg::Color)) }
+                <ybc::Field addons={ true }>
+                    <label class="label">{ "Fade out over: " }</label>
+                    <ybc::Control classes={ classes!("has-addons") }>
+                        <input type="range"
+                            class="input"
+                            min="20"
+                            max="2000"
+                            step="10"
+                            id="decay"
+                            name="decay"
+                            oninput={
+                                link.callback(move |ty: InputEvent| {
+                                    let event: Event = ty.dyn_into().unwrap_throw();
+                                    let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    BeatMsg::Decay(target.value().parse().unwrap_or(200))
+                                })
+                            }
+                            value={ self.effect.decay.num_milliseconds().to_string() }
+                        />
+                        <a class="button is-static">{ format!("{} ms", self.effect.decay.num_milliseconds()) }</a>
+                    </ybc::Control>
+                </ybc::Field>
+            </>
+        }
+    }
+}
@@ -1,9 +1,10 @@
-use chrono::Duration;
 use palette::LinSrgb;
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+use crate::utils::view_checkbox_field;
+
 #[derive(Clone, Debug)]
 pub(crate) struct Ball {
     effect: lights::effects::Ball,
@@ -20,7 +21,8 @@ pub(crate) enum Msg {
     Pos(usize),
     Direction(i8),
     Bounce(bool),
-    Delay(i64),
+    Speed(f32),
+    Smooth(bool),
 }
 
 #[derive(Clone, PartialEq, Properties, Debug)]
@@ -56,8 +58,11 @@ impl Component for Ball {
             Msg::Bounce(bounce) => {
                 self.effect.bounce = bounce;
             }
-            Msg::Delay(delay) => {
-                self.effect.delay = Duration::milliseconds(delay);
+            Msg::Speed(speed) => {
+                self.effect.speed = speed;
+            }
+            Msg::Smooth(smooth) => {
+                self.effect.smooth = smooth;
             }
         }
         ctx.props()
@@ -148,57 +153,44 @@ impl Component for Ball {
                     </ybc::Control>
                 </ybc::Field>
                 <ybc::Field addons={ true }>
-                    <label class="label">{ "Delay between movements: " }</label>
+                    <label class="label">{ "Speed: " }</label>
                     <ybc::Control classes={ classes!("has-addons") }>
                         <input type="range"
                             class="input"
-                            min="10"
-                            max="1000"
-                            step="10"
-                            id="delay"
-                            name="delay"
+                            min="1"
+                            max="100"
+                            step="1"
+                            id="speed"
+                            name="speed"
                             onchange={
-                                ctx.link().callback(move |ty: Event| {
-                                    let target: HtmlInputElement = ty.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let value = target.value();
-                                    let delay = value.parse().unwrap_or(100);
-                                    Msg::Delay(delay)
+                                ctx.link().callback(move |e: Event| {
+                                    let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    Msg::Speed(target.value().parse().unwrap_or(10.0))
                                 })
                             }
                             oninput={
-                                ctx.link().callback(move |ty:InputEvent| {
-                                    let event: Event = ty.dyn_into().unwrap_throw();
+                                ctx.link().callback(move |e: InputEvent| {
+                                    let event: Event = e.dyn_into().unwrap_throw();
                                     let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let value = target.value();
-                                    let delay = value.parse().unwrap_or(100);
-                                    Msg::Delay(delay)
+                                    Msg::Speed(target.value().parse().unwrap_or(10.0))
                                 })
                             }
-                            value= { ctx.props().ball.delay.num_milliseconds().to_string() }
+                            value={ ctx.props().ball.speed.to_string() }
                         />
                         <label>
-                            <input type="number" name="delay" id="delay_real" class="input"
+                            <input type="number" name="speed" id="speed_real" class="input"
+                                step="1"
                                 onchange={
                                     ctx.link().callback(move |e: Event| {
                                         let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
-                                        let value = target.value();
-                                        let delay = value.parse().unwrap_or(100);
-                                        Msg::Delay(delay)
-                                    })
-                                }
-                                oninput={
-                                    ctx.link().callback(move |e: InputEvent| {
-                                    let event: Event = e.dyn_into().unwrap_throw();
-                                    let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let value = target.value();
-                                    let delay = value.parse().unwrap_or(100);
-                                    Msg::Delay(delay)
+                                        Msg::Speed(target.value().parse().unwrap_or(10.0))
                                     })
                                 }
-                                value= { ctx.props().ball.delay.num_milliseconds().to_string() }
-                            /><a class="button is-static">{ "ms" }</a></label>
+                                value={ ctx.props().ball.speed.to_string() }
+                            /><a class="button is-static">{ "pixels/s" }</a></label>
                     </ybc::Control>
                 </ybc::Field>
+                { view_checkbox_field("Glide between pixels", ctx.props().ball.smooth, ctx.link().callback(Msg::Smooth)) }
             </>
         }
     }
@@ -0,0 +1,516 @@
+use chrono::Duration;
+use palette::LinSrgb;
+use yew::prelude::*;
+
+use crate::utils::{view_checkbox_field, view_color_field, view_number_field};
+
+fn millis(value: f64) -> Duration {
+    Duration::milliseconds(value.max(1.0) as i64)
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Fire {
+    effect: lights::effects::Fire,
+}
+
+pub(crate) enum FireMsg {
+    Cooling(f64),
+    Sparking(f64),
+    Reverse(bool),
+    Delay(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct FireProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Fire>>,
+    #[prop_or_default]
+    pub fire: lights::effects::Fire,
+}
+
+impl Component for Fire {
+    type Message = FireMsg;
+
+    type Properties = FireProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().fire.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().fire.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            FireMsg::Cooling(c) => self.effect.cooling = c.clamp(0.0, 255.0) as u8,
+            FireMsg::Sparking(s) => self.effect.sparking = s.clamp(0.0, 255.0) as u8,
+            FireMsg::Reverse(r) => self.effect.reverse = r,
+            FireMsg::Delay(d) => self.effect.delay = millis(d),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_number_field("Cooling (20-100): ", self.effect.cooling as f64, 1.0, link.callback(FireMsg::Cooling)) }
+                { view_number_field("Sparking (50-200): ", self.effect.sparking as f64, 1.0, link.callback(FireMsg::Sparking)) }
+                { view_number_field("Delay (ms): ", self.effect.delay.num_milliseconds() as f64, 1.0, link.callback(FireMsg::Delay)) }
+                { view_checkbox_field("Burn from the end", self.effect.reverse, link.callback(FireMsg::Reverse)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Twinkle {
+    effect: lights::effects::Twinkle,
+}
+
+pub(crate) enum TwinkleMsg {
+    AddColor,
+    ReplaceColor(usize, LinSrgb<u8>),
+    RemoveColor(usize),
+    Density(f64),
+    Fade(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct TwinkleProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Twinkle>>,
+    #[prop_or_default]
+    pub twinkle: lights::effects::Twinkle,
+}
+
+impl Component for Twinkle {
+    type Message = TwinkleMsg;
+
+    type Properties = TwinkleProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().twinkle.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().twinkle.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            TwinkleMsg::AddColor => self.effect.colors.push(LinSrgb::new(255, 255, 255)),
+            TwinkleMsg::ReplaceColor(idx, color) => self.effect.colors[idx] = color,
+            TwinkleMsg::RemoveColor(idx) => {
+                self.effect.colors.remove(idx);
+            }
+            TwinkleMsg::Density(d) => self.effect.density = d.max(0.0) as f32,
+            TwinkleMsg::Fade(f) => self.effect.fade = millis(f),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let colors = self.effect.colors.iter().cloned().enumerate().map(|(idx, color)| {
+            html! {
+                <ybc::Field>
+                    { view_color_field("", color, link.callback(move |c| TwinkleMsg::ReplaceColor(idx, c))) }
+                    <ybc::Control>
+                        <input type="button"
+                            onclick={ link.callback(move |_| TwinkleMsg::RemoveColor(idx)) }
+                            value={ "-" }
+                        />
+                    </ybc::Control>
+                </ybc::Field>
+            }
+        });
+        html! {
+            <>
+                <div classes={ classes!("glow-colors") }>
+                    { for colors }
+                    <ybc::Control>
+                        <input type="button"
+                            onclick={ link.callback(|_| TwinkleMsg::AddColor) }
+                            value={ "+" }
+                        />
+                    </ybc::Control>
+                </div>
+                { view_number_field("Twinkles per pixel per second: ", self.effect.density as f64, 0.05, link.callback(TwinkleMsg::Density)) }
+                { view_number_field("Fade (ms): ", self.effect.fade.num_milliseconds() as f64, 50.0, link.callback(TwinkleMsg::Fade)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TheaterChase {
+    effect: lights::effects::TheaterChase,
+}
+
+pub(crate) enum ChaseMsg {
+    Color(LinSrgb<u8>),
+    Spacing(f64),
+    Delay(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct ChaseProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::TheaterChase>>,
+    #[prop_or_default]
+    pub chase: lights::effects::TheaterChase,
+}
+
+impl Component for TheaterChase {
+    type Message = ChaseMsg;
+
+    type Properties = ChaseProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().chase.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().chase.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ChaseMsg::Color(c) => self.effect.color = c,
+            ChaseMsg::Spacing(s) => self.effect.spacing = s.max(1.0) as usize,
+            ChaseMsg::Delay(d) => self.effect.delay = millis(d),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_color_field("Color: ", self.effect.color, link.callback(ChaseMsg::Color)) }
+                { view_number_field("Light every: ", self.effect.spacing as f64, 1.0, link.callback(ChaseMsg::Spacing)) }
+                { view_number_field("Delay (ms): ", self.effect.delay.num_milliseconds() as f64, 10.0, link.callback(ChaseMsg::Delay)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ColorWipe {
+    effect: lights::effects::ColorWipe,
+}
+
+pub(crate) enum WipeMsg {
+    Color(LinSrgb<u8>),
+    Reverse(bool),
+    Delay(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct WipeProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::ColorWipe>>,
+    #[prop_or_default]
+    pub wipe: lights::effects::ColorWipe,
+}
+
+impl Component for ColorWipe {
+    type Message = WipeMsg;
+
+    type Properties = WipeProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().wipe.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().wipe.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            WipeMsg::Color(c) => self.effect.color = c,
+            WipeMsg::Reverse(r) => self.effect.reverse = r,
+            WipeMsg::Delay(d) => self.effect.delay = millis(d),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_color_field("Color: ", self.effect.color, link.callback(WipeMsg::Color)) }
+                { view_number_field("Delay per pixel (ms): ", self.effect.delay.num_milliseconds() as f64, 10.0, link.callback(WipeMsg::Delay)) }
+                { view_checkbox_field("Wipe from the end", self.effect.reverse, link.callback(WipeMsg::Reverse)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Larson {
+    effect: lights::effects::Larson,
+}
+
+pub(crate) enum LarsonMsg {
+    Color(LinSrgb<u8>),
+    Tail(f64),
+    Delay(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct LarsonProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Larson>>,
+    #[prop_or_default]
+    pub larson: lights::effects::Larson,
+}
+
+impl Component for Larson {
+    type Message = LarsonMsg;
+
+    type Properties = LarsonProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().larson.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().larson.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            LarsonMsg::Color(c) => self.effect.color = c,
+            LarsonMsg::Tail(t) => self.effect.tail = t.max(0.0) as usize,
+            LarsonMsg::Delay(d) => self.effect.delay = millis(d),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_color_field("Color: ", self.effect.color, link.callback(LarsonMsg::Color)) }
+                { view_number_field("Tail length: ", self.effect.tail as f64, 1.0, link.callback(LarsonMsg::Tail)) }
+                { view_number_field("Delay per pixel (ms): ", self.effect.delay.num_milliseconds() as f64, 5.0, link.callback(LarsonMsg::Delay)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Breathing {
+    effect: lights::effects::Breathing,
+}
+
+pub(crate) enum BreathingMsg {
+    Color(LinSrgb<u8>),
+    Period(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct BreathingProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Breathing>>,
+    #[prop_or_default]
+    pub breathing: lights::effects::Breathing,
+}
+
+impl Component for Breathing {
+    type Message = BreathingMsg;
+
+    type Properties = BreathingProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().breathing.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().breathing.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            BreathingMsg::Color(c) => self.effect.color = c,
+            BreathingMsg::Period(p) => self.effect.period = millis(p),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_color_field("Color: ", self.effect.color, link.callback(BreathingMsg::Color)) }
+                { view_number_field("One breath (ms): ", self.effect.period.num_milliseconds() as f64, 100.0, link.callback(BreathingMsg::Period)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Strobe {
+    effect: lights::effects::Strobe,
+}
+
+pub(crate) enum StrobeMsg {
+    Color(LinSrgb<u8>),
+    Period(f64),
+    On(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct StrobeProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Strobe>>,
+    #[prop_or_default]
+    pub strobe: lights::effects::Strobe,
+}
+
+impl Component for Strobe {
+    type Message = StrobeMsg;
+
+    type Properties = StrobeProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().strobe.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().strobe.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            StrobeMsg::Color(c) => self.effect.color = c,
+            StrobeMsg::Period(p) => self.effect.period = millis(p),
+            StrobeMsg::On(o) => self.effect.on = millis(o),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_color_field("Color: ", self.effect.color, link.callback(StrobeMsg::Color)) }
+                { view_number_field("Flash every (ms): ", self.effect.period.num_milliseconds() as f64, 10.0, link.callback(StrobeMsg::Period)) }
+                { view_number_field("Flash for (ms): ", self.effect.on.num_milliseconds() as f64, 5.0, link.callback(StrobeMsg::On)) }
+            </>
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Plasma {
+    effect: lights::effects::Plasma,
+}
+
+pub(crate) enum PlasmaMsg {
+    Speed(f64),
+    Wavelength(f64),
+    Brightness(f64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct PlasmaProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Plasma>>,
+    #[prop_or_default]
+    pub plasma: lights::effects::Plasma,
+}
+
+impl Component for Plasma {
+    type Message = PlasmaMsg;
+
+    type Properties = PlasmaProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().plasma.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().plasma.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            PlasmaMsg::Speed(s) => self.effect.speed = s as f32,
+            PlasmaMsg::Wavelength(w) => self.effect.wavelength = w.max(1.0) as f32,
+            PlasmaMsg::Brightness(b) => self.effect.brightness = b.clamp(0.0, 1.0) as f32,
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                { view_number_field("Speed (waves/s): ", self.effect.speed as f64, 0.05, link.callback(PlasmaMsg::Speed)) }
+                { view_number_field("Wavelength (px): ", self.effect.wavelength as f64, 1.0, link.callback(PlasmaMsg::Wavelength)) }
+                { view_number_field("Brightness (0-1): ", self.effect.brightness as f64, 0.05, link.callback(PlasmaMsg::Brightness)) }
+            </>
+        }
+    }
+}
@@ -3,7 +3,11 @@ use std::str::FromStr;
 use lights::effects::EffectType;
 use yew::prelude::*;
 
-use crate::utils::{view_ball, view_balls, view_composite, view_empty, view_glow, view_rainbow};
+use crate::utils::{
+    view_ball, view_balls, view_beat_flash, view_breathing, view_color_wipe, view_composite,
+    view_empty, view_fire, view_glow, view_larson, view_particles, view_plasma, view_rainbow,
+    view_signal_flash, view_spectrum, view_strobe, view_theater_chase, view_twinkle, view_vu_meter,
+};
 
 #[derive(Clone, Debug)]
 pub(crate) struct Composite {
@@ -153,6 +157,149 @@ impl Composite {
                     })
                 }
             }
+            (EffectType::SpectrumBars(s), first) => {
+                if first {
+                    view_spectrum(&s, &ctx.link(), |spectrum| {
+                        Msg::SetFirst(EffectType::SpectrumBars(spectrum))
+                    })
+                } else {
+                    view_spectrum(&s, &ctx.link(), |spectrum| {
+                        Msg::SetSecond(EffectType::SpectrumBars(spectrum))
+                    })
+                }
+            }
+            (EffectType::VuMeter(v), first) => {
+                if first {
+                    view_vu_meter(&v, &ctx.link(), |meter| {
+                        Msg::SetFirst(EffectType::VuMeter(meter))
+                    })
+                } else {
+                    view_vu_meter(&v, &ctx.link(), |meter| {
+                        Msg::SetSecond(EffectType::VuMeter(meter))
+                    })
+                }
+            }
+            (EffectType::BeatFlash(b), first) => {
+                if first {
+                    view_beat_flash(&b, &ctx.link(), |flash| {
+                        Msg::SetFirst(EffectType::BeatFlash(flash))
+                    })
+                } else {
+                    view_beat_flash(&b, &ctx.link(), |flash| {
+                        Msg::SetSecond(EffectType::BeatFlash(flash))
+                    })
+                }
+            }
+            (EffectType::SignalFlash(f), first) => {
+                if first {
+                    view_signal_flash(&f, &ctx.link(), |flash| {
+                        Msg::SetFirst(EffectType::SignalFlash(flash))
+                    })
+                } else {
+                    view_signal_flash(&f, &ctx.link(), |flash| {
+                        Msg::SetSecond(EffectType::SignalFlash(flash))
+                    })
+                }
+            }
+            (EffectType::Particles(p), first) => {
+                if first {
+                    view_particles(&p, &ctx.link(), |particles| {
+                        Msg::SetFirst(EffectType::Particles(particles))
+                    })
+                } else {
+                    view_particles(&p, &ctx.link(), |particles| {
+                        Msg::SetSecond(EffectType::Particles(particles))
+                    })
+                }
+            }
+            (EffectType::Fire(x), first) => {
+                if first {
+                    view_fire(&x, &ctx.link(), |fire| {
+                        Msg::SetFirst(EffectType::Fire(fire))
+                    })
+                } else {
+                    view_fire(&x, &ctx.link(), |fire| {
+                        Msg::SetSecond(EffectType::Fire(fire))
+                    })
+                }
+            }
+            (EffectType::Twinkle(x), first) => {
+                if first {
+                    view_twinkle(&x, &ctx.link(), |twinkle| {
+                        Msg::SetFirst(EffectType::Twinkle(twinkle))
+                    })
+                } else {
+                    view_twinkle(&x, &ctx.link(), |twinkle| {
+                        Msg::SetSecond(EffectType::Twinkle(twinkle))
+                    })
+                }
+            }
+            (EffectType::TheaterChase(x), first) => {
+                if first {
+                    view_theater_chase(&x, &ctx.link(), |chase| {
+                        Msg::SetFirst(EffectType::TheaterChase(chase))
+                    })
+                } else {
+                    view_theater_chase(&x, &ctx.link(), |chase| {
+                        Msg::SetSecond(EffectType::TheaterChase(chase))
+                    })
+                }
+            }
+            (EffectType::ColorWipe(x), first) => {
+                if first {
+                    view_color_wipe(&x, &ctx.link(), |wipe| {
+                        Msg::SetFirst(EffectType::ColorWipe(wipe))
+                    })
+                } else {
+                    view_color_wipe(&x, &ctx.link(), |wipe| {
+                        Msg::SetSecond(EffectType::ColorWipe(wipe))
+                    })
+                }
+            }
+            (EffectType::Larson(x), first) => {
+                if first {
+                    view_larson(&x, &ctx.link(), |larson| {
+                        Msg::SetFirst(EffectType::Larson(larson))
+                    })
+                } else {
+                    view_larson(&x, &ctx.link(), |larson| {
+                        Msg::SetSecond(EffectType::Larson(larson))
+                    })
+                }
+            }
+            (EffectType::Breathing(x), first) => {
+                if first {
+                    view_breathing(&x, &ctx.link(), |breathing| {
+                        Msg::SetFirst(EffectType::Breathing(breathing))
+                    })
+                } else {
+                    view_breathing(&x, &ctx.link(), |breathing| {
+                        Msg::SetSecond(EffectType::Breathing(breathing))
+                    })
+                }
+            }
+            (EffectType::Strobe(x), first) => {
+                if first {
+                    view_strobe(&x, &ctx.link(), |strobe| {
+                        Msg::SetFirst(EffectType::Strobe(strobe))
+                    })
+                } else {
+                    view_strobe(&x, &ctx.link(), |strobe| {
+                        Msg::SetSecond(EffectType::Strobe(strobe))
+                    })
+                }
+            }
+            (EffectType::Plasma(x), first) => {
+                if first {
+                    view_plasma(&x, &ctx.link(), |plasma| {
+                        Msg::SetFirst(EffectType::Plasma(plasma))
+                    })
+                } else {
+                    view_plasma(&x, &ctx.link(), |plasma| {
+                        Msg::SetSecond(EffectType::Plasma(plasma))
+                    })
+                }
+            }
             _ => {
                 html! {
                     <>
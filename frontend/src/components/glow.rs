@@ -1,10 +1,13 @@
 use chrono::Duration;
+use lights::palettes::PaletteRef;
 use palette::LinSrgb;
 
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+use crate::{components::PaletteField, utils::view_checkbox_field};
+
 #[derive(Clone, Debug)]
 pub(crate) struct Glow {
     effect: lights::effects::Glow,
@@ -20,8 +23,11 @@ pub(crate) enum Msg {
     AddColor,
     ReplaceColor(usize, LinSrgb<u8>),
     RemoveColor(usize),
-    Delay(i64),
+    Palette(Option<PaletteRef>),
+    Period(f64),
+    Smooth(bool),
     Steps(i64),
+    Tint(String),
 }
 
 #[derive(Clone, PartialEq, Properties, Debug)]
@@ -48,10 +54,15 @@ impl Component for Glow {
             Msg::AddColor => self.effect.add_color(LinSrgb::new(255, 0, 0)),
             Msg::ReplaceColor(idx, color) => self.effect.set_color(idx, color),
             Msg::RemoveColor(idx) => self.effect.remove_color(idx),
-            Msg::Delay(delay) => {
-                self.effect.delay = Duration::milliseconds(delay);
+            Msg::Palette(palette) => self.effect.palette = palette,
+            Msg::Period(period) => {
+                self.effect.period = Duration::milliseconds((period.max(0.1) * 1000.0) as i64);
             }
+            Msg::Smooth(smooth) => self.effect.smooth = smooth,
             Msg::Steps(steps) => self.effect.steps = steps as usize,
+            Msg::Tint(signal) => {
+                self.effect.tint = Some(signal).filter(|s| !s.is_empty());
+            }
         };
         ctx.props()
             .onupdate
@@ -98,19 +109,31 @@ impl Component for Glow {
 
         html! {
             <>
-                <div classes={ classes!("glow-colors") }>
-                    { for colors }
-                    <ybc::Control>
-                        <input type="button"
-                            onclick={
-                                ctx.link().callback(move |_| {
-                                    Msg::AddColor
-                                })
-                            }
-                            value={ "+" }
-                        />
-                    </ybc::Control>
-                </div>
+                <PaletteField
+                    palette={ ctx.props().glow.palette.clone() }
+                    onupdate={ Some(ctx.link().callback(Msg::Palette)) }
+                />
+                {
+                    if ctx.props().glow.palette.is_none() {
+                        html! {
+                            <div classes={ classes!("glow-colors") }>
+                                { for colors }
+                                <ybc::Control>
+                                    <input type="button"
+                                        onclick={
+                                            ctx.link().callback(move |_| {
+                                                Msg::AddColor
+                                            })
+                                        }
+                                        value={ "+" }
+                                    />
+                                </ybc::Control>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
                 <ybc::Field addons={ true }>
                     <label class="label">{ "Steps between colors: " }</label>
                     <ybc::Control classes={ classes!("has-addons") }>
@@ -162,56 +185,61 @@ impl Component for Glow {
                     </ybc::Control>
                 </ybc::Field>
                 <ybc::Field addons={ true }>
-                    <label class="label">{ "Delay between movements: " }</label>
+                    <label class="label">{ "Time for one cycle: " }</label>
                     <ybc::Control classes={ classes!("has-addons") }>
                         <input type="range"
                             class="input"
-                            min="10"
-                            max="1000"
-                            step="10"
-                            id="delay"
-                            name="delay"
+                            min="1"
+                            max="60"
+                            step="0.5"
+                            id="period"
+                            name="period"
                             onchange={
-                                ctx.link().callback(move |ty: Event| {
-                                    let target: HtmlInputElement = ty.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let value = target.value();
-                                    let delay = value.parse().unwrap_or(100);
-                                    Msg::Delay(delay)
+                                ctx.link().callback(move |e: Event| {
+                                    let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    Msg::Period(target.value().parse().unwrap_or(2.0))
                                 })
                             }
                             oninput={
-                                ctx.link().callback(move |ty:InputEvent| {
-                                    let event: Event = ty.dyn_into().unwrap_throw();
+                                ctx.link().callback(move |e: InputEvent| {
+                                    let event: Event = e.dyn_into().unwrap_throw();
                                     let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let value = target.value();
-                                    let delay = value.parse().unwrap_or(100);
-                                    Msg::Delay(delay)
+                                    Msg::Period(target.value().parse().unwrap_or(2.0))
                                 })
                             }
-                            value= { ctx.props().glow.delay.num_milliseconds().to_string() }
+                            value={ (ctx.props().glow.period.num_milliseconds() as f64 / 1000.0).to_string() }
                         />
                         <label>
-                            <input type="number" name="delay" id="delay_real" class="input"
+                            <input type="number" name="period" id="period_real" class="input"
+                                step="0.5"
                                 onchange={
-                                    ctx.link().callback(move |ty: Event| {
-                                        let target: HtmlInputElement = ty.target().unwrap_throw().dyn_into().unwrap_throw();
-                                        let value = target.value();
-                                        let delay = value.parse().unwrap_or(100);
-                                        Msg::Delay(delay)
+                                    ctx.link().callback(move |e: Event| {
+                                        let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                        Msg::Period(target.value().parse().unwrap_or(2.0))
                                     })
                                 }
-                                oninput={
-                                    ctx.link().callback(move |ty:InputEvent| {
-                                        let event: Event = ty.dyn_into().unwrap_throw();
-                                        let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                        let value = target.value();
-                                        let delay = value.parse().unwrap_or(100);
-                                            Msg::Delay(delay)
-                                    })
-                                }
-                                value= { ctx.props().glow.delay.num_milliseconds().to_string() }
-                            /><a class="button is-static">{ "ms" }</a></label>
+                                value={ (ctx.props().glow.period.num_milliseconds() as f64 / 1000.0).to_string() }
+                            /><a class="button is-static">{ "s" }</a></label>
+                    </ybc::Control>
+                </ybc::Field>
+                { view_checkbox_field("Blend smoothly", ctx.props().glow.smooth, ctx.link().callback(Msg::Smooth)) }
+                <ybc::Field>
+                    <label class="label" for="tint">{ "Tint by signal: " }</label>
+                    <ybc::Control>
+                        <input type="text"
+                            class="input"
+                            id="tint"
+                            placeholder="none"
+                            value={ ctx.props().glow.tint.clone().unwrap_or_default() }
+                            onchange={
+                                ctx.link().callback(move |e: Event| {
+                                    let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    Msg::Tint(target.value())
+                                })
+                            }
+                        />
                     </ybc::Control>
+                    <p class="help">{ "A color signal filters the glow, a number from 0 to 1 dims it." }</p>
                 </ybc::Field>
             </>
         }
@@ -0,0 +1,133 @@
+use gloo::{net::http::Request, utils::window};
+use serde_json::{json, Value};
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::HtmlInputElement;
+use yew::{platform::spawn_local, prelude::*};
+
+/// Logs in and out, when the backend asks for it.
+pub(crate) struct Login {
+    required: bool,
+    role: Option<String>,
+    password: String,
+    error: Option<String>,
+}
+
+pub(crate) enum Msg {
+    Auth(Value),
+    Password(String),
+    Login,
+    Failed(String),
+    Logout,
+}
+
+impl Component for Login {
+    type Message = Msg;
+
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let callback = ctx.link().callback(Msg::Auth);
+        spawn_local(async move {
+            let auth = Request::get("/auth")
+                .send()
+                .await
+                .expect("Need to get an auth response")
+                .json::<Value>()
+                .await
+                .unwrap_or_default();
+            callback.emit(auth);
+        });
+        Self {
+            required: false,
+            role: None,
+            password: String::new(),
+            error: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Auth(auth) => {
+                self.required = auth["required"].as_bool().unwrap_or_default();
+                self.role = auth["role"].as_str().map(ToOwned::to_owned);
+            }
+            Msg::Password(password) => {
+                self.password = password;
+                return false;
+            }
+            Msg::Login => {
+                let body = json!({ "password": self.password });
+                let callback = ctx.link().callback(Msg::Failed);
+                spawn_local(async move {
+                    let resp = Request::post("/login")
+                        .json(&body)
+                        .expect("json serialized properly")
+                        .send()
+                        .await
+                        .expect("Need to get a login response");
+                    if resp.ok() {
+                        // Everything else was fetched before logging in.
+                        window().location().reload().unwrap_throw();
+                    } else {
+                        let body = resp.json::<Value>().await.unwrap_or_default();
+                        callback.emit(
+                            body["error"]
+                                .as_str()
+                                .unwrap_or("Couldn't log in")
+                                .to_owned(),
+                        );
+                    }
+                });
+            }
+            Msg::Failed(error) => self.error = Some(error),
+            Msg::Logout => spawn_local(async {
+                Request::post("/logout")
+                    .send()
+                    .await
+                    .expect("Need to get a logout response");
+                window().location().reload().unwrap_throw();
+            }),
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if !self.required {
+            return html! {};
+        }
+        let link = ctx.link();
+        if let Some(role) = &self.role {
+            return html! {
+                <ybc::Box classes={ classes!("login") }>
+                    <span class="tag">{ role }</span>
+                    <ybc::Button onclick={ link.callback(|_| Msg::Logout) }>
+                        { "Log out" }
+                    </ybc::Button>
+                </ybc::Box>
+            };
+        }
+        html! {
+            <ybc::Box classes={ classes!("login") }>
+                <ybc::Field>
+                    <label class="label" for="login_password">{ "Password" }</label>
+                    <ybc::Control>
+                        <input class="input"
+                            type="password"
+                            id="login_password"
+                            onchange={ link.callback(|e: Event| {
+                                let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                Msg::Password(target.value())
+                            }) }
+                        />
+                    </ybc::Control>
+                </ybc::Field>
+                <ybc::Button onclick={ link.callback(|_| Msg::Login) }>
+                    { "Log in" }
+                </ybc::Button>
+                if let Some(error) = &self.error {
+                    <p class="help is-danger">{ error }</p>
+                }
+            </ybc::Box>
+        }
+    }
+}
@@ -1,19 +1,39 @@
+mod audio;
 mod ball;
 mod balls;
+mod classic;
 mod composite;
 mod glow;
+mod login;
+mod palettes;
+mod particles;
+mod plugin;
+mod presets;
 mod preview;
 mod rainbow;
+mod runescript;
 mod selector;
+mod signal_flash;
 mod applyform;
 mod history;
 
+pub(crate) use audio::{BeatFlash, SpectrumBars, VuMeter};
 pub(crate) use ball::Ball;
 pub(crate) use balls::Balls;
+pub(crate) use classic::{
+    Breathing, ColorWipe, Fire, Larson, Plasma, Strobe, TheaterChase, Twinkle,
+};
 pub(crate) use composite::Composite;
 pub(crate) use glow::Glow;
+pub(crate) use login::Login;
+pub(crate) use palettes::{PaletteField, Palettes};
+pub(crate) use particles::Particles;
+pub(crate) use plugin::Plugin;
+pub(crate) use presets::Presets;
 pub(crate) use preview::Preview;
 pub(crate) use rainbow::Rainbow;
+pub(crate) use runescript::Runescript;
 pub(crate) use selector::Selector;
+pub(crate) use signal_flash::SignalFlash;
 pub(crate) use applyform::ApplyForm;
 pub(crate) use history::HistoryList;
@@ -0,0 +1,382 @@
+use std::collections::BTreeMap;
+
+use gloo::net::http::Request;
+use lights::palettes::{self, Interpolation, Palette, PaletteRef, Stop};
+use palette::LinSrgb;
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::HtmlInputElement;
+use yew::{platform::spawn_local, prelude::*};
+
+use crate::utils::{view_color_field, view_number_field, view_select_field};
+
+const OWN_COLORS: &str = "Own colors";
+const CUSTOM: &str = "Custom gradient";
+
+/// Fetch the user palettes into our copy of the library, so the preview can
+/// draw effects that use them, and send back every palette name.
+fn fetch_palettes(callback: Callback<Vec<String>>) {
+    spawn_local(async move {
+        let user = Request::get("/palettes")
+            .send()
+            .await
+            .expect("Need to get a palettes response")
+            .json::<BTreeMap<String, Palette>>()
+            .await
+            .unwrap_or_default();
+        for (name, palette) in user {
+            palettes::set_user_palette(name, palette);
+        }
+        callback.emit(palettes::palette_names());
+    });
+}
+
+fn view_gradient(palette: &Palette) -> Html {
+    let stops = palette
+        .colors(16)
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            format!(
+                "rgb({}, {}, {}) {:.1}%",
+                c.red,
+                c.green,
+                c.blue,
+                i as f32 * 100.0 / 15.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    html! {
+        <div
+            class="palette-preview"
+            style={ format!("background: linear-gradient(to right, {});", stops) }
+        />
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct PaletteEditor {
+    palette: Palette,
+}
+
+pub(crate) enum EditorMsg {
+    Interpolation(String),
+    Position(usize, f64),
+    Color(usize, LinSrgb<u8>),
+    AddStop,
+    RemoveStop(usize),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct EditorProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<Palette>>,
+    #[prop_or_default]
+    pub palette: Palette,
+}
+
+impl Component for PaletteEditor {
+    type Message = EditorMsg;
+
+    type Properties = EditorProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            palette: ctx.props().palette.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.palette = ctx.props().palette.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let palette = &mut self.palette;
+        match msg {
+            EditorMsg::Interpolation(name) => {
+                palette.interpolation = Interpolation::from_name(&name).unwrap_or_default()
+            }
+            EditorMsg::Position(idx, position) => {
+                let color = palette.stops()[idx].color;
+                palette.set_stop(idx, Stop::new(position.clamp(0.0, 1.0) as f32, color));
+            }
+            EditorMsg::Color(idx, color) => {
+                let position = palette.stops()[idx].position;
+                palette.set_stop(idx, Stop::new(position, color));
+            }
+            EditorMsg::AddStop => {
+                let color = palette.sample(0.5);
+                palette.add_stop(Stop::new(0.5, color));
+            }
+            EditorMsg::RemoveStop(idx) => palette.remove_stop(idx),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.palette.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let interpolations = Interpolation::iter_names().collect::<Vec<_>>();
+        let stops = self.palette.stops().iter().enumerate().map(|(idx, stop)| {
+            html! {
+                <ybc::Field classes={ classes!("palette-stop") }>
+                    { view_number_field("At (0-1): ", stop.position as f64, 0.05, link.callback(move |p| EditorMsg::Position(idx, p))) }
+                    { view_color_field("", stop.color, link.callback(move |c| EditorMsg::Color(idx, c))) }
+                    <ybc::Control>
+                        <input type="button"
+                            onclick={ link.callback(move |_| EditorMsg::RemoveStop(idx)) }
+                            value={ "-" }
+                        />
+                    </ybc::Control>
+                </ybc::Field>
+            }
+        });
+
+        html! {
+            <>
+                { view_gradient(&self.palette) }
+                { view_select_field("Blend in: ", &interpolations, self.palette.interpolation.name(), link.callback(EditorMsg::Interpolation)) }
+                { for stops }
+                <ybc::Control>
+                    <input type="button"
+                        onclick={ link.callback(|_| EditorMsg::AddStop) }
+                        value={ "Add stop" }
+                    />
+                </ybc::Control>
+            </>
+        }
+    }
+}
+
+/// Picks where an effect gets its colors: its own list, a named palette, or a
+/// gradient of its own.
+#[derive(Clone, Debug)]
+pub(crate) struct PaletteField {
+    names: Vec<String>,
+}
+
+pub(crate) enum FieldMsg {
+    Names(Vec<String>),
+    Select(String),
+    Inline(Palette),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct FieldProps {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<Option<PaletteRef>>>,
+    #[prop_or_default]
+    pub palette: Option<PaletteRef>,
+}
+
+impl Component for PaletteField {
+    type Message = FieldMsg;
+
+    type Properties = FieldProps;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        fetch_palettes(ctx.link().callback(FieldMsg::Names));
+        Self {
+            names: palettes::palette_names(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let palette = match msg {
+            FieldMsg::Names(names) => {
+                self.names = names;
+                return true;
+            }
+            FieldMsg::Select(name) if name == OWN_COLORS => None,
+            FieldMsg::Select(name) if name == CUSTOM => {
+                let current = ctx.props().palette.as_ref().map(PaletteRef::resolve);
+                Some(PaletteRef::Inline(current.unwrap_or_default()))
+            }
+            FieldMsg::Select(name) => Some(PaletteRef::Named(name)),
+            FieldMsg::Inline(palette) => Some(PaletteRef::Inline(palette)),
+        };
+        ctx.props().onupdate.as_ref().map(|u| u.emit(palette));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let options = [OWN_COLORS, CUSTOM]
+            .into_iter()
+            .chain(self.names.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        let (selected, below) = match &ctx.props().palette {
+            None => (OWN_COLORS, html! {}),
+            Some(PaletteRef::Inline(palette)) => (
+                CUSTOM,
+                html! {
+                    <PaletteEditor
+                        palette={ palette.clone() }
+                        onupdate={ Some(link.callback(FieldMsg::Inline)) }
+                    />
+                },
+            ),
+            Some(PaletteRef::Named(name)) => (
+                name.as_str(),
+                view_gradient(&PaletteRef::Named(name.clone()).resolve()),
+            ),
+        };
+        html! {
+            <>
+                { view_select_field("Colors from: ", &options, selected, link.callback(FieldMsg::Select)) }
+                { below }
+            </>
+        }
+    }
+}
+
+/// The palette library, for making, changing and deleting user palettes.
+#[derive(Clone, Debug)]
+pub(crate) struct Palettes {
+    names: Vec<String>,
+    name: String,
+    palette: Palette,
+    error: Option<String>,
+}
+
+pub(crate) enum Msg {
+    Names(Vec<String>),
+    Open(String),
+    Name(String),
+    Edit(Palette),
+    Save,
+    Delete,
+    Done(Option<String>),
+}
+
+impl Component for Palettes {
+    type Message = Msg;
+
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        fetch_palettes(ctx.link().callback(Msg::Names));
+        Self {
+            names: palettes::palette_names(),
+            name: String::new(),
+            palette: Palette::default(),
+            error: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Names(names) => self.names = names,
+            Msg::Open(name) => {
+                self.palette = palettes::palette(&name).unwrap_or_default();
+                self.name = name;
+                self.error = None;
+            }
+            Msg::Name(name) => self.name = name,
+            Msg::Edit(palette) => self.palette = palette,
+            Msg::Save => {
+                let url = format!("/palettes/{}", self.name);
+                let palette = self.palette.clone();
+                let callback = ctx.link().callback(Msg::Done);
+                spawn_local(async move {
+                    let resp = Request::post(&url)
+                        .json(&palette)
+                        .expect("json serialized properly")
+                        .send()
+                        .await
+                        .expect("Need to get a palette response");
+                    let error = if resp.ok() {
+                        None
+                    } else {
+                        Some(resp.text().await.unwrap_or_default())
+                    };
+                    callback.emit(error);
+                });
+            }
+            Msg::Delete => {
+                let url = format!("/palettes/{}", self.name);
+                palettes::remove_user_palette(&self.name);
+                let callback = ctx.link().callback(Msg::Done);
+                spawn_local(async move {
+                    Request::delete(&url)
+                        .send()
+                        .await
+                        .expect("Need to get a palette response");
+                    callback.emit(None);
+                });
+            }
+            Msg::Done(error) => {
+                self.error = error;
+                fetch_palettes(ctx.link().callback(Msg::Names));
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let builtin = palettes::is_builtin(&self.name);
+        let user = !builtin && palettes::user_palettes().contains_key(&self.name);
+        let names = self.names.iter().cloned().map(|name| {
+            let onclick = link.callback(move |_| Msg::Open(name.clone()));
+            html! {
+                <ybc::Button {onclick}>{ name.clone() }</ybc::Button>
+            }
+        });
+        html! {
+            <>
+                <h4 class="title is-4">{ "Palettes" }</h4>
+                <ybc::Buttons>{ for names }</ybc::Buttons>
+                <ybc::Field>
+                    <label class="label" for="palette_name">{ "Name: " }</label>
+                    <ybc::Control>
+                        <input type="text"
+                            class="input"
+                            id="palette_name"
+                            value={ self.name.clone() }
+                            onchange={ link.callback(|e: Event| {
+                                let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                Msg::Name(target.value())
+                            }) }
+                        />
+                    </ybc::Control>
+                    {
+                        if builtin {
+                            html! { <p class="help">{ "Built-in palettes can't be changed, save a copy under a new name." }</p> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                </ybc::Field>
+                <PaletteEditor
+                    palette={ self.palette.clone() }
+                    onupdate={ Some(link.callback(Msg::Edit)) }
+                />
+                <ybc::Buttons>
+                    <ybc::Button
+                        disabled={ builtin || self.name.is_empty() }
+                        onclick={ link.callback(|_| Msg::Save) }
+                    >
+                        { "Save palette" }
+                    </ybc::Button>
+                    <ybc::Button
+                        disabled={ !user }
+                        onclick={ link.callback(|_| Msg::Delete) }
+                    >
+                        { "Delete palette" }
+                    </ybc::Button>
+                </ybc::Buttons>
+                {
+                    match &self.error {
+                        Some(error) => html! { <p class="help is-danger">{ error }</p> },
+                        None => html! {},
+                    }
+                }
+            </>
+        }
+    }
+}
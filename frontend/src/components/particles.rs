@@ -0,0 +1,239 @@
+use chrono::Duration;
+use lights::effects::{Boundary, Emitter, EmitterPosition};
+use palette::LinSrgb;
+use yew::prelude::*;
+
+use crate::utils::{view_color_field, view_number_field, view_select_field};
+
+const BOUNDARIES: [&str; 3] = ["Die", "Wrap", "Bounce"];
+const POSITIONS: [&str; 4] = ["Start", "End", "Center", "Random"];
+
+#[derive(Clone, Debug)]
+pub(crate) struct Particles {
+    effect: lights::effects::Particles,
+}
+
+pub(crate) enum EmitterMsg {
+    Rate(f64),
+    Burst(f64),
+    Position(String),
+    Velocity(f64),
+    Spread(f64),
+    Lifetime(f64),
+    AddColor,
+    SetColor(usize, LinSrgb<u8>),
+    RemoveColor(usize),
+}
+
+pub(crate) enum Msg {
+    Preset(String),
+    Acceleration(f64),
+    Size(f64),
+    Trail(f64),
+    Boundary(String),
+    Restitution(f64),
+    MaxParticles(f64),
+    AddEmitter,
+    RemoveEmitter(usize),
+    Emitter(usize, EmitterMsg),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct Props {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::Particles>>,
+    #[prop_or_default]
+    pub particles: lights::effects::Particles,
+}
+
+fn boundary_name(boundary: &Boundary) -> &'static str {
+    match boundary {
+        Boundary::Die => "Die",
+        Boundary::Wrap => "Wrap",
+        Boundary::Bounce { .. } => "Bounce",
+    }
+}
+
+fn position_name(position: &EmitterPosition) -> &'static str {
+    match position {
+        EmitterPosition::Start => "Start",
+        EmitterPosition::End => "End",
+        EmitterPosition::Center => "Center",
+        EmitterPosition::Random => "Random",
+    }
+}
+
+fn update_emitter(emitter: &mut Emitter, msg: EmitterMsg) {
+    match msg {
+        EmitterMsg::Rate(r) => emitter.rate = r.max(0.0) as f32,
+        EmitterMsg::Burst(b) => emitter.burst = b.max(1.0) as usize,
+        EmitterMsg::Position(p) => {
+            emitter.position = match p.as_str() {
+                "End" => EmitterPosition::End,
+                "Center" => EmitterPosition::Center,
+                "Random" => EmitterPosition::Random,
+                _ => EmitterPosition::Start,
+            }
+        }
+        EmitterMsg::Velocity(v) => emitter.velocity = v as f32,
+        EmitterMsg::Spread(s) => emitter.velocity_spread = s.abs() as f32,
+        EmitterMsg::Lifetime(l) => {
+            emitter.lifetime =
+                Some(Duration::milliseconds(l as i64)).filter(|l| *l > Duration::zero())
+        }
+        EmitterMsg::AddColor => emitter.colors.push(LinSrgb::new(255, 255, 255)),
+        EmitterMsg::SetColor(idx, color) => emitter.colors[idx] = color,
+        EmitterMsg::RemoveColor(idx) => {
+            emitter.colors.remove(idx);
+        }
+    }
+}
+
+impl Component for Particles {
+    type Message = Msg;
+
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().particles.clone(),
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        self.effect = ctx.props().particles.clone();
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let effect = &mut self.effect;
+        match msg {
+            Msg::Preset(name) => {
+                if let Some(preset) = lights::effects::Particles::preset(&name) {
+                    *effect = preset;
+                }
+            }
+            Msg::Acceleration(a) => effect.acceleration = a as f32,
+            Msg::Size(s) => effect.size = s.max(0.5) as f32,
+            Msg::Trail(t) => effect.trail = Duration::milliseconds(t.max(0.0) as i64),
+            Msg::Boundary(b) => {
+                effect.boundary = match b.as_str() {
+                    "Wrap" => Boundary::Wrap,
+                    "Bounce" => Boundary::Bounce { restitution: 1.0 },
+                    _ => Boundary::Die,
+                }
+            }
+            Msg::Restitution(r) => {
+                if let Boundary::Bounce { restitution } = &mut effect.boundary {
+                    *restitution = r.clamp(0.0, 1.0) as f32;
+                }
+            }
+            Msg::MaxParticles(m) => effect.max_particles = m.max(1.0) as usize,
+            Msg::AddEmitter => effect.emitters.push(Emitter::default()),
+            Msg::RemoveEmitter(idx) => {
+                effect.emitters.remove(idx);
+            }
+            Msg::Emitter(idx, msg) => update_emitter(&mut effect.emitters[idx], msg),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let presets = lights::effects::Particles::preset_names().collect::<Vec<_>>();
+        let emitters = self
+            .effect
+            .emitters
+            .iter()
+            .enumerate()
+            .map(|(idx, emitter)| self.view_emitter(ctx, idx, emitter));
+
+        html! {
+            <>
+                <ybc::Field>
+                    <label class="label">{ "Start from: " }</label>
+                    <ybc::Control>
+                        <ybc::Buttons>
+                            { for presets.into_iter().map(|name| html! {
+                                <ybc::Button onclick={ link.callback(move |_| Msg::Preset(name.to_owned())) }>
+                                    { name }
+                                </ybc::Button>
+                            }) }
+                        </ybc::Buttons>
+                    </ybc::Control>
+                </ybc::Field>
+                { view_number_field("Acceleration (px/s²): ", self.effect.acceleration as f64, 1.0, link.callback(Msg::Acceleration)) }
+                { view_number_field("Size (px): ", self.effect.size as f64, 0.5, link.callback(Msg::Size)) }
+                { view_number_field("Trail fade (ms): ", self.effect.trail.num_milliseconds() as f64, 50.0, link.callback(Msg::Trail)) }
+                { view_number_field("Max particles: ", self.effect.max_particles as f64, 10.0, link.callback(Msg::MaxParticles)) }
+                { view_select_field("At the ends: ", &BOUNDARIES, boundary_name(&self.effect.boundary), link.callback(Msg::Boundary)) }
+                {
+                    if let Boundary::Bounce { restitution } = self.effect.boundary {
+                        view_number_field("Speed kept on bounce (0-1): ", restitution as f64, 0.05, link.callback(Msg::Restitution))
+                    } else {
+                        html! {}
+                    }
+                }
+                { for emitters }
+                <ybc::Control>
+                    <input type="button"
+                        onclick={ link.callback(|_| Msg::AddEmitter) }
+                        value={ "Add emitter" }
+                    />
+                </ybc::Control>
+            </>
+        }
+    }
+}
+
+impl Particles {
+    fn view_emitter(&self, ctx: &Context<Self>, idx: usize, emitter: &Emitter) -> Html {
+        let link = ctx.link();
+        let send = move |f: fn(f64) -> EmitterMsg| link.callback(move |v| Msg::Emitter(idx, f(v)));
+        let colors = emitter.colors.iter().cloned().enumerate().map(|(c, color)| {
+            html! {
+                <ybc::Field>
+                    { view_color_field("", color, link.callback(move |color| Msg::Emitter(idx, EmitterMsg::SetColor(c, color)))) }
+                    <ybc::Control>
+                        <input type="button"
+                            onclick={ link.callback(move |_| Msg::Emitter(idx, EmitterMsg::RemoveColor(c))) }
+                            value={ "-" }
+                        />
+                    </ybc::Control>
+                </ybc::Field>
+            }
+        });
+        let lifetime = emitter.lifetime.map(|l| l.num_milliseconds()).unwrap_or(0);
+
+        html! {
+            <ybc::Box>
+                <h5 class="title is-5">{ format!("Emitter {}", idx + 1) }</h5>
+                { view_select_field("Position: ", &POSITIONS, position_name(&emitter.position), link.callback(move |p| Msg::Emitter(idx, EmitterMsg::Position(p)))) }
+                { view_number_field("Spawns per second: ", emitter.rate as f64, 0.1, send(EmitterMsg::Rate)) }
+                { view_number_field("Particles per spawn: ", emitter.burst as f64, 1.0, send(EmitterMsg::Burst)) }
+                { view_number_field("Velocity (px/s): ", emitter.velocity as f64, 1.0, send(EmitterMsg::Velocity)) }
+                { view_number_field("Velocity spread (px/s): ", emitter.velocity_spread as f64, 1.0, send(EmitterMsg::Spread)) }
+                { view_number_field("Lifetime (ms, 0 is forever): ", lifetime as f64, 100.0, send(EmitterMsg::Lifetime)) }
+                <div classes={ classes!("glow-colors") }>
+                    { for colors }
+                    <ybc::Control>
+                        <input type="button"
+                            onclick={ link.callback(move |_| Msg::Emitter(idx, EmitterMsg::AddColor)) }
+                            value={ "+" }
+                        />
+                    </ybc::Control>
+                </div>
+                <ybc::Control>
+                    <input type="button"
+                        onclick={ link.callback(move |_| Msg::RemoveEmitter(idx)) }
+                        value={ "Remove emitter" }
+                    />
+                </ybc::Control>
+            </ybc::Box>
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use gloo::net::http::Request;
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::HtmlSelectElement;
+use yew::{platform::spawn_local, prelude::*};
+
+#[derive(Clone, Debug)]
+pub(crate) struct Plugin {
+    available: Vec<String>,
+}
+
+pub(crate) enum Msg {
+    Available(Vec<String>),
+    Select(String),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct Props {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<String>>,
+    #[prop_or_default]
+    pub plugin: String,
+}
+
+impl Component for Plugin {
+    type Message = Msg;
+
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let callback = ctx.link().callback(Msg::Available);
+        spawn_local(async move {
+            let names = Request::get("/plugins")
+                .send()
+                .await
+                .expect("Need to get a plugins response")
+                .json::<Vec<String>>()
+                .await
+                .unwrap_or_default();
+            callback.emit(names);
+        });
+        Self { available: vec![] }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Available(names) => {
+                // A new or stale effect names no plugin we have, so pick one.
+                let plugin = &ctx.props().plugin;
+                if !names.contains(plugin) {
+                    if let (Some(first), Some(onupdate)) = (names.first(), &ctx.props().onupdate) {
+                        onupdate.emit(first.clone());
+                    }
+                }
+                self.available = names;
+            }
+            Msg::Select(name) => {
+                ctx.props().onupdate.as_ref().map(|u| u.emit(name));
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.available.is_empty() {
+            return html! {
+                <p>{ "No plugins uploaded yet. POST a .wasm module to /plugins/<name> to add one." }</p>
+            };
+        }
+        let selected = &ctx.props().plugin;
+        let options = self.available.iter().map(|name| {
+            html! {
+                <option value={ name.clone() } selected={ name == selected }>{ name }</option>
+            }
+        });
+        html! {
+            <ybc::Field>
+                <label for="plugin" class="label">{ "Plugin: " }</label>
+                <ybc::Control>
+                    <div class="select">
+                        <select
+                            id="plugin"
+                            onchange={ ctx.link().callback(|e: Event| {
+                                let target: HtmlSelectElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                Msg::Select(target.value())
+                            }) }
+                        >
+                            <option value="" disabled=true selected={ selected.is_empty() }>{ "Pick a plugin" }</option>
+                            { for options }
+                        </select>
+                    </div>
+                </ybc::Control>
+            </ybc::Field>
+        }
+    }
+}
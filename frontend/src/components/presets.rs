@@ -0,0 +1,176 @@
+use gloo::net::http::Request;
+use serde_json::Value;
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::{File, HtmlInputElement};
+use yew::{platform::spawn_local, prelude::*};
+
+use crate::utils::view_select_field;
+
+const FORMATS: &[&str] = &["JSON", "MsgPack"];
+const CONFLICTS: &[&str] = &["Skip", "Overwrite", "Rename"];
+
+/// Downloads presets as bundles and imports bundles from other strips.
+pub(crate) struct Presets {
+    format: String,
+    conflict: String,
+    file: Option<File>,
+    /// How the last import went.
+    report: Option<Result<Value, String>>,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub(crate) struct Props {
+    /// The saved presets, kept up to date by the app.
+    #[prop_or_default]
+    pub names: Vec<String>,
+}
+
+pub(crate) enum Msg {
+    Format(String),
+    Conflict(String),
+    File(Option<File>),
+    Import,
+    Imported(Result<Value, String>),
+}
+
+impl Component for Presets {
+    type Message = Msg;
+
+    type Properties = Props;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            format: FORMATS[0].to_owned(),
+            conflict: CONFLICTS[0].to_owned(),
+            file: None,
+            report: None,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Format(format) => self.format = format,
+            Msg::Conflict(conflict) => self.conflict = conflict,
+            Msg::File(file) => self.file = file,
+            Msg::Import => {
+                let Some(file) = self.file.clone() else {
+                    return false;
+                };
+                let mime = if file.name().ends_with(".msgpack") {
+                    "application/msgpack"
+                } else {
+                    "application/json"
+                };
+                let url = format!("/presets/import?conflict={}", self.conflict.to_lowercase());
+                let callback = ctx.link().callback(Msg::Imported);
+                spawn_local(async move {
+                    let resp = Request::post(&url)
+                        .header("Content-Type", mime)
+                        .body(file)
+                        .expect("a file is a body")
+                        .send()
+                        .await
+                        .expect("Need to get an import response");
+                    let ok = resp.ok();
+                    let body = resp.json::<Value>().await.unwrap_or_default();
+                    callback.emit(if ok {
+                        Ok(body)
+                    } else {
+                        Err(body["error"].as_str().unwrap_or("Import failed").to_owned())
+                    });
+                });
+            }
+            // The new names come from the app.
+            Msg::Imported(report) => self.report = Some(report),
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let query = format!("?format={}", self.format.to_lowercase());
+        let presets = ctx.props().names.iter().map(|name| {
+            html! {
+                <li>
+                    <a href={ format!("/presets/{}/export{}", name, query) } download="">
+                        { name }
+                    </a>
+                </li>
+            }
+        });
+        html! {
+            <>
+                <h4 class="title is-4">{ "Presets" }</h4>
+                { view_select_field("Format", FORMATS, &self.format, link.callback(Msg::Format)) }
+                <ul>{ for presets }</ul>
+                <a class="button" href={ format!("/presets/export{}", query) } download="">
+                    { "Export all" }
+                </a>
+                <ybc::Field>
+                    <label class="label" for="preset_bundle">{ "Import a bundle" }</label>
+                    <ybc::Control>
+                        <input type="file"
+                            id="preset_bundle"
+                            accept=".json,.msgpack"
+                            onchange={ link.callback(|e: Event| {
+                                let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                Msg::File(target.files().and_then(|files| files.get(0)))
+                            }) }
+                        />
+                    </ybc::Control>
+                </ybc::Field>
+                { view_select_field("When a name is taken", CONFLICTS, &self.conflict, link.callback(Msg::Conflict)) }
+                <ybc::Button
+                    disabled={ self.file.is_none() }
+                    onclick={ link.callback(|_| Msg::Import) }
+                >
+                    { "Import" }
+                </ybc::Button>
+                { self.view_report() }
+            </>
+        }
+    }
+}
+
+impl Presets {
+    fn view_report(&self) -> Html {
+        let report = match &self.report {
+            Some(Ok(report)) => report,
+            Some(Err(error)) => return html! { <p class="help is-danger">{ error }</p> },
+            None => return html! {},
+        };
+        let count = |kind: &str, outcome: &str| {
+            let value = &report[kind][outcome];
+            value
+                .as_array()
+                .map(Vec::len)
+                .or_else(|| value.as_object().map(|o| o.len()))
+                .unwrap_or_default()
+        };
+        let lines = ["presets", "scripts", "palettes"].map(|kind| {
+            format!(
+                "{}: {} imported, {} renamed, {} skipped, {} failed",
+                kind,
+                count(kind, "imported"),
+                count(kind, "renamed"),
+                count(kind, "skipped"),
+                count(kind, "failed"),
+            )
+        });
+        let failures = report["presets"]["failed"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, error)| {
+                html! {
+                    <li class="has-text-danger">{ format!("{}: {}", name, error.as_str().unwrap_or_default()) }</li>
+                }
+            });
+        html! {
+            <div class="help">
+                { for lines.into_iter().map(|line| html! { <p>{ line }</p> }) }
+                <ul>{ for failures }</ul>
+            </div>
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use anyhow::anyhow;
 use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
 use gloo::timers::callback::Timeout;
-use lights::effects::{Effect, EffectType};
+use lights::effects::{drain_script_output, Effect, EffectType};
 use palette::LinSrgb;
 use wasm_bindgen::{JsCast, JsValue};
-use web_sys::HtmlCanvasElement;
+use web_sys::{HtmlCanvasElement, HtmlInputElement};
 use yew::prelude::*;
 
+use crate::{components::runescript::fetch_library, events::EventStream};
+
 #[derive(Clone, PartialEq, Properties)]
 pub(crate) struct Props {
     #[prop_or(100)]
@@ -16,15 +18,44 @@ pub(crate) struct Props {
     pub effect: EffectType,
 }
 
+/// How many lines of script output we hang on to for the console.
+const CONSOLE_LINES: usize = 100;
+
+/// How far into an effect the scrubber reaches, in seconds.
+const SCRUB_SECONDS: f64 = 60.0;
+
+/// Frames a second to ask the strip for.
+const STREAM_FPS: u32 = 20;
+
 pub(crate) enum Msg {
     Tick(DateTime<Utc>),
+    ClearConsole,
+    /// Show the effect this many seconds after it started.
+    Scrub(f64),
+    /// Go back to running the effect in real time.
+    Live,
+    /// Show what the strip is showing, rather than running the effect here.
+    Strip(bool),
+    /// A frame from the strip, as hex RGB.
+    Frame(String),
+    /// Our copy of the script library is up to date.
+    Library,
 }
 
 pub(crate) struct Preview {
     pixels: Vec<LinSrgb<u8>>,
     timer: Option<Timeout>,
     canvas: NodeRef,
-    effect: Box<dyn Effect>,
+    /// `None` once the effect failed to build or render, see `error`.
+    effect: Option<Box<dyn Effect>>,
+    error: Option<String>,
+    console: Vec<String>,
+    /// When the effect was loaded, which is where scrubbing counts from.
+    start: DateTime<Utc>,
+    /// Seconds from `start` while scrubbing, `None` while live.
+    scrub: Option<f64>,
+    /// Set while showing the strip instead of the effect.
+    stream: Option<EventStream>,
 }
 
 impl Component for Preview {
@@ -34,62 +65,241 @@ impl Component for Preview {
 
     fn create(ctx: &Context<Self>) -> Self {
         let pixels = vec![Default::default(); ctx.props().length];
-        let effect = ctx.props().effect.clone().into_inner();
         let mut this = Self {
             pixels,
-            effect,
+            effect: None,
+            error: None,
+            console: vec![],
+            start: now(),
+            scrub: None,
+            stream: None,
             timer: None,
             canvas: Default::default(),
         };
-        let dur = this
-            .effect
-            .render(&mut this.pixels, now())
-            .unwrap_or(Duration::milliseconds(50));
-        this.set_timer(ctx, dur);
+        this.load_effect(ctx);
+        this.set_timer(ctx, Duration::milliseconds(50));
+        fetch_library(ctx.link().callback(|_| Msg::Library));
         this
     }
 
     fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
         if ctx.props().effect != old_props.effect {
-            self.effect = ctx.props().effect.clone().into_inner();
+            self.load_effect(ctx);
+            if let Some(secs) = self.scrub {
+                if ctx.props().effect.is_seekable() {
+                    self.draw_at(secs);
+                } else {
+                    self.scrub = None;
+                }
+            }
+            if self.timer.is_none() && self.scrub.is_none() && self.stream.is_none() {
+                self.set_timer(ctx, Duration::milliseconds(50));
+            }
         }
         true
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
+            Msg::Tick(_) if self.scrub.is_some() || self.stream.is_some() => false,
             Msg::Tick(t) => {
                 self.pixels
                     .iter_mut()
                     .for_each(|c| *c = LinSrgb::new(0, 0, 0));
                 self.timer = None;
-                let dur = self
-                    .effect
-                    .render(&mut self.pixels, t)
-                    .unwrap_or(Duration::milliseconds(50));
-                if self.render_pixels().is_ok() {
+                let Some(effect) = self.effect.as_mut() else {
+                    return true;
+                };
+                let dur = match effect.render(&mut self.pixels, t) {
+                    Ok(dur) => Some(dur),
+                    Err(e) => {
+                        // Most likely a script that ran out of budget, stop
+                        // running it rather than erroring on every tick.
+                        self.error = Some(e.to_string());
+                        self.effect = None;
+                        None
+                    }
+                };
+                self.collect_output();
+                if self.render_pixels().is_err() {
+                    log::error!("Error rendering pixels");
+                } else if let Some(dur) = dur {
                     self.set_timer(ctx, dur);
-                } else {
+                }
+                true
+            }
+            Msg::ClearConsole => {
+                self.console.clear();
+                true
+            }
+            Msg::Scrub(secs) => {
+                self.timer = None;
+                self.scrub = Some(secs);
+                self.draw_at(secs);
+                true
+            }
+            Msg::Live => {
+                self.scrub = None;
+                if self.timer.is_none() {
+                    self.set_timer(ctx, Duration::milliseconds(50));
+                }
+                true
+            }
+            Msg::Strip(true) => {
+                self.timer = None;
+                self.scrub = None;
+                let url = format!("/live?fps={}", STREAM_FPS);
+                let callback = ctx.link().callback(|(_, frame)| Msg::Frame(frame));
+                match EventStream::open(&url, &["frame"], callback) {
+                    Ok(stream) => self.stream = Some(stream),
+                    Err(e) => self.error = Some(format!("Couldn't watch the strip: {:?}", e)),
+                }
+                true
+            }
+            Msg::Strip(false) => {
+                self.stream = None;
+                // The strip's frames may not have been this preview's length.
+                self.pixels = vec![Default::default(); ctx.props().length];
+                self.load_effect(ctx);
+                self.set_timer(ctx, Duration::milliseconds(50));
+                true
+            }
+            Msg::Frame(_) if self.stream.is_none() => false,
+            Msg::Frame(frame) => {
+                let Ok(bytes) = hex::decode(frame) else {
+                    log::error!("Bad frame from the strip");
+                    return false;
+                };
+                self.pixels = bytes
+                    .chunks_exact(3)
+                    .map(|rgb| LinSrgb::new(rgb[0], rgb[1], rgb[2]))
+                    .collect();
+                if self.render_pixels().is_err() {
                     log::error!("Error rendering pixels");
                 }
+                false
+            }
+            // Only scripts could have been missing a module.
+            Msg::Library if !ctx.props().effect.is_scripted() => false,
+            Msg::Library => {
+                self.load_effect(ctx);
                 true
             }
         }
     }
 
-    fn view(&self, _ctx: &Context<Self>) -> Html {
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let error = self.error.as_ref().map(|e| {
+            html! {
+                <ybc::Notification classes={ classes!("is-danger") }>{ e }</ybc::Notification>
+            }
+        });
+        let console = (!self.console.is_empty()).then(|| {
+            html! {
+                <div class="preview-console">
+                    <pre style="max-height: 10em; overflow-y: auto;">
+                        { self.console.join("\n") }
+                    </pre>
+                    <ybc::Button
+                        classes={ classes!("is-small") }
+                        onclick={ ctx.link().callback(|_| Msg::ClearConsole) }>
+                        { "Clear" }
+                    </ybc::Button>
+                </div>
+            }
+        });
+        let streaming = self.stream.is_some();
+        let strip = html! {
+            <ybc::Button
+                classes={ classes!("is-small", streaming.then_some("is-primary")) }
+                onclick={ ctx.link().callback(move |_| Msg::Strip(!streaming)) }>
+                { "From the strip" }
+            </ybc::Button>
+        };
+        let scrubber = (ctx.props().effect.is_seekable() && !streaming).then(|| {
+            let secs = self.scrub.unwrap_or_default();
+            let oninput = ctx.link().batch_callback(|e: InputEvent| {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                input.value().parse().ok().map(Msg::Scrub)
+            });
+            html! {
+                <div class="preview-scrubber">
+                    <input
+                        type="range"
+                        min="0"
+                        max={ SCRUB_SECONDS.to_string() }
+                        step="0.02"
+                        value={ secs.to_string() }
+                        { oninput } />
+                    <span>{ format!("{:.2}s", secs) }</span>
+                    <ybc::Button
+                        classes={ classes!("is-small", self.scrub.is_none().then_some("is-primary")) }
+                        onclick={ ctx.link().callback(|_| Msg::Live) }>
+                        { "Live" }
+                    </ybc::Button>
+                </div>
+            }
+        });
         html! {
-            <canvas
-                width=1000
-                height=100
-                ref={ self.canvas.clone() }
-                style={ "width: 100%; height: 50px;" }
-             />
+            <>
+                <canvas
+                    width=1000
+                    height=100
+                    ref={ self.canvas.clone() }
+                    style={ "width: 100%; height: 50px;" }
+                 />
+                { strip }
+                { for scrubber }
+                { for error }
+                { for console }
+            </>
         }
     }
 }
 
 impl Preview {
+    fn load_effect(&mut self, ctx: &Context<Self>) {
+        match ctx.props().effect.clone().try_into_inner() {
+            Ok(effect) => {
+                self.effect = Some(effect);
+                self.error = None;
+                self.start = now();
+            }
+            Err(e) => {
+                log::error!("Error loading effect: {:?}", e);
+                self.effect = None;
+                self.error = Some(e.to_string());
+            }
+        }
+        self.collect_output();
+    }
+
+    /// Draw the effect `secs` after it started rather than now.
+    fn draw_at(&mut self, secs: f64) {
+        self.pixels
+            .iter_mut()
+            .for_each(|c| *c = LinSrgb::new(0, 0, 0));
+        let Some(effect) = self.effect.as_mut() else {
+            return;
+        };
+        let t = self.start + Duration::microseconds((secs * 1_000_000.0) as i64);
+        if let Err(e) = effect.render_at(&mut self.pixels, self.start, t) {
+            self.error = Some(e.to_string());
+        }
+        self.collect_output();
+        if self.render_pixels().is_err() {
+            log::error!("Error rendering pixels");
+        }
+    }
+
+    fn collect_output(&mut self) {
+        let output = drain_script_output();
+        self.console.extend(output.lines().map(ToOwned::to_owned));
+        if self.console.len() > CONSOLE_LINES {
+            self.console.drain(..self.console.len() - CONSOLE_LINES);
+        }
+    }
+
     fn set_timer(&mut self, ctx: &Context<Self>, dur: Duration) {
         let link = ctx.link().clone();
         self.timer = Some(Timeout::new(dur.num_milliseconds() as u32, move || {
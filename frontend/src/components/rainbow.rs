@@ -1,10 +1,12 @@
-use chrono::Duration;
+use lights::palettes::PaletteRef;
 use palette::LinSrgb;
 
 use wasm_bindgen::{JsCast, UnwrapThrowExt};
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
+use crate::{components::PaletteField, utils::view_checkbox_field};
+
 #[derive(Clone, Debug)]
 pub(crate) struct Rainbow {
     effect: lights::effects::Rainbow,
@@ -20,8 +22,10 @@ pub(crate) enum Msg {
     AddColor,
     ReplaceColor(usize, LinSrgb<u8>),
     RemoveColor(usize),
+    Palette(Option<PaletteRef>),
     Spacing(usize),
-    Delay(i64),
+    Speed(f32),
+    Smooth(bool),
     Direction,
 }
 
@@ -49,7 +53,9 @@ impl Component for Rainbow {
             Msg::AddColor => self.effect.add_color(LinSrgb::new(255, 0, 0)),
             Msg::ReplaceColor(idx, color) => self.effect.set_color(idx, color),
             Msg::RemoveColor(idx) => self.effect.remove_color(idx),
-            Msg::Delay(delay) => self.effect.delay = Duration::milliseconds(delay),
+            Msg::Palette(palette) => self.effect.palette = palette,
+            Msg::Speed(speed) => self.effect.speed = speed,
+            Msg::Smooth(smooth) => self.effect.smooth = smooth,
             Msg::Direction => self.effect.direction *= -1,
             Msg::Spacing(s) => self.effect.set_spacing(s),
         };
@@ -104,19 +110,31 @@ impl Component for Rainbow {
 
         html! {
             <>
-                <div classes={ classes!("rainbow-colors") }>
-                    { for colors }
-                    <ybc::Control>
-                        <input type="button"
-                            onclick={
-                                ctx.link().callback(move |_| {
-                                    Msg::AddColor
-                                })
-                            }
-                            value={ "+" }
-                        />
-                    </ybc::Control>
-                </div>
+                <PaletteField
+                    palette={ ctx.props().rainbow.palette.clone() }
+                    onupdate={ Some(ctx.link().callback(Msg::Palette)) }
+                />
+                {
+                    if ctx.props().rainbow.palette.is_none() {
+                        html! {
+                            <div classes={ classes!("rainbow-colors") }>
+                                { for colors }
+                                <ybc::Control>
+                                    <input type="button"
+                                        onclick={
+                                            ctx.link().callback(move |_| {
+                                                Msg::AddColor
+                                            })
+                                        }
+                                        value={ "+" }
+                                    />
+                                </ybc::Control>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
                 <ybc::Field addons={ true }>
                     <label for="direction" class="label">{ "Direction: " }</label>
                     <ybc::Control>
@@ -158,56 +176,44 @@ impl Component for Rainbow {
                     </ybc::Control>
                 </ybc::Field>
                 <ybc::Field addons={ true }>
-                    <label class="label">{ "Delay between movements: " }</label>
+                    <label class="label">{ "Speed: " }</label>
                     <ybc::Control classes={ classes!("has-addons") }>
                         <input type="range"
                             class="input"
-                            min="10"
-                            max="1000"
-                            step="10"
-                            id="delay"
-                            name="delay"
+                            min="1"
+                            max="100"
+                            step="1"
+                            id="speed"
+                            name="speed"
                             onchange={
                                 ctx.link().callback(move |e: Event| {
                                     let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let value = target.value();
-                                    let delay = value.parse().unwrap_or(100) ;
-                                    Msg::Delay(delay)
+                                    Msg::Speed(target.value().parse().unwrap_or(10.0))
                                 })
                             }
                             oninput={
                                 ctx.link().callback(move |e: InputEvent| {
                                     let event: Event = e.dyn_into().unwrap_throw();
                                     let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                    let delay = target.value().parse().unwrap_or(100);
-                                    Msg::Delay(delay)
+                                    Msg::Speed(target.value().parse().unwrap_or(10.0))
                                 })
                             }
-                            value= { ctx.props().rainbow.delay.num_milliseconds().to_string() }
+                            value={ ctx.props().rainbow.speed.to_string() }
                         />
                         <label>
-                            <input type="number" name="delay" id="delay_real" class="input"
+                            <input type="number" name="speed" id="speed_real" class="input"
+                                step="1"
                                 onchange={
                                     ctx.link().callback(move |e: Event| {
-                                        let event: Event = e.dyn_into().unwrap_throw();
-                                        let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                        let value = target.value();
-                                        let delay = value.parse().unwrap_or(100) ;
-                                        Msg::Delay(delay)
-                                    })
-                                }
-                                oninput={
-                                    ctx.link().callback(move |e:InputEvent| {
-                                        let event: Event = e.dyn_into().unwrap_throw();
-                                        let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
-                                        let delay = target.value().parse().unwrap_or(100);
-                                        Msg::Delay(delay)
+                                        let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                        Msg::Speed(target.value().parse().unwrap_or(10.0))
                                     })
                                 }
-                                value= { ctx.props().rainbow.delay.num_milliseconds().to_string() }
-                            /><a class="button is-static">{ "ms" }</a></label>
+                                value={ ctx.props().rainbow.speed.to_string() }
+                            /><a class="button is-static">{ "pixels/s" }</a></label>
                     </ybc::Control>
                 </ybc::Field>
+                { view_checkbox_field("Glide between pixels", ctx.props().rainbow.smooth, ctx.link().callback(Msg::Smooth)) }
             </>
         }
     }
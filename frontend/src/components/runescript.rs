@@ -0,0 +1,460 @@
+use gloo::net::http::Request;
+use lights::effects::{
+    self, CompileReport, ParamKind, ParamSpec, ParamValue, ParamValues, SourceCode,
+};
+use palette::LinSrgb;
+use serde_json::json;
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::{platform::spawn_local, prelude::*};
+
+const KEYWORDS: &[&str] = &[
+    "async", "await", "break", "const", "continue", "crate", "else", "enum", "false", "fn", "for",
+    "if", "impl", "in", "is", "let", "loop", "match", "mod", "not", "pub", "return", "select",
+    "self", "struct", "super", "true", "use", "while", "yield",
+];
+
+/// The script's own source, as opposed to a library module it uses.
+const MAIN_SOURCE: &str = "main";
+
+/// Fetch the script library into our copy of it, so the preview can run
+/// scripts that are in it or `mod` modules from it.
+pub(crate) fn fetch_library(callback: Callback<()>) {
+    spawn_local(async move {
+        let names = Request::get("/scripts")
+            .send()
+            .await
+            .expect("Need to get a scripts response")
+            .json::<Vec<String>>()
+            .await
+            .unwrap_or_default();
+        for name in names {
+            let Ok(resp) = Request::get(&format!("/scripts/{}", name)).send().await else {
+                continue;
+            };
+            let source = resp
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|v| v.get("source")?.as_str().map(ToOwned::to_owned));
+            if let Some(source) = source {
+                effects::set_library_module(name, source);
+            }
+        }
+        callback.emit(());
+    });
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Runescript {
+    /// What is in the editor right now.
+    text: String,
+    /// The last source that compiled, which is what the preview is running.
+    compiled: SourceCode,
+    /// The library script the editor was filled from, and its text, so it's
+    /// kept as a reference until it's edited.
+    library: Option<(String, String)>,
+    params: ParamValues,
+    report: CompileReport,
+    compiling: bool,
+}
+
+pub(crate) enum Msg {
+    Edit(String),
+    Loaded(String, String),
+    Compile,
+    Compiled(String, CompileReport),
+    Template(String),
+    Param(String, ParamValue),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct Props {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<(SourceCode, ParamValues)>>,
+    #[prop_or_default]
+    pub runescript: SourceCode,
+    #[prop_or_default]
+    pub params: ParamValues,
+}
+
+impl Component for Runescript {
+    type Message = Msg;
+
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let mut this = Self {
+            text: String::new(),
+            compiled: ctx.props().runescript.clone(),
+            library: None,
+            params: ctx.props().params.clone(),
+            report: Default::default(),
+            compiling: false,
+        };
+        this.load(ctx);
+        this
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>, old_props: &Self::Properties) -> bool {
+        self.params = ctx.props().params.clone();
+        if ctx.props().runescript != old_props.runescript
+            && ctx.props().runescript != self.compiled
+        {
+            self.compiled = ctx.props().runescript.clone();
+            self.load(ctx);
+        }
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Edit(text) => {
+                self.text = text;
+                true
+            }
+            Msg::Loaded(name, text) => {
+                self.library = Some((name, text.clone()));
+                self.text = text;
+                self.compile(ctx);
+                true
+            }
+            Msg::Compile => {
+                self.compile(ctx);
+                true
+            }
+            Msg::Compiled(source, report) => {
+                self.compiling = false;
+                self.report = report;
+                if self.report.is_ok() {
+                    self.compiled = match &self.library {
+                        Some((name, text)) if *text == source => SourceCode::Library(name.clone()),
+                        _ => SourceCode::Source(source),
+                    };
+                    self.emit(ctx);
+                }
+                true
+            }
+            Msg::Template(name) => {
+                if let Some((_, template)) = SourceCode::templates().find(|(n, _)| *n == name) {
+                    self.text = template.to_string();
+                    self.compile(ctx);
+                }
+                true
+            }
+            Msg::Param(name, value) => {
+                self.params.set(name, value);
+                self.emit(ctx);
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let error = self.report.error.as_ref().map(|e| {
+            html! {
+                <ybc::Notification classes={ classes!("is-danger") }>{ e }</ybc::Notification>
+            }
+        });
+        let diagnostics = self.report.diagnostics.iter().map(|d| {
+            let location = match (d.line, d.source.as_str()) {
+                (0, MAIN_SOURCE | "") => String::new(),
+                (0, source) => format!("In {}: ", source),
+                (line, MAIN_SOURCE | "") => format!("Line {}, column {}: ", line, d.column),
+                (line, source) => format!("{}, line {}, column {}: ", source, line, d.column),
+            };
+            html! {
+                <li class="has-text-danger">{ location }{ &d.message }</li>
+            }
+        });
+        let templates = SourceCode::templates().map(|(name, _)| {
+            html! { <option value={ name }>{ name }</option> }
+        });
+        let params = self
+            .report
+            .schema
+            .iter()
+            .map(|spec| self.view_param(ctx, spec));
+
+        html! {
+            <>
+                <ybc::Field addons={ true }>
+                    <ybc::Control>
+                        <div class="select">
+                            <select
+                                onchange={ ctx.link().callback(|e: Event| {
+                                    let target: HtmlSelectElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    Msg::Template(target.value())
+                                }) }
+                            >
+                                <option value="" selected=true disabled=true>{ "Start from a template..." }</option>
+                                { for templates }
+                            </select>
+                        </div>
+                    </ybc::Control>
+                    <ybc::Control>
+                        <ybc::Button
+                            classes={ classes!("is-primary", self.compiling.then_some("is-loading")) }
+                            onclick={ ctx.link().callback(|_| Msg::Compile) }>
+                            { "Compile" }
+                        </ybc::Button>
+                    </ybc::Control>
+                </ybc::Field>
+                { self.view_editor(ctx) }
+                { for error }
+                <ul class="script-diagnostics">
+                    { for diagnostics }
+                </ul>
+                <div class="script-params">
+                    { for params }
+                </div>
+            </>
+        }
+    }
+}
+
+impl Runescript {
+    /// Fill the editor from the current source, fetching it from the library
+    /// if needed.
+    fn load(&mut self, ctx: &Context<Self>) {
+        match &self.compiled {
+            SourceCode::Source(code) => {
+                self.library = None;
+                self.text = code.clone();
+                self.compile(ctx);
+            }
+            SourceCode::Library(name) => {
+                let library = name.clone();
+                let callback = ctx
+                    .link()
+                    .callback(move |source| Msg::Loaded(library.clone(), source));
+                let url = format!("/scripts/{}", name);
+                spawn_local(async move {
+                    let resp = Request::get(&url)
+                        .send()
+                        .await
+                        .expect("Need to get a script response")
+                        .json::<serde_json::Value>()
+                        .await;
+                    let source = resp
+                        .ok()
+                        .and_then(|v| v.get("source")?.as_str().map(ToOwned::to_owned))
+                        .unwrap_or_default();
+                    callback.emit(source);
+                });
+            }
+        }
+    }
+
+    fn compile(&mut self, ctx: &Context<Self>) {
+        self.compiling = true;
+        let source = self.text.clone();
+        let body = json!({ "source": source, "params": self.params });
+        let callback = ctx
+            .link()
+            .callback(move |report: CompileReport| Msg::Compiled(source.clone(), report));
+        spawn_local(async move {
+            let report = Request::post("/compile")
+                .json(&body)
+                .expect("json serialized properly")
+                .send()
+                .await
+                .expect("Need to get a compile response")
+                .json::<CompileReport>()
+                .await
+                .unwrap_or_else(|e| CompileReport {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                });
+            callback.emit(report);
+        });
+    }
+
+    fn emit(&self, ctx: &Context<Self>) {
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit((self.compiled.clone(), self.params.clone())));
+    }
+
+    /// A textarea laid exactly over a highlighted copy of its contents. The
+    /// textarea text is transparent, so only its caret and selection show.
+    fn view_editor(&self, ctx: &Context<Self>) -> Html {
+        let rows = self.text.lines().count() + 1;
+        let error_lines = self
+            .report
+            .diagnostics
+            .iter()
+            .filter(|d| d.source.is_empty() || d.source == MAIN_SOURCE)
+            .map(|d| d.line)
+            .collect::<Vec<_>>();
+        let lines = self.text.split('\n').enumerate().map(|(idx, line)| {
+            let error = error_lines
+                .contains(&(idx + 1))
+                .then_some("script-error-line");
+            html! {
+                <>
+                    if idx > 0 { { "\n" } }
+                    <span class={ classes!(error) }>{ highlight_line(line) }</span>
+                </>
+            }
+        });
+
+        html! {
+            <div class="script-editor box">
+                <pre aria-hidden="true">
+                    { for lines }{ " " }
+                </pre>
+                <textarea
+                    spellcheck="false"
+                    rows={ rows.to_string() }
+                    value={ self.text.clone() }
+                    oninput={ ctx.link().callback(|e: InputEvent| {
+                        let event: Event = e.dyn_into().unwrap_throw();
+                        let target: HtmlTextAreaElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
+                        Msg::Edit(target.value())
+                    }) }
+                />
+            </div>
+        }
+    }
+
+    fn view_param(&self, ctx: &Context<Self>, spec: &ParamSpec) -> Html {
+        let name = spec.name.clone();
+        let id = format!("param_{}", name);
+        let value = self.params.value_for(spec);
+        let control = match (&spec.kind, value) {
+            (ParamKind::Color, ParamValue::Color(color)) => html! {
+                <input type="color"
+                    id={ id.clone() }
+                    value={ format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue) }
+                    onchange={ ctx.link().callback(move |c: Event| {
+                        let target: HtmlInputElement = c.target().unwrap_throw().dyn_into().unwrap_throw();
+                        let value = target.value();
+                        let mut buf = [0_u8; 3];
+                        let s = value.trim_start_matches('#');
+                        let v = hex::decode_to_slice(s, &mut buf);
+                        if v.is_err() {
+                            return Msg::Param(name.clone(), ParamValue::Color(color));
+                        }
+
+                        Msg::Param(name.clone(), ParamValue::Color(LinSrgb::new(buf[0], buf[1], buf[2])))
+                    }) }
+                />
+            },
+            (ParamKind::Int { min, max }, ParamValue::Int(i)) => html! {
+                <input type="range"
+                    class="input"
+                    id={ id.clone() }
+                    min={ min.to_string() }
+                    max={ max.to_string() }
+                    step="1"
+                    value={ i.to_string() }
+                    onchange={ ctx.link().callback(move |e: Event| {
+                        let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                        let value = target.value().parse().unwrap_or(i);
+                        Msg::Param(name.clone(), ParamValue::Int(value))
+                    }) }
+                />
+            },
+            (ParamKind::Float { min, max }, ParamValue::Float(f)) => html! {
+                <input type="range"
+                    class="input"
+                    id={ id.clone() }
+                    min={ min.to_string() }
+                    max={ max.to_string() }
+                    step={ ((max - min) / 100.0).to_string() }
+                    value={ f.to_string() }
+                    onchange={ ctx.link().callback(move |e: Event| {
+                        let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                        let value = target.value().parse().unwrap_or(f);
+                        Msg::Param(name.clone(), ParamValue::Float(value))
+                    }) }
+                />
+            },
+            (ParamKind::Bool, ParamValue::Bool(b)) => html! {
+                <input type="checkbox"
+                    id={ id.clone() }
+                    checked={ b }
+                    onchange={ ctx.link().callback(move |e: Event| {
+                        let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                        Msg::Param(name.clone(), ParamValue::Bool(target.checked()))
+                    }) }
+                />
+            },
+            (ParamKind::Enum(options), ParamValue::Enum(selected)) => {
+                let options = options.iter().map(|o| {
+                    html! {
+                        <option value={ o.clone() } selected={ o == &selected }>{ o }</option>
+                    }
+                });
+                html! {
+                    <div class="select">
+                        <select
+                            id={ id.clone() }
+                            onchange={ ctx.link().callback(move |e: Event| {
+                                let target: HtmlSelectElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                Msg::Param(name.clone(), ParamValue::Enum(target.value()))
+                            }) }
+                        >
+                            { for options }
+                        </select>
+                    </div>
+                }
+            }
+            _ => html! { <p>{ "Unsupported parameter" }</p> },
+        };
+
+        html! {
+            <ybc::Field>
+                <label for={ id } class="label">{ format!("{}: ", spec.name) }</label>
+                <ybc::Control>
+                    { control }
+                </ybc::Control>
+            </ybc::Field>
+        }
+    }
+}
+
+/// Split one line of Rune into spans coloured by a rough idea of what each
+/// token is. This only has to be good enough to read, not to parse.
+fn highlight_line(line: &str) -> Html {
+    let mut tokens = vec![];
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let (len, class) = if rest.starts_with("//") {
+            (rest.len(), Some("script-comment"))
+        } else if c == '"' || c == '`' || c == '\'' {
+            let len = rest[1..].find(c).map(|i| i + 2).unwrap_or(rest.len());
+            (len, Some("script-string"))
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .char_indices()
+                .find(|&(i, c)| {
+                    let decimal_point =
+                        c == '.' && rest[i + 1..].starts_with(|n: char| n.is_ascii_digit());
+                    !(c.is_ascii_digit() || c == '_' || decimal_point)
+                })
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            (len, Some("script-number"))
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let class = KEYWORDS.contains(&&rest[..len]).then_some("script-keyword");
+            (len, class)
+        } else {
+            (c.len_utf8(), None)
+        };
+
+        let token = &rest[..len];
+        tokens.push(match class {
+            Some(class) => html! { <span class={ class }>{ token }</span> },
+            None => html! { { token } },
+        });
+        rest = &rest[len..];
+    }
+
+    html! { { for tokens } }
+}
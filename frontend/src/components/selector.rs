@@ -49,7 +49,7 @@ impl Component for Selector {
         let options: Vec<_> = effects
             .iter()
             .cloned()
-            .filter(|ty| !(ctx.props().internal && ty == &"Rune Script"))
+            .filter(|ty| !(ctx.props().internal && matches!(*ty, "Rune Script" | "Wasm Plugin")))
             .map(|i| {
                 let id = format!("{}", i);
                 let classes = if i == ctx.props().ty {
@@ -0,0 +1,100 @@
+use chrono::Duration;
+use palette::LinSrgb;
+
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::utils::view_color_field;
+
+#[derive(Clone, Debug)]
+pub(crate) struct SignalFlash {
+    effect: lights::effects::SignalFlash,
+}
+
+pub(crate) enum Msg {
+    Signal(String),
+    Color(LinSrgb<u8>),
+    Decay(i64),
+}
+
+#[derive(Clone, PartialEq, Properties, Debug)]
+pub(crate) struct Props {
+    #[prop_or_default]
+    pub onupdate: Option<Callback<lights::effects::SignalFlash>>,
+    #[prop_or_default]
+    pub flash: lights::effects::SignalFlash,
+}
+
+impl Component for SignalFlash {
+    type Message = Msg;
+
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            effect: ctx.props().flash.clone(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Signal(s) => self.effect.signal = s,
+            Msg::Color(c) => self.effect.color = c,
+            Msg::Decay(d) => self.effect.decay = Duration::milliseconds(d),
+        }
+        ctx.props()
+            .onupdate
+            .as_ref()
+            .map(|u| u.emit(self.effect.clone()));
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        html! {
+            <>
+                <ybc::Field>
+                    <label class="label" for="signal">{ "Signal: " }</label>
+                    <ybc::Control>
+                        <input type="text"
+                            class="input"
+                            id="signal"
+                            value={ self.effect.signal.clone() }
+                            onchange={
+                                link.callback(move |e: Event| {
+                                    let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    Msg::Signal(target.value())
+                                })
+                            }
+                        />
+                    </ybc::Control>
+                    <p class="help">{ "Flashes whenever this is set, e.g. POST /signals/doorbell/trigger" }</p>
+                </ybc::Field>
+                { view_color_field("Flash color: ", self.effect.color, link.callback(Msg::Color)) }
+                <ybc::Field addons={ true }>
+                    <label class="label">{ "Fade out over: " }</label>
+                    <ybc::Control classes={ classes!("has-addons") }>
+                        <input type="range"
+                            class="input"
+                            min="20"
+                            max="5000"
+                            step="10"
+                            id="decay"
+                            name="decay"
+                            oninput={
+                                link.callback(move |ty: InputEvent| {
+                                    let event: Event = ty.dyn_into().unwrap_throw();
+                                    let target: HtmlInputElement = event.target().unwrap_throw().dyn_into().unwrap_throw();
+                                    Msg::Decay(target.value().parse().unwrap_or(1000))
+                                })
+                            }
+                            value={ self.effect.decay.num_milliseconds().to_string() }
+                        />
+                        <a class="button is-static">{ format!("{} ms", self.effect.decay.num_milliseconds()) }</a>
+                    </ybc::Control>
+                </ybc::Field>
+            </>
+        }
+    }
+}
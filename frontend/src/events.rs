@@ -0,0 +1,44 @@
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{EventSource, MessageEvent};
+use yew::Callback;
+
+/// Server-sent events from the backend, closed when it's dropped.
+pub(crate) struct EventStream {
+    source: EventSource,
+    _listeners: Vec<Closure<dyn FnMut(MessageEvent)>>,
+}
+
+impl EventStream {
+    /// Call `callback` with the name and data of each event from `url` that's
+    /// one of `names`.
+    pub(crate) fn open(
+        url: &str,
+        names: &[&'static str],
+        callback: Callback<(&'static str, String)>,
+    ) -> Result<Self, JsValue> {
+        let source = EventSource::new(url)?;
+        let listeners = names
+            .iter()
+            .map(|&name| {
+                let callback = callback.clone();
+                let listener = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+                    if let Some(data) = e.data().as_string() {
+                        callback.emit((name, data));
+                    }
+                });
+                source.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())?;
+                Ok(listener)
+            })
+            .collect::<Result<_, JsValue>>()?;
+        Ok(Self {
+            source,
+            _listeners: listeners,
+        })
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
@@ -1,6 +1,13 @@
 use crate::components;
 
-use lights::effects::{Ball, Balls, Composite, Glow, Rainbow, SourceCode};
+use lights::effects::{
+    Ball, Balls, BeatFlash, Breathing, ColorWipe, Composite, Fire, Glow, Larson, ParamValues,
+    Particles, Plasma, Rainbow, SignalFlash, SourceCode, SpectrumBars, Strobe, TheaterChase,
+    Twinkle, VuMeter,
+};
+use palette::LinSrgb;
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 use yew::{
     html::{IntoPropValue, Scope},
     prelude::*,
@@ -14,6 +21,102 @@ pub fn view_empty() -> Html {
     }
 }
 
+pub fn view_color_field(
+    label: &'static str,
+    color: LinSrgb<u8>,
+    onchange: Callback<LinSrgb<u8>>,
+) -> Html {
+    html! {
+        <ybc::Field>
+            <label class="label">{ label }</label>
+            <input type="color"
+                value={ format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue) }
+                onchange={ move |c: Event| {
+                    let target: HtmlInputElement = c.target().expect("should have target").dyn_into().unwrap_throw();
+                    let mut buf = [0_u8; 3];
+                    if hex::decode_to_slice(target.value().trim_start_matches('#'), &mut buf).is_ok() {
+                        onchange.emit(LinSrgb::new(buf[0], buf[1], buf[2]));
+                    }
+                } }
+            />
+        </ybc::Field>
+    }
+}
+
+pub fn view_number_field(
+    label: &'static str,
+    value: f64,
+    step: f64,
+    onchange: Callback<f64>,
+) -> Html {
+    html! {
+        <ybc::Field>
+            <label class="label">{ label }</label>
+            <ybc::Control>
+                <input type="number"
+                    class="input"
+                    step={ step.to_string() }
+                    value={ value.to_string() }
+                    onchange={ move |e: Event| {
+                        let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                        if let Ok(value) = target.value().parse() {
+                            onchange.emit(value);
+                        }
+                    } }
+                />
+            </ybc::Control>
+        </ybc::Field>
+    }
+}
+
+pub fn view_checkbox_field(label: &'static str, checked: bool, onchange: Callback<bool>) -> Html {
+    html! {
+        <ybc::Field>
+            <ybc::Control>
+                <label class="checkbox">
+                    <input type="checkbox"
+                        checked={ checked }
+                        onchange={ move |e: Event| {
+                            let target: HtmlInputElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                            onchange.emit(target.checked());
+                        } }
+                    />
+                    { " " }{ label }
+                </label>
+            </ybc::Control>
+        </ybc::Field>
+    }
+}
+
+pub fn view_select_field<S: AsRef<str>>(
+    label: &'static str,
+    options: &[S],
+    selected: &str,
+    onchange: Callback<String>,
+) -> Html {
+    let options = options.iter().map(|option| {
+        let option = option.as_ref();
+        html! {
+            <option value={ option.to_owned() } selected={ option == selected }>{ option }</option>
+        }
+    });
+    html! {
+        <ybc::Field>
+            <label class="label">{ label }</label>
+            <ybc::Control>
+                <div class="select">
+                    <select onchange={ move |e: Event| {
+                        let target: HtmlSelectElement = e.target().unwrap_throw().dyn_into().unwrap_throw();
+                        onchange.emit(target.value());
+                    } }>
+                        { for options }
+                    </select>
+                </div>
+            </ybc::Control>
+        </ybc::Field>
+    }
+}
+
 pub fn view_ball<COMP, F, IN, M>(ball: &Ball, link: &Scope<COMP>, lambda: F) -> Html
 where
     COMP: Component,
@@ -89,8 +192,223 @@ where
     }
 }
 
+pub fn view_spectrum<COMP, F, IN, M>(spectrum: &SpectrumBars, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<SpectrumBars>>>,
+{
+    html! {
+        <components::SpectrumBars
+            spectrum = { spectrum.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_vu_meter<COMP, F, IN, M>(meter: &VuMeter, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<VuMeter>>>,
+{
+    html! {
+        <components::VuMeter
+            meter = { meter.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_beat_flash<COMP, F, IN, M>(flash: &BeatFlash, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<BeatFlash>>>,
+{
+    html! {
+        <components::BeatFlash
+            flash = { flash.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_signal_flash<COMP, F, IN, M>(flash: &SignalFlash, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<SignalFlash>>>,
+{
+    html! {
+        <components::SignalFlash
+            flash = { flash.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_particles<COMP, F, IN, M>(particles: &Particles, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Particles>>>,
+{
+    html! {
+        <components::Particles
+            particles = { particles.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_fire<COMP, F, IN, M>(fire: &Fire, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Fire>>>,
+{
+    html! {
+        <components::Fire
+            fire = { fire.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_twinkle<COMP, F, IN, M>(twinkle: &Twinkle, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Twinkle>>>,
+{
+    html! {
+        <components::Twinkle
+            twinkle = { twinkle.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_theater_chase<COMP, F, IN, M>(
+    chase: &TheaterChase,
+    link: &Scope<COMP>,
+    lambda: F,
+) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<TheaterChase>>>,
+{
+    html! {
+        <components::TheaterChase
+            chase = { chase.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_color_wipe<COMP, F, IN, M>(wipe: &ColorWipe, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<ColorWipe>>>,
+{
+    html! {
+        <components::ColorWipe
+            wipe = { wipe.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_larson<COMP, F, IN, M>(larson: &Larson, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Larson>>>,
+{
+    html! {
+        <components::Larson
+            larson = { larson.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_breathing<COMP, F, IN, M>(breathing: &Breathing, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Breathing>>>,
+{
+    html! {
+        <components::Breathing
+            breathing = { breathing.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_strobe<COMP, F, IN, M>(strobe: &Strobe, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Strobe>>>,
+{
+    html! {
+        <components::Strobe
+            strobe = { strobe.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_plasma<COMP, F, IN, M>(plasma: &Plasma, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<Plasma>>>,
+{
+    html! {
+        <components::Plasma
+            plasma = { plasma.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
+pub fn view_plugin<COMP, F, IN, M>(plugin: &str, link: &Scope<COMP>, lambda: F) -> Html
+where
+    COMP: Component,
+    F: Fn(IN) -> M + 'static,
+    M: Into<COMP::Message>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<String>>>,
+{
+    html! {
+        <components::Plugin
+            plugin = { plugin.to_owned() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
+    }
+}
+
 pub fn view_runescript<COMP, F, IN, M>(
     runescript: &SourceCode,
+    params: &ParamValues,
     link: &Scope<COMP>,
     lambda: F,
 ) -> Html
@@ -98,15 +416,13 @@ where
     COMP: Component,
     F: Fn(IN) -> M + 'static,
     M: Into<COMP::Message>,
-    Option<Callback<IN>>: IntoPropValue<Option<Callback<SourceCode>>>,
+    Option<Callback<IN>>: IntoPropValue<Option<Callback<(SourceCode, ParamValues)>>>,
 {
     html! {
-        <>
-            {"Work in Progress"}
-        </>
-        // <components::Runescript
-        //     runescript = { runescript.clone() }
-        //     onupdate = { Some(link.callback(lambda)) }
-        //  />
+        <components::Runescript
+            runescript = { runescript.clone() }
+            params = { params.clone() }
+            onupdate = { Some(link.callback(lambda)) }
+         />
     }
 }
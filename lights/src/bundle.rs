@@ -0,0 +1,231 @@
+//! Presets packed up to move between strips, along with the library scripts
+//! and user palettes they use.
+//!
+//! Bundles are written as JSON or MsgPack. `version` is the `Details` version
+//! the presets were written at, so a bundle from an older build is migrated
+//! on import like anything else in storage.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    details::Details,
+    effects::{library_module, EffectType, SourceCode},
+    error::Result,
+    migrations,
+    palettes::{self, Palette, PaletteRef},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle<D = Details> {
+    pub version: u32,
+    pub presets: Vec<D>,
+    /// Library modules the presets' scripts use, by name.
+    #[serde(default)]
+    pub scripts: BTreeMap<String, String>,
+    /// User palettes the presets use, by name. Built-in ones aren't included.
+    #[serde(default)]
+    pub palettes: BTreeMap<String, Palette>,
+}
+
+impl Bundle {
+    /// Bundle up `presets` with whatever they use from the script library and
+    /// user palettes.
+    pub fn new(presets: Vec<Details>) -> Self {
+        let user_palettes = palettes::user_palettes();
+        let mut scripts = BTreeMap::new();
+        let mut palettes = BTreeMap::new();
+        for details in &presets {
+            let (script_names, palette_names) = references(&details.effect);
+            for name in script_names {
+                collect_module(&name, &mut scripts);
+            }
+            for name in palette_names {
+                if let Some(palette) = user_palettes.get(&name) {
+                    palettes.insert(name, palette.clone());
+                }
+            }
+        }
+        Self {
+            version: migrations::VERSION,
+            presets,
+            scripts,
+            palettes,
+        }
+    }
+}
+
+/// The library modules and palettes `effect` uses by name.
+pub fn references(effect: &EffectType) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut scripts = BTreeSet::new();
+    let mut palettes = BTreeSet::new();
+    walk_effects(effect, &mut |effect| match effect {
+        EffectType::RuneScript(SourceCode::Library(name), _) => {
+            // A main module's `mod`s are from the root, not inside it.
+            if let Some(source) = library_module(name) {
+                scripts.extend(module_declarations(&source, ""));
+            }
+            scripts.insert(name.clone());
+        }
+        EffectType::RuneScript(SourceCode::Source(source), _) => {
+            scripts.extend(module_declarations(source, ""));
+        }
+        _ => {
+            if let Some(PaletteRef::Named(name)) = palette(effect) {
+                if !palettes::is_builtin(name) {
+                    palettes.insert(name.clone());
+                }
+            }
+        }
+    });
+    (scripts, palettes)
+}
+
+/// Every library module that a script in `scripts` or `effects` loads with
+/// `mod`. These can't be imported under another name, since the `mod`
+/// declarations would still point at the old one.
+pub fn declared_modules<'a>(
+    scripts: &BTreeMap<String, String>,
+    effects: impl IntoIterator<Item = &'a EffectType>,
+) -> BTreeSet<String> {
+    let mut declared = BTreeSet::new();
+    for (name, source) in scripts {
+        // A library script is either run as a main module or loaded as one.
+        declared.extend(module_declarations(source, ""));
+        declared.extend(module_declarations(source, name));
+    }
+    for effect in effects {
+        walk_effects(effect, &mut |effect| {
+            if let EffectType::RuneScript(SourceCode::Source(source), _) = effect {
+                declared.extend(module_declarations(source, ""));
+            }
+        });
+    }
+    declared
+}
+
+/// The library modules `effect` runs or loads, all the way down, looked up in
+/// `scripts` instead of the library.
+pub fn needed_modules(effect: &EffectType, scripts: &BTreeMap<String, String>) -> BTreeSet<String> {
+    let mut pending = vec![];
+    walk_effects(effect, &mut |effect| match effect {
+        EffectType::RuneScript(SourceCode::Library(name), _) => {
+            if let Some(source) = scripts.get(name) {
+                pending.extend(module_declarations(source, ""));
+            }
+            pending.push(name.clone());
+        }
+        EffectType::RuneScript(SourceCode::Source(source), _) => {
+            pending.extend(module_declarations(source, ""));
+        }
+        _ => {}
+    });
+    let mut needed = BTreeSet::new();
+    while let Some(name) = pending.pop() {
+        if !needed.insert(name.clone()) {
+            continue;
+        }
+        if let Some(source) = scripts.get(&name) {
+            pending.extend(module_declarations(source, &name));
+        }
+    }
+    needed
+}
+
+/// Point `effect` at renamed library modules and palettes. Only the main
+/// module of a script can be renamed, `mod` declarations inside scripts
+/// aren't rewritten, see `declared_modules`.
+pub fn rename_references(
+    effect: &mut EffectType,
+    scripts: &BTreeMap<String, String>,
+    palettes: &BTreeMap<String, String>,
+) -> Result<()> {
+    visit_effects(effect, &mut |effect| match effect {
+        EffectType::RuneScript(SourceCode::Library(name), _) => {
+            if let Some(renamed) = scripts.get(name) {
+                *name = renamed.clone();
+            }
+        }
+        _ => {
+            if let Some(PaletteRef::Named(name)) = palette_mut(effect) {
+                if let Some(renamed) = palettes.get(name) {
+                    *name = renamed.clone();
+                }
+            }
+        }
+    })
+}
+
+/// Call `f` on `effect` and every effect inside it.
+fn walk_effects(effect: &EffectType, f: &mut impl FnMut(&EffectType)) {
+    if let EffectType::Composite(composite) = effect {
+        walk_effects(composite.first(), f);
+        walk_effects(composite.second(), f);
+    }
+    f(effect);
+}
+
+/// Like `walk_effects`, but `f` can change them. Changing an effect inside a
+/// composite into a script is an error.
+fn visit_effects(effect: &mut EffectType, f: &mut impl FnMut(&mut EffectType)) -> Result<()> {
+    if let EffectType::Composite(composite) = effect {
+        let mut first = composite.first().clone();
+        let mut second = composite.second().clone();
+        visit_effects(&mut first, f)?;
+        visit_effects(&mut second, f)?;
+        composite.set_first(first)?;
+        composite.set_second(second)?;
+    }
+    f(effect);
+    Ok(())
+}
+
+fn palette(effect: &EffectType) -> Option<&PaletteRef> {
+    match effect {
+        EffectType::Glow(glow) => glow.palette.as_ref(),
+        EffectType::Rainbow(rainbow) => rainbow.palette.as_ref(),
+        _ => None,
+    }
+}
+
+fn palette_mut(effect: &mut EffectType) -> Option<&mut PaletteRef> {
+    match effect {
+        EffectType::Glow(glow) => glow.palette.as_mut(),
+        EffectType::Rainbow(rainbow) => rainbow.palette.as_mut(),
+        _ => None,
+    }
+}
+
+/// Add the library module `name`, and the ones it declares, to `modules`.
+fn collect_module(name: &str, modules: &mut BTreeMap<String, String>) {
+    if modules.contains_key(name) {
+        return;
+    }
+    let Some(source) = library_module(name) else {
+        return;
+    };
+    let declared = module_declarations(&source, name);
+    modules.insert(name.to_owned(), source);
+    for module in declared {
+        collect_module(&module, modules);
+    }
+}
+
+/// The library modules `source` pulls in with `mod name;`, with their full
+/// path from inside the module `parent`.
+fn module_declarations(source: &str, parent: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let line = line.strip_prefix("pub ").unwrap_or(line);
+            let name = line.strip_prefix("mod ")?.strip_suffix(';')?.trim();
+            Some(if parent.is_empty() {
+                name.to_owned()
+            } else {
+                format!("{}::{}", parent, name)
+            })
+        })
+        .collect()
+}
@@ -1,6 +1,12 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
 use serde::{Deserialize, Serialize};
 
-use crate::effects::EffectType;
+use crate::{effects::EffectType, error::Result};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Details {
@@ -20,3 +26,22 @@ impl Default for Details {
         }
     }
 }
+
+impl Details {
+    /// The effect in a JSON file, or stdin if `path` is `-`, for the command
+    /// line tools. The JSON can be whole details like `/details` gives, when
+    /// their length comes along too, or just an effect.
+    pub fn read_effect(path: &Path) -> Result<(EffectType, Option<usize>)> {
+        let json = if path.as_os_str() == "-" {
+            let mut json = String::new();
+            io::stdin().read_to_string(&mut json)?;
+            json
+        } else {
+            fs::read_to_string(path)?
+        };
+        Ok(match serde_json::from_str::<Details>(&json) {
+            Ok(details) => (details.effect, Some(details.length)),
+            Err(_) => (serde_json::from_str(&json)?, None),
+        })
+    }
+}
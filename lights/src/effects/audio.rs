@@ -0,0 +1,371 @@
+use std::{collections::VecDeque, f32::consts::PI, sync::RwLock};
+
+use chrono::{serde::ts_milliseconds_option, Duration};
+use once_cell::sync::Lazy;
+use palette::LinSrgb;
+use rune::{ContextError, Module};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationMilliSeconds};
+
+use super::{scale, Effect, EffectType, Instant, FRAME_MILLIS};
+use crate::{
+    error::Result,
+    signals::{self, Signal},
+};
+
+/// How many frequency bands the analysis splits the spectrum into.
+pub const BANDS: usize = 8;
+
+const FFT_SIZE: usize = 1024;
+const LOWEST_BAND_HZ: f32 = 40.0;
+const HIGHEST_BAND_HZ: f32 = 16_000.0;
+
+/// Band peaks decay by this much every analysis, so levels adapt to the
+/// volume of whatever is playing.
+const PEAK_DECAY: f32 = 0.995;
+/// Below this nothing counts as sound, so silence doesn't get amplified into
+/// noise.
+const NOISE_FLOOR: f32 = 1e-3;
+
+/// About a second of bass energy at 44.1kHz with 512 sample reads.
+const BEAT_HISTORY: usize = 86;
+const BEAT_THRESHOLD: f32 = 1.4;
+const MIN_BEAT_GAP_MILLIS: i64 = 250;
+
+/// The latest analysis of whatever the backend is listening to.
+///
+/// This stays silent unless something calls `set_audio_levels`, which is the
+/// case in the browser preview.
+static LEVELS: Lazy<RwLock<AudioLevels>> = Lazy::new(Default::default);
+
+/// Band energies and beat events from the audio analysis.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct AudioLevels {
+    /// Energy in each band from lowest to highest frequency, from 0 to 1.
+    pub bands: [f32; BANDS],
+    /// Overall loudness, from 0 to 1.
+    pub level: f32,
+    #[serde(with = "ts_milliseconds_option")]
+    pub last_beat: Option<Instant>,
+}
+
+impl AudioLevels {
+    pub fn since_beat(&self, t: Instant) -> Option<Duration> {
+        self.last_beat.map(|b| t.signed_duration_since(b))
+    }
+}
+
+pub fn audio_levels() -> AudioLevels {
+    LEVELS.read().expect("audio levels poisoned").clone()
+}
+
+/// Publish the analysis as of `t`. The overall level and beats are also
+/// mirrored to the `audio.level` and `audio.beat` signals.
+pub fn set_audio_levels(levels: AudioLevels, t: Instant) {
+    signals::set_signal("audio.level", Signal::Float(levels.level as f64), t);
+    let mut current = LEVELS.write().expect("audio levels poisoned");
+    if let Some(beat) = levels.last_beat.filter(|&b| Some(b) != current.last_beat) {
+        signals::set_signal("audio.beat", Signal::Bool(true), beat);
+    }
+    *current = levels;
+}
+
+/// Turns a stream of mono samples into `AudioLevels`.
+///
+/// Bands are spaced logarithmically between 40Hz and 16kHz and normalized
+/// against their own recent peaks. A beat is bass energy well above its
+/// average over the last second.
+#[derive(Debug)]
+pub struct AudioAnalyzer {
+    sample_rate: f32,
+    window: VecDeque<f32>,
+    peaks: [f32; BANDS],
+    level_peak: f32,
+    bass_history: VecDeque<f32>,
+    last_beat: Option<Instant>,
+}
+
+impl AudioAnalyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            window: VecDeque::with_capacity(FFT_SIZE),
+            peaks: [NOISE_FLOOR; BANDS],
+            level_peak: NOISE_FLOOR,
+            bass_history: VecDeque::with_capacity(BEAT_HISTORY),
+            last_beat: None,
+        }
+    }
+
+    /// Add samples (from -1 to 1) captured at `t`, and analyze the most
+    /// recent window once there's enough of them.
+    pub fn push(&mut self, samples: &[f32], t: Instant) -> Option<AudioLevels> {
+        self.window.extend(samples);
+        while self.window.len() > FFT_SIZE {
+            self.window.pop_front();
+        }
+        if self.window.len() < FFT_SIZE {
+            return None;
+        }
+
+        // Hann window, to keep the edges of the window out of the spectrum.
+        let mut re = self
+            .window
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s * (0.5 - 0.5 * (2.0 * PI * i as f32 / FFT_SIZE as f32).cos()))
+            .collect::<Vec<_>>();
+        let mut im = vec![0.0; FFT_SIZE];
+        fft(&mut re, &mut im);
+
+        let bin_hz = self.sample_rate / FFT_SIZE as f32;
+        let mut energies = [0.0; BANDS];
+        let mut bands = [0.0; BANDS];
+        for i in 0..BANDS {
+            let lo = ((band_edge(i) / bin_hz) as usize).max(1);
+            if lo >= FFT_SIZE / 2 {
+                continue;
+            }
+            let hi = ((band_edge(i + 1) / bin_hz) as usize).clamp(lo + 1, FFT_SIZE / 2);
+            energies[i] = (lo..hi)
+                .map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt())
+                .sum::<f32>()
+                / (hi - lo) as f32;
+            self.peaks[i] = (self.peaks[i] * PEAK_DECAY).max(energies[i]).max(NOISE_FLOOR);
+            bands[i] = energies[i] / self.peaks[i];
+        }
+
+        let rms = (self.window.iter().map(|s| s * s).sum::<f32>() / FFT_SIZE as f32).sqrt();
+        self.level_peak = (self.level_peak * PEAK_DECAY).max(rms).max(NOISE_FLOOR);
+        let level = rms / self.level_peak;
+
+        let bass = energies[0] + energies[1];
+        if self.bass_history.len() == BEAT_HISTORY {
+            let average = self.bass_history.iter().sum::<f32>() / BEAT_HISTORY as f32;
+            let gap = Duration::milliseconds(MIN_BEAT_GAP_MILLIS);
+            let rested = self
+                .last_beat
+                .map(|b| t.signed_duration_since(b) >= gap)
+                .unwrap_or(true);
+            if rested && bass > NOISE_FLOOR && bass > average * BEAT_THRESHOLD {
+                self.last_beat = Some(t);
+            }
+            self.bass_history.pop_front();
+        }
+        self.bass_history.push_back(bass);
+
+        Some(AudioLevels {
+            bands,
+            level,
+            last_beat: self.last_beat,
+        })
+    }
+}
+
+fn band_edge(i: usize) -> f32 {
+    LOWEST_BAND_HZ * (HIGHEST_BAND_HZ / LOWEST_BAND_HZ).powf(i as f32 / BANDS as f32)
+}
+
+/// In place radix-2 FFT. `re` and `im` have to be the same power of two long.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = -2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + len / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+fn mix(a: LinSrgb<u8>, b: LinSrgb<u8>, f: f32) -> LinSrgb<u8> {
+    let (a, b) = (a.into_format::<f32>(), b.into_format::<f32>());
+    (a + (b - a) * f.clamp(0.0, 1.0)).into_format()
+}
+
+/// One bar per band, side by side along the strip.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SpectrumBars {
+    /// Color at the bottom of each bar.
+    pub low: LinSrgb<u8>,
+    /// Color at the top of a full bar.
+    pub high: LinSrgb<u8>,
+}
+
+impl Default for SpectrumBars {
+    fn default() -> Self {
+        Self {
+            low: LinSrgb::new(0, 0, 255),
+            high: LinSrgb::new(255, 0, 255),
+        }
+    }
+}
+
+impl Effect for SpectrumBars {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], _t: Instant) -> Result<Duration> {
+        let levels = audio_levels();
+        let len = pixels.len();
+        for (i, level) in levels.bands.iter().enumerate() {
+            let bar = &mut pixels[i * len / BANDS..(i + 1) * len / BANDS];
+            let width = bar.len();
+            let lit = (level * width as f32).round() as usize;
+            for (j, pixel) in bar.iter_mut().take(lit).enumerate() {
+                *pixel = mix(self.low, self.high, j as f32 / width as f32);
+            }
+        }
+        Ok(Duration::milliseconds(FRAME_MILLIS))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::SpectrumBars(self.clone())
+    }
+}
+
+/// Fills the strip from the start in proportion to the overall loudness.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct VuMeter {
+    pub low: LinSrgb<u8>,
+    pub mid: LinSrgb<u8>,
+    pub high: LinSrgb<u8>,
+}
+
+impl Default for VuMeter {
+    fn default() -> Self {
+        Self {
+            low: LinSrgb::new(0, 255, 0),
+            mid: LinSrgb::new(255, 255, 0),
+            high: LinSrgb::new(255, 0, 0),
+        }
+    }
+}
+
+impl Effect for VuMeter {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], _t: Instant) -> Result<Duration> {
+        let len = pixels.len();
+        let lit = (audio_levels().level * len as f32).round() as usize;
+        for (i, pixel) in pixels.iter_mut().take(lit).enumerate() {
+            let position = i as f32 / len as f32;
+            *pixel = if position < 0.6 {
+                self.low
+            } else if position < 0.85 {
+                self.mid
+            } else {
+                self.high
+            };
+        }
+        Ok(Duration::milliseconds(FRAME_MILLIS))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::VuMeter(self.clone())
+    }
+}
+
+/// Lights the whole strip on every beat and fades it out over `decay`.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BeatFlash {
+    pub color: LinSrgb<u8>,
+
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub decay: Duration,
+}
+
+impl Default for BeatFlash {
+    fn default() -> Self {
+        Self {
+            color: LinSrgb::new(255, 255, 255),
+            decay: Duration::milliseconds(200),
+        }
+    }
+}
+
+impl Effect for BeatFlash {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        let decay = self.decay.num_milliseconds().max(1) as f32;
+        let brightness = audio_levels()
+            .since_beat(t)
+            .map(|d| 1.0 - d.num_milliseconds() as f32 / decay)
+            .unwrap_or(0.0);
+        if brightness > 0.0 {
+            let color = scale(self.color, brightness);
+            for pixel in pixels {
+                *pixel = color;
+            }
+        }
+        Ok(Duration::milliseconds(FRAME_MILLIS))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::BeatFlash(self.clone())
+    }
+}
+
+/// Band levels, from lowest to highest frequency.
+#[rune::function]
+fn bands() -> Vec<f64> {
+    audio_levels().bands.iter().map(|&b| b as f64).collect()
+}
+
+#[rune::function]
+fn band(idx: usize) -> f64 {
+    audio_levels().bands.get(idx).copied().unwrap_or_default() as f64
+}
+
+#[rune::function]
+fn level() -> f64 {
+    audio_levels().level as f64
+}
+
+/// When the last beat was, in the same milliseconds as the `t` scripts get.
+#[rune::function]
+fn last_beat() -> Option<i64> {
+    audio_levels().last_beat.map(|b| b.timestamp_millis())
+}
+
+/// The `audio` module, so scripts can react to the same analysis as the
+/// built in effects.
+pub(crate) fn rune_module() -> Result<Module, ContextError> {
+    let mut module = Module::with_crate("audio")?;
+    module.function_meta(bands)?;
+    module.function_meta(band)?;
+    module.function_meta(level)?;
+    module.function_meta(last_beat)?;
+    Ok(module)
+}
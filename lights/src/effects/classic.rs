@@ -0,0 +1,661 @@
+//! The effects every other controller ships with.
+
+use std::f32::consts::PI;
+
+use chrono::Duration;
+use palette::{convert::FromColor, Hsv, LinSrgb, Srgb};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationMilliSeconds};
+
+use super::{random::Rng, scale, seek, Effect, EffectType, Instant, Simulation, FRAME_MILLIS};
+use crate::error::Result;
+
+/// Where `ms` is in a cycle of `period`, from 0 to 1.
+fn phase(ms: i64, period: Duration) -> f32 {
+    let period = period.num_milliseconds().max(1);
+    ms.rem_euclid(period) as f32 / period as f32
+}
+
+/// How many whole `delay`s `ms` is.
+fn steps(ms: i64, delay: Duration) -> i64 {
+    ms.div_euclid(delay.num_milliseconds().max(1))
+}
+
+/// Time until the next whole `delay`.
+fn until_next_step(ms: i64, delay: Duration) -> Duration {
+    let delay = delay.num_milliseconds().max(1);
+    Duration::milliseconds(delay - ms.rem_euclid(delay))
+}
+
+/// Milliseconds into an effect's run, for `render_at`.
+fn since(start: Instant, t: Instant) -> i64 {
+    t.signed_duration_since(start).num_milliseconds()
+}
+
+/// Fire2012, after Mark Kriegsman's FastLED example: a column of heat that
+/// cools as it rises, fed by random sparks at the bottom.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Fire {
+    /// How fast the flames cool down as they rise, 20 to 100 looks good.
+    pub cooling: u8,
+    /// Chance out of 255 of a new spark each step, 50 to 200 looks good.
+    pub sparking: u8,
+    /// Burn from the end of the strip instead of the start.
+    pub reverse: bool,
+
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub delay: Duration,
+
+    #[serde(skip)]
+    heat: Vec<u8>,
+    #[serde(skip)]
+    rng: Rng,
+    #[serde(skip)]
+    last_step: Option<i64>,
+    #[serde(skip)]
+    started: Option<Instant>,
+    #[serde(skip)]
+    last_update: Option<Instant>,
+}
+
+impl Default for Fire {
+    fn default() -> Self {
+        Self {
+            cooling: 55,
+            sparking: 120,
+            reverse: false,
+            delay: Duration::milliseconds(16),
+            heat: vec![],
+            rng: Rng::default(),
+            last_step: None,
+            started: None,
+            last_update: None,
+        }
+    }
+}
+
+impl Fire {
+    fn step(&mut self) {
+        let len = self.heat.len();
+        if len == 0 {
+            return;
+        }
+        let cooling = (self.cooling as usize * 10 / len.max(1) + 2) as u64;
+        for cell in self.heat.iter_mut() {
+            *cell = cell.saturating_sub((self.rng.next_u64() % cooling) as u8);
+        }
+        for k in (2..len).rev() {
+            self.heat[k] = ((self.heat[k - 1] as u16 + 2 * self.heat[k - 2] as u16) / 3) as u8;
+        }
+        if (self.rng.next_u64() % 255) < self.sparking as u64 {
+            let y = self.rng.below(7.min(len));
+            let spark = 160 + self.rng.below(96) as u8;
+            self.heat[y] = self.heat[y].saturating_add(spark);
+        }
+    }
+
+    /// Black through red and yellow to white.
+    fn heat_color(heat: u8) -> LinSrgb<u8> {
+        let t192 = (heat as u16 * 191 / 255) as u8;
+        let ramp = (t192 & 0x3f) << 2;
+        if t192 & 0x80 != 0 {
+            LinSrgb::new(255, 255, ramp)
+        } else if t192 & 0x40 != 0 {
+            LinSrgb::new(255, ramp, 0)
+        } else {
+            LinSrgb::new(ramp, 0, 0)
+        }
+    }
+}
+
+impl Effect for Fire {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        if self.heat.len() != pixels.len() {
+            self.heat = vec![0; pixels.len()];
+        }
+        let step = steps(t.timestamp_millis(), self.delay);
+        // Catch up on missed steps, but not forever after a long pause.
+        let missed = self.last_step.map(|s| (step - s).clamp(0, 10)).unwrap_or(1);
+        for _ in 0..missed {
+            self.step();
+        }
+        self.last_step = Some(step);
+        self.last_update = Some(t);
+
+        let len = pixels.len();
+        for (i, heat) in self.heat.iter().enumerate() {
+            let idx = if self.reverse { len - 1 - i } else { i };
+            pixels[idx] = Self::heat_color(*heat);
+        }
+        Ok(until_next_step(t.timestamp_millis(), self.delay))
+    }
+
+    fn is_ready(&self, t: Instant) -> Result<bool> {
+        Ok(self.last_step != Some(steps(t.timestamp_millis(), self.delay)))
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Fire(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        seek(self, pixels, start, t)
+    }
+}
+
+impl Simulation for Fire {
+    fn progress(&self) -> Option<(Instant, Instant)> {
+        self.started.zip(self.last_update)
+    }
+
+    fn restart(&mut self, start: Instant) {
+        self.heat.clear();
+        self.rng = Rng::default();
+        self.last_step = None;
+        self.started = Some(start);
+        self.last_update = None;
+    }
+}
+
+/// Pixels randomly light up in one of `colors` and fade away.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Twinkle {
+    pub colors: Vec<LinSrgb<u8>>,
+    /// Chance of each pixel starting a twinkle, per second.
+    pub density: f32,
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub fade: Duration,
+
+    #[serde(skip)]
+    twinkles: Vec<(LinSrgb<u8>, f32)>,
+    #[serde(skip)]
+    rng: Rng,
+    #[serde(skip)]
+    last_update: Option<Instant>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl Default for Twinkle {
+    fn default() -> Self {
+        Self {
+            colors: vec![
+                LinSrgb::new(255, 255, 255),
+                LinSrgb::new(255, 200, 80),
+                LinSrgb::new(120, 160, 255),
+            ],
+            density: 0.3,
+            fade: Duration::milliseconds(800),
+            twinkles: vec![],
+            rng: Rng::default(),
+            last_update: None,
+            started: None,
+        }
+    }
+}
+
+impl Effect for Twinkle {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        if self.twinkles.len() != pixels.len() {
+            self.twinkles = vec![(LinSrgb::new(0, 0, 0), 0.0); pixels.len()];
+        }
+        let dt = self
+            .last_update
+            .map(|u| t.signed_duration_since(u).num_milliseconds() as f32 / 1000.0)
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0);
+        self.last_update = Some(t);
+
+        let fade = self.fade.num_milliseconds().max(1) as f32 / 1000.0;
+        for ((color, brightness), pixel) in self.twinkles.iter_mut().zip(pixels.iter_mut()) {
+            *brightness = (*brightness - dt / fade).max(0.0);
+            if *brightness == 0.0 && self.rng.next_f32() < self.density * dt {
+                *color = self
+                    .colors
+                    .get(self.rng.below(self.colors.len()))
+                    .copied()
+                    .unwrap_or(LinSrgb::new(255, 255, 255));
+                *brightness = 1.0;
+            }
+            if *brightness > 0.0 {
+                *pixel = scale(*color, *brightness);
+            }
+        }
+        Ok(Duration::milliseconds(FRAME_MILLIS))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Twinkle(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        seek(self, pixels, start, t)
+    }
+}
+
+impl Simulation for Twinkle {
+    fn progress(&self) -> Option<(Instant, Instant)> {
+        self.started.zip(self.last_update)
+    }
+
+    fn restart(&mut self, start: Instant) {
+        self.twinkles.clear();
+        self.rng = Rng::default();
+        self.started = Some(start);
+        self.last_update = None;
+    }
+}
+
+/// Every `spacing`th pixel lit, marching along like a theater marquee.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TheaterChase {
+    pub color: LinSrgb<u8>,
+    pub spacing: usize,
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub delay: Duration,
+}
+
+impl Default for TheaterChase {
+    fn default() -> Self {
+        Self {
+            color: LinSrgb::new(255, 180, 0),
+            spacing: 3,
+            delay: Duration::milliseconds(100),
+        }
+    }
+}
+
+impl TheaterChase {
+    /// The frame `ms` milliseconds in, from the epoch or the start of a run.
+    fn draw(&self, pixels: &mut [LinSrgb<u8>], ms: i64) -> Duration {
+        let spacing = self.spacing.max(1) as i64;
+        let offset = steps(ms, self.delay).rem_euclid(spacing);
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            if (i as i64 + spacing - offset) % spacing == 0 {
+                *pixel = self.color;
+            }
+        }
+        until_next_step(ms, self.delay)
+    }
+}
+
+impl Effect for TheaterChase {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        Ok(self.draw(pixels, t.timestamp_millis()))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::TheaterChase(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        Ok(self.draw(pixels, since(start, t)))
+    }
+}
+
+/// Fills the strip one pixel per `delay`, then empties it the same way.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ColorWipe {
+    pub color: LinSrgb<u8>,
+    pub reverse: bool,
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub delay: Duration,
+}
+
+impl Default for ColorWipe {
+    fn default() -> Self {
+        Self {
+            color: LinSrgb::new(0, 255, 0),
+            reverse: false,
+            delay: Duration::milliseconds(50),
+        }
+    }
+}
+
+impl ColorWipe {
+    /// The frame `ms` milliseconds in, from the epoch or the start of a run.
+    fn draw(&self, pixels: &mut [LinSrgb<u8>], ms: i64) -> Duration {
+        let len = pixels.len();
+        if len == 0 {
+            return self.delay;
+        }
+        let position = steps(ms, self.delay).rem_euclid(2 * len as i64) as usize;
+        let lit = if position < len {
+            0..position + 1
+        } else {
+            position - len + 1..len
+        };
+        for i in lit {
+            let idx = if self.reverse { len - 1 - i } else { i };
+            pixels[idx] = self.color;
+        }
+        until_next_step(ms, self.delay)
+    }
+}
+
+impl Effect for ColorWipe {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        Ok(self.draw(pixels, t.timestamp_millis()))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::ColorWipe(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        Ok(self.draw(pixels, since(start, t)))
+    }
+}
+
+/// A dot sweeping back and forth with a fading tail, like KITT or a Cylon.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Larson {
+    pub color: LinSrgb<u8>,
+    /// How many pixels the tail fades over.
+    pub tail: usize,
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub delay: Duration,
+}
+
+impl Default for Larson {
+    fn default() -> Self {
+        Self {
+            color: LinSrgb::new(255, 0, 0),
+            tail: 4,
+            delay: Duration::milliseconds(40),
+        }
+    }
+}
+
+impl Larson {
+    /// The frame `ms` milliseconds in, from the epoch or the start of a run.
+    fn draw(&self, pixels: &mut [LinSrgb<u8>], ms: i64) -> Duration {
+        let len = pixels.len() as i64;
+        if len < 2 {
+            pixels.iter_mut().for_each(|p| *p = self.color);
+            return self.delay;
+        }
+        let cycle = 2 * (len - 1);
+        let step = steps(ms, self.delay);
+        let position = |step: i64| {
+            let p = step.rem_euclid(cycle);
+            if p < len {
+                p
+            } else {
+                cycle - p
+            }
+        };
+        // Walking back through earlier steps draws the tail around the ends
+        // the way the dot actually went.
+        for behind in (0..=self.tail as i64).rev() {
+            let brightness = 1.0 - behind as f32 / (self.tail as f32 + 1.0);
+            pixels[position(step - behind) as usize] = scale(self.color, brightness);
+        }
+        until_next_step(ms, self.delay)
+    }
+}
+
+impl Effect for Larson {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        Ok(self.draw(pixels, t.timestamp_millis()))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Larson(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        Ok(self.draw(pixels, since(start, t)))
+    }
+}
+
+/// The whole strip slowly brightening and dimming.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Breathing {
+    pub color: LinSrgb<u8>,
+    /// One full breath, in and out.
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub period: Duration,
+}
+
+impl Default for Breathing {
+    fn default() -> Self {
+        Self {
+            color: LinSrgb::new(0, 120, 255),
+            period: Duration::seconds(4),
+        }
+    }
+}
+
+impl Breathing {
+    /// The frame `ms` milliseconds in, from the epoch or the start of a run.
+    fn draw(&self, pixels: &mut [LinSrgb<u8>], ms: i64) -> Duration {
+        let brightness = (1.0 - (2.0 * PI * phase(ms, self.period)).cos()) / 2.0;
+        // Squared, so it lingers near dark like breathing does.
+        let color = scale(self.color, brightness * brightness);
+        pixels.iter_mut().for_each(|p| *p = color);
+        Duration::milliseconds(FRAME_MILLIS)
+    }
+}
+
+impl Effect for Breathing {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        Ok(self.draw(pixels, t.timestamp_millis()))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Breathing(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        Ok(self.draw(pixels, since(start, t)))
+    }
+}
+
+/// Flashes the whole strip for `on` out of every `period`.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Strobe {
+    pub color: LinSrgb<u8>,
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub period: Duration,
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub on: Duration,
+}
+
+impl Default for Strobe {
+    fn default() -> Self {
+        Self {
+            color: LinSrgb::new(255, 255, 255),
+            period: Duration::milliseconds(500),
+            on: Duration::milliseconds(50),
+        }
+    }
+}
+
+impl Strobe {
+    /// The frame `ms` milliseconds in, from the epoch or the start of a run.
+    fn draw(&self, pixels: &mut [LinSrgb<u8>], ms: i64) -> Duration {
+        let period = self.period.num_milliseconds().max(1);
+        let on = self.on.num_milliseconds().clamp(0, period);
+        let into = ms.rem_euclid(period);
+        if into < on {
+            pixels.iter_mut().for_each(|p| *p = self.color);
+            Duration::milliseconds(on - into)
+        } else {
+            Duration::milliseconds(period - into)
+        }
+    }
+}
+
+impl Effect for Strobe {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        Ok(self.draw(pixels, t.timestamp_millis()))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Strobe(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        Ok(self.draw(pixels, since(start, t)))
+    }
+}
+
+/// Overlapping sine waves run through the hue wheel.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Plasma {
+    /// How fast the waves move, roughly in waves per second.
+    pub speed: f32,
+    /// How many pixels one wave spans.
+    pub wavelength: f32,
+    pub brightness: f32,
+}
+
+impl Default for Plasma {
+    fn default() -> Self {
+        Self {
+            speed: 0.3,
+            wavelength: 30.0,
+            brightness: 1.0,
+        }
+    }
+}
+
+impl Plasma {
+    /// The frame `ms` milliseconds in, from the epoch or the start of a run.
+    fn draw(&self, pixels: &mut [LinSrgb<u8>], ms: i64) -> Duration {
+        // Keep the time small so f32 doesn't lose the fraction.
+        let time = (ms.rem_euclid(1_000_000) as f32 / 1000.0) * self.speed * 2.0 * PI;
+        let k = 2.0 * PI / self.wavelength.max(1.0);
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let x = i as f32 * k;
+            let v = (x + time).sin() + (0.5 * x - 1.3 * time).sin() + (0.25 * x + 0.7 * time).sin();
+            // `v` is between -3 and 3, spread it over the whole wheel.
+            let hue = (v + 3.0) / 6.0 * 360.0;
+            let hsv = Hsv::new(hue, 1.0, self.brightness.clamp(0.0, 1.0));
+            *pixel = Srgb::from_color(hsv).into_linear().into_format();
+        }
+        Duration::milliseconds(FRAME_MILLIS)
+    }
+}
+
+impl Effect for Plasma {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        Ok(self.draw(pixels, t.timestamp_millis()))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Plasma(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        Ok(self.draw(pixels, since(start, t)))
+    }
+}
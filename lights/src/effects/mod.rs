@@ -1,20 +1,59 @@
+mod audio;
+mod classic;
+mod particles;
+mod plugin;
+mod random;
 mod rune;
+mod signal;
 use std::{fmt::Debug, iter, str::FromStr};
 
-use chrono::{serde::ts_milliseconds_option, DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use dyn_clone::DynClone;
-use enterpolation::{linear::ConstEquidistantLinear, Curve};
 use enum_dispatch::enum_dispatch;
-use mopa::mopafy;
-use palette::{convert::FromColor, Hsv, LinSrgb};
+use palette::{LinSrgb, Mix};
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DurationMilliSeconds};
 
+pub use self::audio::*;
+pub use self::classic::*;
+pub use self::particles::*;
+pub use self::plugin::*;
 pub use self::rune::*;
-use crate::error::{Error, Result};
+pub use self::signal::*;
+use crate::{
+    error::{Error, Result},
+    palettes::{Interpolation, Palette, PaletteRef, Stop},
+    signals,
+};
 
 type Instant = DateTime<Utc>;
 
+/// How often effects that move smoothly want to be drawn, which is also
+/// about how often the audio analysis updates.
+pub(super) const FRAME_MILLIS: i64 = 20;
+
+/// Seconds from `started` to `t`, starting the clock at `t` if it hasn't
+/// been started yet.
+fn elapsed_secs(started: &mut Option<Instant>, t: Instant) -> f64 {
+    let started = *started.get_or_insert(t);
+    t.signed_duration_since(started).num_milliseconds().max(0) as f64 / 1000.0
+}
+
+/// Time until `elapsed` next reaches a whole number of `step`s, so effects
+/// that move a step at a time aren't drawn again in between.
+fn until_next_step(elapsed: f64, step: f64) -> Duration {
+    if !step.is_finite() || step <= 0.0 {
+        return Duration::milliseconds(100);
+    }
+    let left = step - elapsed.rem_euclid(step);
+    Duration::milliseconds(((left * 1000.0).ceil() as i64).max(1))
+}
+
+/// `color` dimmed to `f` of its brightness.
+pub(super) fn scale(color: LinSrgb<u8>, f: f32) -> LinSrgb<u8> {
+    (color.into_format::<f32>() * f.clamp(0.0, 1.0)).into_format()
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum EffectType {
     Empty(Empty),
@@ -23,7 +62,22 @@ pub enum EffectType {
     Glow(Glow),
     Rainbow(Rainbow),
     Composite(Composite),
-    RuneScript(SourceCode),
+    SpectrumBars(SpectrumBars),
+    VuMeter(VuMeter),
+    BeatFlash(BeatFlash),
+    SignalFlash(SignalFlash),
+    Particles(Particles),
+    Fire(Fire),
+    Twinkle(Twinkle),
+    TheaterChase(TheaterChase),
+    ColorWipe(ColorWipe),
+    Larson(Larson),
+    Breathing(Breathing),
+    Strobe(Strobe),
+    Plasma(Plasma),
+    RuneScript(SourceCode, ParamValues),
+    /// A WASM module from the plugin store, by name.
+    WasmPlugin(String),
 }
 
 impl EffectType {
@@ -35,7 +89,21 @@ impl EffectType {
             "Glow",
             "Rainbow",
             "Composite",
+            "Spectrum",
+            "VU Meter",
+            "Beat Flash",
+            "Signal Flash",
+            "Particles",
+            "Fire",
+            "Twinkle",
+            "Theater Chase",
+            "Color Wipe",
+            "Larson Scanner",
+            "Breathing",
+            "Strobe",
+            "Plasma",
             "Rune Script",
+            "Wasm Plugin",
         ]
         .into_iter()
     }
@@ -48,51 +116,128 @@ impl EffectType {
             EffectType::Balls(_) => "Balls",
             EffectType::Glow(_) => "Glow",
             EffectType::Rainbow(_) => "Rainbow",
-            EffectType::RuneScript(_) => "Rune Script",
+            EffectType::SpectrumBars(_) => "Spectrum",
+            EffectType::VuMeter(_) => "VU Meter",
+            EffectType::BeatFlash(_) => "Beat Flash",
+            EffectType::SignalFlash(_) => "Signal Flash",
+            EffectType::Particles(_) => "Particles",
+            EffectType::Fire(_) => "Fire",
+            EffectType::Twinkle(_) => "Twinkle",
+            EffectType::TheaterChase(_) => "Theater Chase",
+            EffectType::ColorWipe(_) => "Color Wipe",
+            EffectType::Larson(_) => "Larson Scanner",
+            EffectType::Breathing(_) => "Breathing",
+            EffectType::Strobe(_) => "Strobe",
+            EffectType::Plasma(_) => "Plasma",
+            EffectType::RuneScript(..) => "Rune Script",
+            EffectType::WasmPlugin(_) => "Wasm Plugin",
         }
     }
 
+    /// Whether the effect can be drawn at any moment, see `Effect::render_at`.
+    /// Scripts and plugins never can, so they're ruled out before `inner_ref`.
+    pub fn is_seekable(&self) -> bool {
+        !self.is_scripted() && self.inner_ref().is_ok_and(|e| e.is_seekable())
+    }
+
+    /// Scripts and plugins can't be copied into the `&dyn Effect` that
+    /// `inner_ref` hands out, so they can't go inside a `Composite`.
+    pub fn is_scripted(&self) -> bool {
+        matches!(self, EffectType::RuneScript(..) | EffectType::WasmPlugin(_))
+    }
+
     pub fn into_inner(self) -> Box<dyn Effect> {
-        match self {
+        match self.try_into_inner() {
+            Ok(effect) => effect,
+            Err(e) => {
+                log::error!("Error compiling: {:?}", e);
+                panic!("Error");
+            }
+        }
+    }
+
+    /// Like `into_inner`, but hands back the error when a script doesn't
+    /// compile instead of panicking.
+    pub fn try_into_inner(self) -> Result<Box<dyn Effect>> {
+        Ok(match self {
             EffectType::Empty(Empty) => Box::new(Empty),
             EffectType::Composite(c) => Box::new(c),
             EffectType::Ball(b) => Box::new(b),
             EffectType::Balls(bs) => Box::new(bs),
             EffectType::Glow(g) => Box::new(g),
             EffectType::Rainbow(r) => Box::new(r),
-            EffectType::RuneScript(s) => {
-                let source = RuneScript::from_source(s);
-                if source.is_err() {
-                    log::error!("Error compiling: {:?}", source);
-                    panic!("Error");
-                }
-                Box::new(source.unwrap())
-            }
-        }
-    }
-
-    pub fn inner_ref(&self) -> &dyn Effect {
-        match self {
+            EffectType::SpectrumBars(s) => Box::new(s),
+            EffectType::VuMeter(v) => Box::new(v),
+            EffectType::BeatFlash(b) => Box::new(b),
+            EffectType::SignalFlash(f) => Box::new(f),
+            EffectType::Particles(p) => Box::new(p),
+            EffectType::Fire(f) => Box::new(f),
+            EffectType::Twinkle(t) => Box::new(t),
+            EffectType::TheaterChase(c) => Box::new(c),
+            EffectType::ColorWipe(w) => Box::new(w),
+            EffectType::Larson(l) => Box::new(l),
+            EffectType::Breathing(b) => Box::new(b),
+            EffectType::Strobe(s) => Box::new(s),
+            EffectType::Plasma(p) => Box::new(p),
+            EffectType::RuneScript(s, p) => Box::new(RuneScript::with_params(s, p)?),
+            EffectType::WasmPlugin(name) => Box::new(WasmPlugin::load(name)?),
+        })
+    }
+
+    pub fn inner_ref(&self) -> Result<&dyn Effect> {
+        let effect: &dyn Effect = match self {
             EffectType::Empty(Empty) => &Empty,
             EffectType::Composite(c) => c,
             EffectType::Ball(b) => b,
             EffectType::Balls(bs) => bs,
             EffectType::Glow(g) => g,
             EffectType::Rainbow(r) => r,
-            EffectType::RuneScript(s) => todo!(),
-        }
+            EffectType::SpectrumBars(s) => s,
+            EffectType::VuMeter(v) => v,
+            EffectType::BeatFlash(b) => b,
+            EffectType::SignalFlash(f) => f,
+            EffectType::Particles(p) => p,
+            EffectType::Fire(f) => f,
+            EffectType::Twinkle(t) => t,
+            EffectType::TheaterChase(c) => c,
+            EffectType::ColorWipe(w) => w,
+            EffectType::Larson(l) => l,
+            EffectType::Breathing(b) => b,
+            EffectType::Strobe(s) => s,
+            EffectType::Plasma(p) => p,
+            EffectType::RuneScript(..) | EffectType::WasmPlugin(_) => {
+                return Err(Error::CompositeScriptError)
+            }
+        };
+        Ok(effect)
     }
 
-    pub fn inner_mut_ref(&mut self) -> &mut dyn Effect {
-        match self {
+    pub fn inner_mut_ref(&mut self) -> Result<&mut dyn Effect> {
+        let effect: &mut dyn Effect = match self {
             EffectType::Empty(e) => e,
             EffectType::Composite(c) => c,
             EffectType::Ball(b) => b,
             EffectType::Balls(bs) => bs,
             EffectType::Glow(g) => g,
             EffectType::Rainbow(r) => r,
-            EffectType::RuneScript(s) => todo!(),
-        }
+            EffectType::SpectrumBars(s) => s,
+            EffectType::VuMeter(v) => v,
+            EffectType::BeatFlash(b) => b,
+            EffectType::SignalFlash(f) => f,
+            EffectType::Particles(p) => p,
+            EffectType::Fire(f) => f,
+            EffectType::Twinkle(t) => t,
+            EffectType::TheaterChase(c) => c,
+            EffectType::ColorWipe(w) => w,
+            EffectType::Larson(l) => l,
+            EffectType::Breathing(b) => b,
+            EffectType::Strobe(s) => s,
+            EffectType::Plasma(p) => p,
+            EffectType::RuneScript(..) | EffectType::WasmPlugin(_) => {
+                return Err(Error::CompositeScriptError)
+            }
+        };
+        Ok(effect)
     }
 
     pub fn default_from_name(name: &str) -> Self {
@@ -103,7 +248,21 @@ impl EffectType {
             "Glow" => Self::Glow(Default::default()),
             "Rainbow" => Self::Rainbow(Default::default()),
             "Composite" => Self::Composite(Default::default()),
-            "Rune Script" => Self::RuneScript(Default::default()),
+            "Spectrum" => Self::SpectrumBars(Default::default()),
+            "VU Meter" => Self::VuMeter(Default::default()),
+            "Beat Flash" => Self::BeatFlash(Default::default()),
+            "Signal Flash" => Self::SignalFlash(Default::default()),
+            "Particles" => Self::Particles(Default::default()),
+            "Fire" => Self::Fire(Default::default()),
+            "Twinkle" => Self::Twinkle(Default::default()),
+            "Theater Chase" => Self::TheaterChase(Default::default()),
+            "Color Wipe" => Self::ColorWipe(Default::default()),
+            "Larson Scanner" => Self::Larson(Default::default()),
+            "Breathing" => Self::Breathing(Default::default()),
+            "Strobe" => Self::Strobe(Default::default()),
+            "Plasma" => Self::Plasma(Default::default()),
+            "Rune Script" => Self::RuneScript(Default::default(), Default::default()),
+            "Wasm Plugin" => Self::WasmPlugin(Default::default()),
             _ => Default::default(),
         }
     }
@@ -126,7 +285,21 @@ impl FromStr for EffectType {
             "Balls" => Ok(Self::Balls(Default::default())),
             "Glow" => Ok(Self::Glow(Default::default())),
             "Rainbow" => Ok(Self::Rainbow(Default::default())),
-            "Rune Script" => Ok(Self::RuneScript(Default::default())),
+            "Spectrum" => Ok(Self::SpectrumBars(Default::default())),
+            "VU Meter" => Ok(Self::VuMeter(Default::default())),
+            "Beat Flash" => Ok(Self::BeatFlash(Default::default())),
+            "Signal Flash" => Ok(Self::SignalFlash(Default::default())),
+            "Particles" => Ok(Self::Particles(Default::default())),
+            "Fire" => Ok(Self::Fire(Default::default())),
+            "Twinkle" => Ok(Self::Twinkle(Default::default())),
+            "Theater Chase" => Ok(Self::TheaterChase(Default::default())),
+            "Color Wipe" => Ok(Self::ColorWipe(Default::default())),
+            "Larson Scanner" => Ok(Self::Larson(Default::default())),
+            "Breathing" => Ok(Self::Breathing(Default::default())),
+            "Strobe" => Ok(Self::Strobe(Default::default())),
+            "Plasma" => Ok(Self::Plasma(Default::default())),
+            "Rune Script" => Ok(Self::RuneScript(Default::default(), Default::default())),
+            "Wasm Plugin" => Ok(Self::WasmPlugin(Default::default())),
             _ => Err(Error::BadEffectType),
         }
     }
@@ -168,9 +341,93 @@ impl From<Rainbow> for EffectType {
     }
 }
 
+impl From<SpectrumBars> for EffectType {
+    fn from(orig: SpectrumBars) -> Self {
+        Self::SpectrumBars(orig)
+    }
+}
+
+impl From<VuMeter> for EffectType {
+    fn from(orig: VuMeter) -> Self {
+        Self::VuMeter(orig)
+    }
+}
+
+impl From<BeatFlash> for EffectType {
+    fn from(orig: BeatFlash) -> Self {
+        Self::BeatFlash(orig)
+    }
+}
+
+impl From<SignalFlash> for EffectType {
+    fn from(orig: SignalFlash) -> Self {
+        Self::SignalFlash(orig)
+    }
+}
+
+impl From<Particles> for EffectType {
+    fn from(orig: Particles) -> Self {
+        Self::Particles(orig)
+    }
+}
+
+impl From<Fire> for EffectType {
+    fn from(orig: Fire) -> Self {
+        Self::Fire(orig)
+    }
+}
+
+impl From<Twinkle> for EffectType {
+    fn from(orig: Twinkle) -> Self {
+        Self::Twinkle(orig)
+    }
+}
+
+impl From<TheaterChase> for EffectType {
+    fn from(orig: TheaterChase) -> Self {
+        Self::TheaterChase(orig)
+    }
+}
+
+impl From<ColorWipe> for EffectType {
+    fn from(orig: ColorWipe) -> Self {
+        Self::ColorWipe(orig)
+    }
+}
+
+impl From<Larson> for EffectType {
+    fn from(orig: Larson) -> Self {
+        Self::Larson(orig)
+    }
+}
+
+impl From<Breathing> for EffectType {
+    fn from(orig: Breathing) -> Self {
+        Self::Breathing(orig)
+    }
+}
+
+impl From<Strobe> for EffectType {
+    fn from(orig: Strobe) -> Self {
+        Self::Strobe(orig)
+    }
+}
+
+impl From<Plasma> for EffectType {
+    fn from(orig: Plasma) -> Self {
+        Self::Plasma(orig)
+    }
+}
+
+impl From<WasmPlugin> for EffectType {
+    fn from(orig: WasmPlugin) -> Self {
+        Self::WasmPlugin(orig.name().to_owned())
+    }
+}
+
 impl From<RuneScript> for EffectType {
     fn from(orig: RuneScript) -> Self {
-        Self::RuneScript(orig.sourcecode)
+        Self::RuneScript(orig.sourcecode, orig.params)
     }
 }
 
@@ -182,9 +439,77 @@ pub trait Effect:
     fn is_ready(&self, t: Instant) -> Result<bool>;
 
     fn to_cloned_type(&self) -> EffectType;
+
+    /// Whether `render_at` works, so any moment can be drawn without drawing
+    /// the ones before it first.
+    fn is_seekable(&self) -> bool {
+        false
+    }
+
+    /// Draw how the effect looks at `t` if it started at `start`, whatever
+    /// was drawn before. Effects driven by the outside world, like audio or
+    /// scripts, can't do this and give `Error::NotSeekable`.
+    fn render_at(
+        &mut self,
+        _pixels: &mut [LinSrgb<u8>],
+        _start: Instant,
+        _t: Instant,
+    ) -> Result<Duration> {
+        Err(Error::NotSeekable)
+    }
 }
 
-mopafy!(Effect);
+/// An effect that can only step forward from its last frame, like a particle
+/// system, so it seeks by running itself again from the start.
+pub(crate) trait Simulation: Effect {
+    /// When the run started and when it was last drawn, if it has been
+    /// `restart`ed since it was made.
+    fn progress(&self) -> Option<(Instant, Instant)>;
+
+    /// Forget everything that has happened, ready to start again at `start`.
+    fn restart(&mut self, start: Instant);
+}
+
+/// `render_at` for a `Simulation`. Carries on from the last frame when that
+/// is earlier in the same run, otherwise starts over, then draws every frame
+/// the strip would have up to `t`.
+pub(crate) fn seek<S: Simulation>(
+    sim: &mut S,
+    pixels: &mut [LinSrgb<u8>],
+    start: Instant,
+    t: Instant,
+) -> Result<Duration> {
+    let mut now = match sim.progress() {
+        Some((started, last)) if started == start && last <= t => last,
+        _ => {
+            sim.restart(start);
+            start
+        }
+    };
+    let mut scratch = vec![LinSrgb::new(0, 0, 0); pixels.len()];
+    while now < t {
+        scratch.fill(LinSrgb::new(0, 0, 0));
+        let frame = sim.render(&mut scratch, now)?;
+        // However short a frame an effect asks for, make progress.
+        let next = now + frame.max(Duration::milliseconds(1));
+        if next >= t {
+            break;
+        }
+        now = next;
+    }
+    sim.render(pixels, t)
+}
+
+// mopa's downcasts transmute pointers, which newer clippy flags inside the
+// expansion.
+#[allow(clippy::transmute_ptr_to_ref)]
+mod downcast {
+    use mopa::mopafy;
+
+    use super::Effect;
+
+    mopafy!(Effect);
+}
 
 dyn_clone::clone_trait_object!(Effect);
 
@@ -203,11 +528,37 @@ impl Effect for Empty {
     fn to_cloned_type(&self) -> EffectType {
         EffectType::Empty(Empty)
     }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        _start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        self.render(pixels, t)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "CompositeParts")]
 pub struct Composite(Box<EffectType>, Box<EffectType>);
 
+/// How a `Composite` is stored, checked by `Composite::new` on the way in.
+#[derive(Deserialize)]
+struct CompositeParts(Box<EffectType>, Box<EffectType>);
+
+impl TryFrom<CompositeParts> for Composite {
+    type Error = Error;
+
+    fn try_from(CompositeParts(first, second): CompositeParts) -> Result<Self> {
+        Self::new(*first, *second)
+    }
+}
+
 impl Default for Composite {
     fn default() -> Self {
         Self::new(EffectType::default(), EffectType::default()).expect("empty")
@@ -216,8 +567,7 @@ impl Default for Composite {
 
 impl Composite {
     pub fn new(first: EffectType, second: EffectType) -> Result<Self> {
-        if matches!(first, EffectType::RuneScript(_)) || matches!(second, EffectType::RuneScript(_))
-        {
+        if first.is_scripted() || second.is_scripted() {
             Err(Error::CompositeScriptError)
         } else {
             Ok(Self(Box::new(first), Box::new(second)))
@@ -229,10 +579,10 @@ impl Composite {
     }
 
     pub fn set_first(&mut self, e: EffectType) -> Result<()> {
-        if matches!(e, EffectType::RuneScript(_)) {
+        if e.is_scripted() {
             return Err(Error::CompositeScriptError);
         }
-        self.0 = Box::new(e);
+        *self.0 = e;
         Ok(())
     }
 
@@ -241,22 +591,22 @@ impl Composite {
     }
 
     pub fn set_second(&mut self, e: EffectType) -> Result<()> {
-        if matches!(e, EffectType::RuneScript(_)) {
+        if e.is_scripted() {
             return Err(Error::CompositeScriptError);
         }
-        self.1 = Box::new(e);
+        *self.1 = e;
         Ok(())
     }
 }
 
 impl Effect for Composite {
     fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
-        let d = self.0.inner_mut_ref().render(pixels, t)?;
-        Ok(std::cmp::min(d, self.1.inner_mut_ref().render(pixels, t)?))
+        let d = self.0.inner_mut_ref()?.render(pixels, t)?;
+        Ok(std::cmp::min(d, self.1.inner_mut_ref()?.render(pixels, t)?))
     }
 
     fn is_ready(&self, t: Instant) -> Result<bool> {
-        [self.0.inner_ref(), self.1.inner_ref()]
+        [self.0.inner_ref()?, self.1.inner_ref()?]
             .iter()
             .try_fold(false, |accum, e| Ok(accum || e.is_ready(t)?))
     }
@@ -264,21 +614,42 @@ impl Effect for Composite {
     fn to_cloned_type(&self) -> EffectType {
         EffectType::Composite(self.clone())
     }
+
+    fn is_seekable(&self) -> bool {
+        self.0.is_seekable() && self.1.is_seekable()
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        let d = self.0.inner_mut_ref()?.render_at(pixels, start, t)?;
+        Ok(std::cmp::min(
+            d,
+            self.1.inner_mut_ref()?.render_at(pixels, start, t)?,
+        ))
+    }
 }
 
-#[serde_as]
+/// A single lit pixel running along the strip.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Ball {
     pub color: LinSrgb<u8>,
+    /// Where the ball starts.
     pub position: usize,
     pub count: usize,
     pub direction: i8,
     pub bounce: bool,
-
-    #[serde_as(as = "DurationMilliSeconds<i64>")]
-    pub delay: Duration,
-    #[serde(with = "ts_milliseconds_option")]
-    next_update: Option<Instant>,
+    /// Pixels per second.
+    pub speed: f32,
+    /// Glide between pixels instead of jumping from one to the next.
+    #[serde(default)]
+    pub smooth: bool,
+
+    #[serde(skip)]
+    started: Option<Instant>,
 }
 
 impl Ball {
@@ -287,7 +658,7 @@ impl Ball {
         position: usize,
         direction: i8,
         bounce: bool,
-        delay: Duration,
+        speed: f32,
         count: usize,
     ) -> Self {
         Self {
@@ -296,55 +667,47 @@ impl Ball {
             direction,
             bounce,
             count,
+            speed,
+            smooth: false,
 
-            delay,
-            next_update: None,
+            started: None,
         }
     }
 
-    pub fn wrap(color: LinSrgb<u8>, millis: i64, count: usize) -> Self {
-        Self::new(color, 0, 1, false, Duration::milliseconds(millis), count)
+    pub fn wrap(color: LinSrgb<u8>, speed: f32, count: usize) -> Self {
+        Self::new(color, 0, 1, false, speed, count)
     }
 
-    pub fn wrap_backward(color: LinSrgb<u8>, millis: i64, count: usize) -> Self {
-        Self::new(
-            color,
-            count - 1,
-            -1,
-            false,
-            Duration::milliseconds(millis),
-            count,
-        )
+    pub fn wrap_backward(color: LinSrgb<u8>, speed: f32, count: usize) -> Self {
+        Self::new(color, count - 1, -1, false, speed, count)
     }
 
-    pub fn bounce(color: LinSrgb<u8>, millis: i64, count: usize) -> Self {
-        Self::new(color, 0, 1, true, Duration::milliseconds(millis), count)
+    pub fn bounce(color: LinSrgb<u8>, speed: f32, count: usize) -> Self {
+        Self::new(color, 0, 1, true, speed, count)
     }
 
-    pub fn bounce_backward(color: LinSrgb<u8>, millis: i64, count: usize) -> Self {
-        Self::new(
-            color,
-            count - 1,
-            -1,
-            true,
-            Duration::milliseconds(millis),
-            count,
-        )
+    pub fn bounce_backward(color: LinSrgb<u8>, speed: f32, count: usize) -> Self {
+        Self::new(color, count - 1, -1, true, speed, count)
     }
 
-    fn update_state(&mut self) {
-        if self.bounce {
-            if self.position == self.count - 1 {
-                self.direction = -1;
-            } else if self.position == 0 {
-                self.direction = 1;
+    /// Where the ball is `elapsed` seconds after it started, in pixels along
+    /// a strip of `len`.
+    fn position_at(&self, elapsed: f64, len: usize) -> f64 {
+        let mut travelled = elapsed * self.speed as f64 * self.direction.signum() as f64;
+        if !self.smooth {
+            travelled = travelled.trunc();
+        }
+        let start = self.position.min(len - 1) as f64;
+        if self.bounce && len > 1 {
+            let span = (len - 1) as f64;
+            let p = (start + travelled).rem_euclid(2.0 * span);
+            if p > span {
+                2.0 * span - p
+            } else {
+                p
             }
         } else {
-            if self.position == self.count - 1 && self.direction > 0 {
-                self.position = 0;
-            } else if self.position == 0 && self.direction < 0 {
-                self.position = self.count - 1;
-            }
+            (start + travelled).rem_euclid(len as f64)
         }
     }
 
@@ -365,43 +728,63 @@ impl Default for Ball {
             count: 1,
             direction: 1,
             bounce: false,
-            delay: Duration::milliseconds(100),
-            next_update: None,
+            speed: 10.0,
+            smooth: false,
+            started: None,
         }
     }
 }
 
 impl Effect for Ball {
     fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
-        if self.count != pixels.len() {
-            self.count = pixels.len();
+        let len = pixels.len();
+        if len == 0 {
+            return Ok(Duration::milliseconds(FRAME_MILLIS));
         }
-        if self.is_ready(t)? {
-            self.update_state();
-            let pixel = (self.position as isize + self.direction as isize) as usize % pixels.len();
-            self.position = pixel;
-            self.next_update = Some(t + self.delay);
+        self.count = len;
+        let elapsed = elapsed_secs(&mut self.started, t);
+        let position = self.position_at(elapsed, len);
+
+        if !self.smooth {
+            pixels[position as usize % len] = self.color;
+            return Ok(until_next_step(elapsed, 1.0 / self.speed.abs() as f64));
         }
-        pixels[self.position] = self.color;
-
-        let time_left = self
-            .next_update
-            .map(|u| u.signed_duration_since(t))
-            .unwrap_or(Duration::seconds(0));
-        if self.delay > time_left {
-            Ok(self.delay - time_left)
-        } else {
-            Ok(self.delay)
+        // Split the ball between the two pixels it is over.
+        let first = position.floor() as usize % len;
+        let frac = position.fract() as f32;
+        for (i, coverage) in [(first, 1.0 - frac), ((first + 1) % len, frac)] {
+            let lit = scale(self.color, coverage);
+            let pixel = &mut pixels[i];
+            *pixel = LinSrgb::new(
+                pixel.red.max(lit.red),
+                pixel.green.max(lit.green),
+                pixel.blue.max(lit.blue),
+            );
         }
+        Ok(Duration::milliseconds(FRAME_MILLIS))
     }
 
-    fn is_ready(&self, t: Instant) -> Result<bool> {
-        Ok(self.next_update.is_none() || t >= self.next_update.unwrap())
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
     }
 
     fn to_cloned_type(&self) -> EffectType {
         EffectType::Ball(self.clone())
     }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        self.started = Some(start);
+        self.render(pixels, t)
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -452,34 +835,59 @@ impl Effect for Balls {
     fn to_cloned_type(&self) -> EffectType {
         EffectType::Balls(self.clone())
     }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        let mut min = Duration::seconds(1);
+        for ball in self.0.iter_mut() {
+            min = min.min(ball.render_at(pixels, start, t)?);
+        }
+        Ok(min)
+    }
 }
 
 #[serde_as]
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Glow {
     colors: Vec<LinSrgb<u8>>,
-    color_idx: usize,
-    cur_color: LinSrgb<u8>,
-    step: usize,
+    /// Steps from one color to the next, unless `smooth`.
     pub steps: usize,
-
+    /// Blend continuously instead of in `steps` steps.
+    #[serde(default)]
+    pub smooth: bool,
+    /// A signal to tint the glow by, see `Signal::tint`.
+    #[serde(default)]
+    pub tint: Option<String>,
+    /// Glow through this palette instead of `colors`.
+    #[serde(default)]
+    pub palette: Option<PaletteRef>,
+
+    /// How long one trip through every color takes.
     #[serde_as(as = "DurationMilliSeconds<i64>")]
-    pub delay: Duration,
-    #[serde(with = "ts_milliseconds_option")]
-    next_update: Option<Instant>,
+    pub period: Duration,
+    #[serde(skip)]
+    started: Option<Instant>,
 }
 
 impl Glow {
-    pub fn new(colors: Vec<LinSrgb<u8>>, steps: usize, delay: Duration) -> Self {
+    pub fn new(colors: Vec<LinSrgb<u8>>, steps: usize, period: Duration) -> Self {
         Self {
-            cur_color: colors[0],
             colors,
-            color_idx: 0,
-            step: 0,
             steps,
+            smooth: false,
+            tint: None,
+            palette: None,
 
-            delay,
-            next_update: None,
+            period,
+            started: None,
         }
     }
 
@@ -498,6 +906,23 @@ impl Glow {
     pub fn remove_color(&mut self, idx: usize) {
         self.colors.remove(idx);
     }
+
+    /// What we glow through, with our own colors spread evenly round a loop.
+    fn gradient(&self) -> Palette {
+        match &self.palette {
+            Some(palette) => palette.resolve(),
+            None => {
+                let n = self.colors.len() as f32;
+                let stops = self
+                    .colors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| Stop::new(i as f32 / n, *c))
+                    .collect();
+                Palette::new(stops, Interpolation::LinearRgb)
+            }
+        }
+    }
 }
 
 impl Default for Glow {
@@ -505,68 +930,97 @@ impl Default for Glow {
         Self::new(
             vec![LinSrgb::new(0, 0, 0), LinSrgb::new(255, 0, 0)],
             10,
-            Duration::milliseconds(100),
+            Duration::seconds(2),
         )
     }
 }
 
 impl Effect for Glow {
     fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
-        let color: LinSrgb<u8> = if self.is_ready(t)? {
-            let gradient = ConstEquidistantLinear::<f32, _, 2>::equidistant_unchecked([
-                self.colors[self.color_idx].into_format::<f32>(),
-                self.colors[(self.color_idx + 1) % self.colors.len()].into_format(),
-            ]);
-            let color = gradient.take(self.steps).skip(self.step).next().unwrap();
-            self.step += 1;
-            if self.step == self.steps {
-                self.step = 0;
-                self.color_idx = (self.color_idx + 1) % self.colors.len();
-            }
-
-            self.next_update = Some(t + self.delay);
-            color.into_format()
+        let elapsed = elapsed_secs(&mut self.started, t);
+        let period = (self.period.num_milliseconds().max(1) as f64) / 1000.0;
+        let gradient = self.gradient();
+        let phase = (elapsed / period).fract();
+        let (phase, next) = if self.smooth {
+            (phase, Duration::milliseconds(FRAME_MILLIS))
         } else {
-            LinSrgb::new(0, 0, 0)
+            let steps = (gradient.stops().len().max(1) * self.steps.max(1)) as f64;
+            (
+                (phase * steps).floor() / steps,
+                until_next_step(elapsed, period / steps),
+            )
+        };
+        let color = gradient.sample_cyclic(phase as f32);
+        let color = match self.tint.as_deref().and_then(signals::signal) {
+            Some(signal) => signal.value.tint(color),
+            None => color,
         };
 
         for pixel in pixels {
             *pixel = color;
         }
-
-        let time_left = self.next_update.map(|u| u.signed_duration_since(t));
-        if time_left.is_none() || self.delay <= time_left.unwrap() {
-            Ok(self.delay)
-        } else {
-            Ok(self.delay - time_left.unwrap())
-        }
+        Ok(next)
     }
 
-    fn is_ready(&self, t: Instant) -> Result<bool> {
-        Ok(self.next_update.map(|u| t >= u).unwrap_or(true))
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
     }
 
     fn to_cloned_type(&self) -> EffectType {
         EffectType::Glow(self.clone())
     }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        self.started = Some(start);
+        self.render(pixels, t)
+    }
 }
 
-#[serde_as]
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Rainbow {
     colors: Vec<LinSrgb<u8>>,
     color_width: usize,
     spacing: usize,
     pub direction: i8,
-
-    step: usize,
-
+    /// Draw a smooth gradient from this palette instead of `colors`,
+    /// `color_width` pixels for each stop.
+    #[serde(default)]
+    pub palette: Option<PaletteRef>,
+    /// Pixels per second.
+    pub speed: f32,
+    /// Glide between pixels instead of jumping from one to the next.
+    #[serde(default)]
+    pub smooth: bool,
+
+    #[serde(skip)]
     color_strip: Vec<LinSrgb<u8>>,
+    /// The palette `color_strip` was drawn from, to notice it changing.
+    #[serde(skip)]
+    drawn_palette: Option<Palette>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
 
-    #[serde_as(as = "DurationMilliSeconds<i64>")]
-    pub delay: Duration,
-    #[serde(with = "ts_milliseconds_option")]
-    next_update: Option<Instant>,
+impl PartialEq for Rainbow {
+    fn eq(&self, other: &Self) -> bool {
+        // Only the settings, the strip is redrawn from them when needed.
+        self.colors == other.colors
+            && self.color_width == other.color_width
+            && self.spacing == other.spacing
+            && self.direction == other.direction
+            && self.palette == other.palette
+            && self.speed == other.speed
+            && self.smooth == other.smooth
+    }
 }
 
 impl Rainbow {
@@ -575,33 +1029,50 @@ impl Rainbow {
         color_width: usize,
         spacing: usize,
         direction: i8,
-        delay: Duration,
+        speed: f32,
     ) -> Self {
         let mut this = Self {
             colors,
             color_width,
             spacing,
             direction,
-            step: 0,
+            palette: None,
+            speed,
+            smooth: false,
             color_strip: vec![],
-            delay,
-            next_update: None,
+            drawn_palette: None,
+            started: None,
         };
         this.generate_color_strip();
         this
     }
 
     fn generate_color_strip(&mut self) {
+        self.drawn_palette = self.palette.as_ref().map(PaletteRef::resolve);
+        if let Some(palette) = &self.drawn_palette {
+            let width = self.color_width.max(1);
+            let len = palette.stops().len().max(1) * width;
+            self.color_strip = (0..len)
+                .flat_map(|i| {
+                    let color = palette.sample_cyclic(i as f32 / len as f32);
+                    let gap = if (i + 1) % width == 0 {
+                        self.spacing
+                    } else {
+                        0
+                    };
+                    iter::once(color).chain(iter::repeat_n(LinSrgb::new(0u8, 0, 0), gap))
+                })
+                .collect();
+            return;
+        }
         self.color_strip = self
             .colors
             .iter()
             .cloned()
-            .map(|c| {
-                iter::repeat(c)
-                    .take(self.color_width)
-                    .chain(iter::repeat(LinSrgb::new(0u8, 0, 0)).take(self.spacing))
+            .flat_map(|c| {
+                iter::repeat_n(c, self.color_width)
+                    .chain(iter::repeat_n(LinSrgb::new(0u8, 0, 0), self.spacing))
             })
-            .flatten()
             .collect::<Vec<_>>();
         log::info!("strip length: {}", self.color_strip.len());
     }
@@ -646,38 +1117,69 @@ impl Rainbow {
 
 impl Effect for Rainbow {
     fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
-        let len = pixels.len();
-        let tmp = &self.color_strip[..]
-            .iter()
-            .cloned()
-            .cycle()
-            .take(len)
-            .collect::<Vec<_>>();
-        pixels[self.step..].copy_from_slice(&tmp[..len - self.step]);
-        pixels[..self.step].copy_from_slice(&tmp[len - self.step..]);
-        // pixels[self.step] = LinSrgb::new(255, 255, 255);
-
-        if self.is_ready(t)? {
-            self.step =
-                (self.step as isize + (1 * self.direction as isize)) as usize % pixels.len();
-            self.next_update = Some(t + self.delay);
+        // Named palettes can be edited while we run.
+        if self.color_strip.is_empty()
+            || self.palette.as_ref().map(PaletteRef::resolve) != self.drawn_palette
+        {
+            self.generate_color_strip();
+        }
+        let strip = &self.color_strip[..];
+        let n = strip.len();
+        if n == 0 {
+            return Ok(Duration::milliseconds(FRAME_MILLIS));
         }
 
-        let time_left = self.next_update.map(|u| u.signed_duration_since(t));
-        if time_left.is_none() || self.delay <= time_left.unwrap() {
-            Ok(self.delay)
+        let elapsed = elapsed_secs(&mut self.started, t);
+        let mut offset = elapsed * self.speed as f64 * self.direction.signum() as f64;
+        if !self.smooth {
+            offset = offset.trunc();
+        }
+        let offset = offset.rem_euclid(n as f64);
+        let whole = offset.floor() as usize;
+        let frac = offset.fract() as f32;
+        // Moving forward, each pixel shows the part of the strip `offset`
+        // pixels behind it.
+        for (i, pixel) in pixels.iter_mut().enumerate() {
+            let at = (i % n + n - whole) % n;
+            *pixel = if frac > 0.0 {
+                let behind = strip[(at + n - 1) % n].into_format::<f32>();
+                strip[at]
+                    .into_format::<f32>()
+                    .mix(behind, frac)
+                    .into_format()
+            } else {
+                strip[at]
+            };
+        }
+
+        if self.smooth {
+            Ok(Duration::milliseconds(FRAME_MILLIS))
         } else {
-            Ok(self.delay - time_left.unwrap())
+            Ok(until_next_step(elapsed, 1.0 / self.speed.abs() as f64))
         }
     }
 
-    fn is_ready(&self, t: Instant) -> Result<bool> {
-        Ok(self.next_update.map(|u| t >= u).unwrap_or(true))
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
     }
 
     fn to_cloned_type(&self) -> EffectType {
         EffectType::Rainbow(self.clone())
     }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        self.started = Some(start);
+        self.render(pixels, t)
+    }
 }
 
 impl Default for Rainbow {
@@ -695,7 +1197,7 @@ impl Default for Rainbow {
             1,
             3,
             1,
-            Duration::milliseconds(100),
+            10.0,
         )
     }
 }
@@ -0,0 +1,424 @@
+use chrono::Duration;
+use palette::LinSrgb;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationMilliSeconds};
+
+use super::{random::Rng, seek, Effect, EffectType, Instant, Simulation, FRAME_MILLIS};
+use crate::error::Result;
+
+/// Longest step we simulate in one go, so a stall doesn't launch everything
+/// off the end of the strip.
+const MAX_STEP_SECS: f32 = 0.1;
+
+/// What happens to particles that reach either end of the strip.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum Boundary {
+    /// Come back in at the other end.
+    Wrap,
+    /// Turn around, keeping `restitution` of their speed.
+    Bounce { restitution: f32 },
+    /// Disappear.
+    #[default]
+    Die,
+}
+
+/// Where an emitter puts new particles.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum EmitterPosition {
+    #[default]
+    Start,
+    End,
+    Center,
+    Random,
+}
+
+/// A source of particles.
+///
+/// Speeds are in pixels per second, positive towards the end of the strip.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Emitter {
+    /// Spawns per second. Fractions carry over between frames.
+    pub rate: f32,
+    /// Particles per spawn, more than one makes a burst like a firework.
+    pub burst: usize,
+    pub position: EmitterPosition,
+    pub velocity: f32,
+    /// New particles get `velocity` plus or minus up to this much.
+    pub velocity_spread: f32,
+    /// Each particle picks one of these.
+    pub colors: Vec<LinSrgb<u8>>,
+    /// How long particles live, fading out as they age. Forever if `None`.
+    #[serde_as(as = "Option<DurationMilliSeconds<i64>>")]
+    pub lifetime: Option<Duration>,
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Self {
+            rate: 1.0,
+            burst: 1,
+            position: EmitterPosition::Start,
+            velocity: 20.0,
+            velocity_spread: 0.0,
+            colors: vec![LinSrgb::new(255, 255, 255)],
+            lifetime: Some(Duration::seconds(3)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Particle {
+    position: f32,
+    velocity: f32,
+    color: LinSrgb<u8>,
+    age: f32,
+    lifetime: Option<f32>,
+}
+
+impl Particle {
+    /// How bright the particle still is, fading linearly over its life.
+    fn life(&self) -> f32 {
+        self.lifetime
+            .map(|l| 1.0 - self.age / l.max(f32::EPSILON))
+            .unwrap_or(1.0)
+    }
+}
+
+/// A general particle system: emitters spawn particles that move with
+/// sub-pixel precision, fall under `acceleration`, leave fading trails and
+/// bounce off, wrap around or fall off the ends of the strip.
+///
+/// `comet`, `fireworks`, `rain` and `meteor` are starting points.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Particles {
+    pub emitters: Vec<Emitter>,
+    /// Pixels per second per second, applied to every particle.
+    pub acceleration: f32,
+    /// Radius in pixels, with an antialiased edge.
+    pub size: f32,
+    /// How long a trail takes to fade away, no trail if zero.
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub trail: Duration,
+    pub boundary: Boundary,
+    /// Emitters stop spawning while there are this many particles.
+    pub max_particles: usize,
+
+    #[serde(skip)]
+    particles: Vec<Particle>,
+    #[serde(skip)]
+    trails: Vec<LinSrgb<f32>>,
+    #[serde(skip)]
+    spawn_debt: Vec<f32>,
+    #[serde(skip)]
+    rng: Rng,
+    #[serde(skip)]
+    last_update: Option<Instant>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl PartialEq for Particles {
+    fn eq(&self, other: &Self) -> bool {
+        // Only the settings, the particles in flight change every frame.
+        self.emitters == other.emitters
+            && self.acceleration == other.acceleration
+            && self.size == other.size
+            && self.trail == other.trail
+            && self.boundary == other.boundary
+            && self.max_particles == other.max_particles
+    }
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self::comet()
+    }
+}
+
+impl Particles {
+    pub fn new(
+        emitters: Vec<Emitter>,
+        acceleration: f32,
+        size: f32,
+        trail: Duration,
+        boundary: Boundary,
+    ) -> Self {
+        Self {
+            emitters,
+            acceleration,
+            size,
+            trail,
+            boundary,
+            max_particles: 200,
+            particles: vec![],
+            trails: vec![],
+            spawn_debt: vec![],
+            rng: Rng::default(),
+            last_update: None,
+            started: None,
+        }
+    }
+
+    /// Names of the presets, for `preset`.
+    pub fn preset_names() -> impl Iterator<Item = &'static str> {
+        ["Comet", "Fireworks", "Rain", "Meteor"].into_iter()
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "Comet" => Some(Self::comet()),
+            "Fireworks" => Some(Self::fireworks()),
+            "Rain" => Some(Self::rain()),
+            "Meteor" => Some(Self::meteor()),
+            _ => None,
+        }
+    }
+
+    pub fn comet() -> Self {
+        Self::new(
+            vec![Emitter {
+                rate: 0.5,
+                velocity: 30.0,
+                colors: vec![LinSrgb::new(0, 128, 255)],
+                lifetime: None,
+                ..Default::default()
+            }],
+            0.0,
+            1.0,
+            Duration::milliseconds(600),
+            Boundary::Die,
+        )
+    }
+
+    pub fn fireworks() -> Self {
+        Self::new(
+            vec![Emitter {
+                rate: 0.8,
+                burst: 16,
+                position: EmitterPosition::Random,
+                velocity: 0.0,
+                velocity_spread: 25.0,
+                colors: vec![
+                    LinSrgb::new(255, 40, 0),
+                    LinSrgb::new(255, 200, 0),
+                    LinSrgb::new(0, 255, 120),
+                    LinSrgb::new(180, 0, 255),
+                ],
+                lifetime: Some(Duration::milliseconds(1200)),
+            }],
+            0.0,
+            0.5,
+            Duration::milliseconds(300),
+            Boundary::Die,
+        )
+    }
+
+    pub fn rain() -> Self {
+        Self::new(
+            vec![Emitter {
+                rate: 6.0,
+                position: EmitterPosition::End,
+                velocity: -15.0,
+                velocity_spread: 5.0,
+                colors: vec![LinSrgb::new(0, 60, 255), LinSrgb::new(80, 120, 255)],
+                lifetime: Some(Duration::seconds(6)),
+                ..Default::default()
+            }],
+            -15.0,
+            0.5,
+            Duration::milliseconds(150),
+            Boundary::Die,
+        )
+    }
+
+    pub fn meteor() -> Self {
+        Self::new(
+            vec![Emitter {
+                rate: 0.3,
+                velocity: 60.0,
+                velocity_spread: 15.0,
+                colors: vec![LinSrgb::new(255, 255, 200), LinSrgb::new(255, 140, 40)],
+                lifetime: Some(Duration::seconds(3)),
+                ..Default::default()
+            }],
+            0.0,
+            2.0,
+            Duration::milliseconds(1500),
+            Boundary::Die,
+        )
+    }
+
+    fn spawn(&mut self, emitter: usize, len: f32) {
+        let emitter = &self.emitters[emitter];
+        let position = match emitter.position {
+            EmitterPosition::Start => 0.0,
+            EmitterPosition::End => len - 1.0,
+            EmitterPosition::Center => len / 2.0,
+            EmitterPosition::Random => self.rng.next_f32() * len,
+        };
+        let lifetime = emitter.lifetime.map(|l| l.num_milliseconds() as f32 / 1000.0);
+        for _ in 0..emitter.burst {
+            if self.particles.len() >= self.max_particles {
+                return;
+            }
+            let color = emitter
+                .colors
+                .get(self.rng.below(emitter.colors.len()))
+                .copied()
+                .unwrap_or(LinSrgb::new(255, 255, 255));
+            self.particles.push(Particle {
+                position,
+                velocity: emitter.velocity + self.rng.spread(emitter.velocity_spread),
+                color,
+                age: 0.0,
+                lifetime,
+            });
+        }
+    }
+
+    fn step(&mut self, dt: f32, len: f32) {
+        self.spawn_debt.resize(self.emitters.len(), 0.0);
+        for i in 0..self.emitters.len() {
+            // Never owe more spawns than there's room for, or a huge rate
+            // would spin here spawning nothing.
+            let free = self.max_particles.saturating_sub(self.particles.len());
+            let room = free.div_ceil(self.emitters[i].burst.max(1)) as f32;
+            self.spawn_debt[i] =
+                (self.spawn_debt[i] + self.emitters[i].rate.max(0.0) * dt).min(room);
+            while self.spawn_debt[i] >= 1.0 {
+                self.spawn_debt[i] -= 1.0;
+                self.spawn(i, len);
+            }
+        }
+
+        let boundary = self.boundary;
+        for particle in self.particles.iter_mut() {
+            particle.velocity += self.acceleration * dt;
+            particle.position += particle.velocity * dt;
+            particle.age += dt;
+            match boundary {
+                Boundary::Wrap => particle.position = particle.position.rem_euclid(len),
+                Boundary::Bounce { restitution } => {
+                    if particle.position < 0.0 {
+                        particle.position = -particle.position;
+                        particle.velocity = particle.velocity.abs() * restitution;
+                    } else if particle.position > len - 1.0 {
+                        particle.position = 2.0 * (len - 1.0) - particle.position;
+                        particle.velocity = -particle.velocity.abs() * restitution;
+                    }
+                }
+                Boundary::Die => {}
+            }
+        }
+        let margin = self.size + 1.0;
+        self.particles.retain(|p| {
+            p.life() > 0.0 && p.position > -margin && p.position < len - 1.0 + margin
+        });
+    }
+
+    fn draw(&mut self, dt: f32) {
+        let trail_secs = self.trail.num_milliseconds() as f32 / 1000.0;
+        // Trails are down to 1% after `trail`.
+        let keep = if trail_secs > 0.0 {
+            0.01_f32.powf(dt / trail_secs)
+        } else {
+            0.0
+        };
+        for pixel in self.trails.iter_mut() {
+            *pixel *= keep;
+        }
+
+        let size = self.size.max(0.5);
+        for particle in self.particles.iter() {
+            let color = particle.color.into_format::<f32>() * particle.life();
+            let first = (particle.position - size).floor().max(0.0) as usize;
+            let last = (particle.position + size).ceil().max(0.0) as usize;
+            for (i, pixel) in self
+                .trails
+                .iter_mut()
+                .enumerate()
+                .take(last + 1)
+                .skip(first)
+            {
+                let distance = (i as f32 - particle.position).abs();
+                let coverage = (size + 0.5 - distance).clamp(0.0, 1.0);
+                let lit = color * coverage;
+                *pixel = LinSrgb::new(
+                    pixel.red.max(lit.red),
+                    pixel.green.max(lit.green),
+                    pixel.blue.max(lit.blue),
+                );
+            }
+        }
+    }
+}
+
+impl Effect for Particles {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        if self.trails.len() != pixels.len() {
+            self.trails = vec![LinSrgb::new(0.0, 0.0, 0.0); pixels.len()];
+        }
+        let dt = self
+            .last_update
+            .map(|u| t.signed_duration_since(u).num_milliseconds() as f32 / 1000.0)
+            .unwrap_or(0.0)
+            .clamp(0.0, MAX_STEP_SECS);
+        self.last_update = Some(t);
+        // Nothing to draw on, and wrapping around no pixels would be NaN.
+        if pixels.is_empty() {
+            return Ok(Duration::milliseconds(FRAME_MILLIS));
+        }
+
+        self.step(dt, pixels.len() as f32);
+        self.draw(dt);
+
+        // Draw over whatever is underneath, so particles work in composites.
+        for (pixel, lit) in pixels.iter_mut().zip(self.trails.iter()) {
+            let lit: LinSrgb<u8> = lit.into_format();
+            *pixel = LinSrgb::new(
+                pixel.red.max(lit.red),
+                pixel.green.max(lit.green),
+                pixel.blue.max(lit.blue),
+            );
+        }
+        Ok(Duration::milliseconds(FRAME_MILLIS))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::Particles(self.clone())
+    }
+
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn render_at(
+        &mut self,
+        pixels: &mut [LinSrgb<u8>],
+        start: Instant,
+        t: Instant,
+    ) -> Result<Duration> {
+        seek(self, pixels, start, t)
+    }
+}
+
+impl Simulation for Particles {
+    fn progress(&self) -> Option<(Instant, Instant)> {
+        self.started.zip(self.last_update)
+    }
+
+    fn restart(&mut self, start: Instant) {
+        self.particles.clear();
+        self.trails.clear();
+        self.spawn_debt.clear();
+        self.rng = Rng::default();
+        self.started = Some(start);
+        self.last_update = None;
+    }
+}
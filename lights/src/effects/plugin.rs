@@ -0,0 +1,235 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use chrono::Duration;
+use once_cell::sync::Lazy;
+use palette::LinSrgb;
+use serde::{Deserialize, Serialize};
+use wasmi::{
+    Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+use super::{Effect, EffectType, Instant};
+use crate::error::{Error, Result};
+
+/// How much fuel a plugin gets for each call into it, roughly one unit per
+/// instruction. Running out traps the plugin instead of stalling the strip.
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// The most memory a plugin can grow to, so one can't take all of the Pi's.
+const MAX_MEMORY: usize = 16 * 1024 * 1024;
+
+/// Compiled WASM plugin modules, by name.
+///
+/// Like the script library, the backend keeps these in its database and
+/// mirrors them in here.
+static PLUGINS: Lazy<RwLock<BTreeMap<String, Arc<Module>>>> = Lazy::new(Default::default);
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+});
+
+fn plugin_error(e: impl ToString) -> Error {
+    Error::Plugin(e.to_string())
+}
+
+/// Compile `bytes` and add it to the available plugins, replacing any plugin
+/// with the same name.
+pub fn set_plugin_module(name: impl Into<String>, bytes: &[u8]) -> Result<()> {
+    let module = Module::new(&ENGINE, bytes).map_err(plugin_error)?;
+    PLUGINS
+        .write()
+        .expect("plugins poisoned")
+        .insert(name.into(), Arc::new(module));
+    Ok(())
+}
+
+/// Compile `bytes` without adding it, to see that it would load.
+pub fn check_plugin_module(bytes: &[u8]) -> Result<()> {
+    Module::new(&ENGINE, bytes).map_err(plugin_error)?;
+    Ok(())
+}
+
+pub fn remove_plugin_module(name: &str) {
+    PLUGINS.write().expect("plugins poisoned").remove(name);
+}
+
+pub fn plugin_names() -> Vec<String> {
+    PLUGINS
+        .read()
+        .expect("plugins poisoned")
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// A running instance of a plugin.
+///
+/// Plugins can't import anything, and talk to us through two exports:
+///
+/// * `init(len: i32) -> i32` sets up for a strip of `len` pixels, and returns
+///   a pointer to `len * 3` bytes of RGB in its memory.
+/// * `render(ptr: i32, len: i32, t: i64) -> i32` draws the frame for `t`
+///   (milliseconds since the epoch) into that buffer, and returns how many
+///   milliseconds until it wants to be called again.
+///
+/// The buffer holds the current pixels when `render` is called, so plugins can
+/// draw on top of them.
+struct PluginInstance {
+    store: Store<StoreLimits>,
+    instance: Instance,
+    memory: Memory,
+    render: TypedFunc<(i32, i32, i64), i32>,
+    buffer: usize,
+    len: usize,
+}
+
+impl PluginInstance {
+    fn new(module: &Module) -> Result<Self> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(&ENGINE, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(plugin_error)?;
+        let linker = Linker::<StoreLimits>::new(&ENGINE);
+        let instance = linker
+            .instantiate(&mut store, module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(plugin_error)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| plugin_error("plugin doesn't export its memory"))?;
+        let render = instance
+            .get_typed_func(&store, "render")
+            .map_err(plugin_error)?;
+        Ok(Self {
+            store,
+            instance,
+            memory,
+            render,
+            buffer: 0,
+            len: 0,
+        })
+    }
+
+    fn init(&mut self, len: usize) -> Result<()> {
+        let init = self
+            .instance
+            .get_typed_func::<i32, i32>(&self.store, "init")
+            .map_err(plugin_error)?;
+        self.store.set_fuel(FUEL_PER_CALL).map_err(plugin_error)?;
+        let ptr = init
+            .call(&mut self.store, len as i32)
+            .map_err(plugin_error)?;
+        self.buffer = ptr as u32 as usize;
+        self.len = len;
+        self.buffer_range()?;
+        Ok(())
+    }
+
+    fn buffer_range(&self) -> Result<std::ops::Range<usize>> {
+        let outside = || plugin_error("pixel buffer is outside of plugin memory");
+        let end = self
+            .len
+            .checked_mul(3)
+            .and_then(|bytes| self.buffer.checked_add(bytes))
+            .ok_or_else(outside)?;
+        if end > self.memory.data(&self.store).len() {
+            return Err(outside());
+        }
+        Ok(self.buffer..end)
+    }
+
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        if pixels.len() != self.len {
+            self.init(pixels.len())?;
+        }
+
+        let range = self.buffer_range()?;
+        let buffer = &mut self.memory.data_mut(&mut self.store)[range.clone()];
+        for (chunk, pixel) in buffer.chunks_exact_mut(3).zip(pixels.iter()) {
+            let (r, g, b) = pixel.into_components();
+            chunk.copy_from_slice(&[r, g, b]);
+        }
+
+        self.store.set_fuel(FUEL_PER_CALL).map_err(plugin_error)?;
+        let delay = self
+            .render
+            .call(
+                &mut self.store,
+                (self.buffer as i32, self.len as i32, t.timestamp_millis()),
+            )
+            .map_err(plugin_error)?;
+
+        // The plugin could have grown its memory, so look it up again.
+        let range = self.buffer_range()?;
+        let buffer = &self.memory.data(&self.store)[range];
+        for (chunk, pixel) in buffer.chunks_exact(3).zip(pixels.iter_mut()) {
+            *pixel = LinSrgb::new(chunk[0], chunk[1], chunk[2]);
+        }
+        Ok(Duration::milliseconds(delay.max(1) as i64))
+    }
+}
+
+/// An effect implemented by a WebAssembly module from the plugin store.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct WasmPlugin {
+    name: String,
+
+    #[serde(skip)]
+    instance: Option<Arc<Mutex<PluginInstance>>>,
+}
+
+impl WasmPlugin {
+    pub fn load(name: String) -> Result<Self> {
+        if name.is_empty() {
+            return Err(plugin_error("No plugin chosen"));
+        }
+        let module = PLUGINS
+            .read()
+            .expect("plugins poisoned")
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| plugin_error(format!("No plugin named `{name}`")))?;
+        let instance = PluginInstance::new(&module)?;
+        Ok(Self {
+            name,
+            instance: Some(Arc::new(Mutex::new(instance))),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for WasmPlugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPlugin")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Effect for WasmPlugin {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        let instance = self
+            .instance
+            .as_ref()
+            .ok_or_else(|| plugin_error("plugin isn't loaded"))?;
+        instance.lock().expect("plugin poisoned").render(pixels, t)
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::WasmPlugin(self.name.clone())
+    }
+}
@@ -0,0 +1,42 @@
+/// A small xorshift generator for effects that want noise.
+///
+/// Every effect starts from the same seed, so the same effect rendered at the
+/// same times always comes out the same, in the preview and on the strip.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Rng(u64);
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Uniform in `0.0..1.0`.
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1_u64 << 24) as f32
+    }
+
+    /// Uniform in `-spread..spread`.
+    pub(crate) fn spread(&mut self, spread: f32) -> f32 {
+        (self.next_f32() * 2.0 - 1.0) * spread
+    }
+
+    /// Uniform in `0..n`, or 0 when `n` is 0.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        if n == 0 {
+            0
+        } else {
+            (self.next_u64() % n as u64) as usize
+        }
+    }
+}
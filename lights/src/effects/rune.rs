@@ -1,17 +1,21 @@
+mod library;
+mod params;
+mod report;
 mod types;
 
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use chrono::TimeZone;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
 use rune::{
     alloc::{prelude::TryClone, Box},
-    compile::Component,
-    runtime::{debug::DebugArgs, RuntimeContext},
+    compile::{Component, ComponentRef},
+    modules::capture_io::{self, CaptureIo},
+    runtime::{budget, debug::DebugArgs, RuntimeContext},
     Any, Context, Source, Unit, Value, Vm,
 };
 use rune::{termcolor::StandardStream, Diagnostics, Options, Sources};
@@ -22,11 +26,24 @@ use crate::error::{Error, Result, RuneError};
 
 use super::{Effect, EffectType, Instant};
 
+pub use self::library::{
+    library_module, library_names, remove_library_module, set_library_module,
+};
+pub use self::params::*;
+pub use self::report::*;
+use self::library::LibraryLoader;
+
 static REQUIRED_FNS: Lazy<HashMap<Component, DebugArgs>> = Lazy::new(|| {
     [
         (
             "init",
-            DebugArgs::Named(Box::from_std(vec![].into_boxed_slice()).unwrap()),
+            DebugArgs::Named(
+                Box::from_std(
+                    vec![Box::from_std("params".to_string().into_boxed_str()).unwrap()]
+                        .into_boxed_slice(),
+                )
+                .unwrap(),
+            ),
         ),
         (
             "render",
@@ -36,6 +53,7 @@ static REQUIRED_FNS: Lazy<HashMap<Component, DebugArgs>> = Lazy::new(|| {
                         Box::from_std("state".to_string().into_boxed_str()).unwrap(),
                         Box::from_std("pixels".to_string().into_boxed_str()).unwrap(),
                         Box::from_std("t".to_string().into_boxed_str()).unwrap(),
+                        Box::from_std("params".to_string().into_boxed_str()).unwrap(),
                     ]
                     .into_boxed_slice(),
                 )
@@ -63,8 +81,72 @@ static REQUIRED_FNS: Lazy<HashMap<Component, DebugArgs>> = Lazy::new(|| {
     .collect()
 });
 
-// TODO: We want a single Context/RuntimeContext that is cloned between all scripts
-// TODO: We need a budget to run so we don't overflow the time waiting for the next tick
+/// The number of arguments each required function can take, and how it's
+/// written now. Scripts from before parameters have `init()` and
+/// `render(state, pixels, t)`, and are still called that way.
+const ACCEPTED_ARITIES: &[(&str, &[usize], &str)] = &[
+    ("init", &[0, 1], "init(params)"),
+    ("render", &[3, 4], "render(state, pixels, t, params)"),
+    ("is_ready", &[2], "is_ready(state, t)"),
+];
+
+/// How many instructions a script gets for each call into it. A script that
+/// runs out is stopped with an error rather than holding up the render loop,
+/// or locking up the tab in the browser preview.
+const INSTRUCTION_BUDGET: usize = 1_000_000;
+
+/// Once the cache holds this many compiled units we throw them all away, so a
+/// long editing session doesn't keep every intermediate script around.
+const MAX_CACHED_UNITS: usize = 32;
+
+/// The parts of the Rune runtime that don't depend on any particular script.
+///
+/// Building the `Context` installs every default module, which takes a
+/// noticeable amount of time on a Pi Zero, so we only ever do it once.
+struct SharedRuntime {
+    context: Context,
+    runtime: Arc<RuntimeContext>,
+}
+
+static RUNTIME: OnceCell<SharedRuntime> = OnceCell::new();
+
+/// Everything scripts print ends up in here until someone drains it.
+static SCRIPT_OUTPUT: Lazy<CaptureIo> = Lazy::new(CaptureIo::new);
+
+/// Compiled units keyed by their whole source, so reactivating a known script
+/// doesn't have to compile it again. Keying by a hash alone would run the
+/// wrong unit for two sources that collide.
+static UNITS: Lazy<Mutex<HashMap<String, Arc<Unit>>>> = Lazy::new(Default::default);
+
+fn shared_runtime() -> Result<&'static SharedRuntime, RuneError> {
+    RUNTIME.get_or_try_init(|| {
+        // TODO: Figure out which functions and stuff we want to provide to Rune
+        log::info!("Creating context");
+        // The default modules minus stdio, which we replace with a capture so
+        // the preview can show what scripts print.
+        let mut context = Context::with_config(false)?;
+        context.install(&capture_io::module(&SCRIPT_OUTPUT)?)?;
+        context.install(&types::module()?)?;
+        context.install(&super::audio::rune_module()?)?;
+        context.install(&crate::signals::rune_module()?)?;
+        log::info!("Created context: {context:?}");
+        let runtime = Arc::new(context.runtime()?);
+        Ok(SharedRuntime { context, runtime })
+    })
+}
+
+/// Take everything scripts have printed since the last call.
+pub fn drain_script_output() -> String {
+    String::from_utf8_lossy(&SCRIPT_OUTPUT.drain()).into_owned()
+}
+
+fn budgeted<T>(f: impl FnOnce() -> T) -> T {
+    budget::with(INSTRUCTION_BUDGET, f).call()
+}
+
+fn clear_unit_cache() {
+    UNITS.lock().expect("unit cache poisoned").clear();
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RuneScript {
@@ -77,59 +159,170 @@ pub struct RuneScript {
     #[serde(skip)]
     private_data: Value,
 
+    #[serde(skip)]
+    schema: Vec<ParamSpec>,
+
+    /// `params` as the object handed to the script.
+    #[serde(skip)]
+    params_value: Value,
+
+    /// False for scripts written before `render` took `params`.
+    #[serde(skip)]
+    render_takes_params: bool,
+
     // TODO: Make this a new type that we control, for loading from the database
     pub(crate) sourcecode: SourceCode,
+    pub(crate) params: ParamValues,
 }
 
 impl RuneScript {
     pub fn from_source(sourcecode: SourceCode) -> Result<Self, RuneError> {
-        // TODO: Figure out which functions and stuff we want to provide to Rune
-        log::info!("Creating context");
-        let mut context = Context::with_default_modules()?;
-        log::info!("Created context: {context:?}");
-        context.install(&types::module()?)?;
+        Self::with_params(sourcecode, ParamValues::default())
+    }
+
+    pub fn with_params(sourcecode: SourceCode, params: ParamValues) -> Result<Self, RuneError> {
+        let runtime = Arc::clone(&shared_runtime()?.runtime);
+        let unit = Self::compile(&sourcecode)?;
+
+        let mut vm = Vm::new(Arc::clone(&runtime), Arc::clone(&unit));
+        let schema = if Self::exports(&unit, "params") {
+            schema_from_value(budgeted(|| vm.call(&["params"], ()))?)?
+        } else {
+            vec![]
+        };
+        log::info!("Script parameters: {:?}", schema);
+        let params_value = params.to_value(&schema)?;
+
+        let private_data = if Self::arity(&unit, "init") == Some(0) {
+            budgeted(|| vm.call(&["init"], ()))?
+        } else {
+            budgeted(|| vm.call(&["init"], (&params_value,)))?
+        };
+        let render_takes_params = Self::arity(&unit, "render") != Some(3);
+        log::info!("Loaded private_data: {:?}", private_data);
+        // log::info!("private_data info: {:#?}", private_data.type_info());
+        if let Value::Object(ref o) = private_data {
+            log::info!("private_data info: {:#?}", o);
+        }
+        let private_data = private_data;
+
+        Ok(Self {
+            unit,
+            runtime,
+            private_data,
+            schema,
+            params_value,
+            render_takes_params,
+
+            sourcecode,
+            params,
+        })
+    }
+
+    /// Compile `sourcecode` and run its `init`, reporting what went wrong in
+    /// a form an editor can show next to the code.
+    pub fn check(sourcecode: SourceCode, params: ParamValues) -> CompileReport {
+        match Self::with_params(sourcecode, params) {
+            Ok(script) => CompileReport {
+                schema: script.schema,
+                ..Default::default()
+            },
+            Err(RuneError::Diagnostics(diagnostics)) => CompileReport {
+                diagnostics,
+                ..Default::default()
+            },
+            Err(e) => CompileReport {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// The parameters this script declared with its `params()` function.
+    pub fn schema(&self) -> &[ParamSpec] {
+        &self.schema[..]
+    }
+
+    pub fn params(&self) -> &ParamValues {
+        &self.params
+    }
+
+    /// Change the value of a parameter. This takes effect on the next `render`,
+    /// the script's state isn't rebuilt.
+    pub fn set_param(&mut self, name: &str, value: ParamValue) -> Result<(), RuneError> {
+        self.params.set(name, value);
+        self.params_value = self.params.to_value(&self.schema)?;
+        Ok(())
+    }
+
+    fn exports(unit: &Unit, name: &str) -> bool {
+        unit.debug_info()
+            .map(|debug_info| {
+                debug_info
+                    .functions
+                    .values()
+                    .any(|di| matches!(di.path.last(), Some(ComponentRef::Str(n)) if n == name))
+            })
+            .unwrap_or(false)
+    }
+
+    /// How many arguments the function `name` takes, if the script has one.
+    fn arity(unit: &Unit, name: &str) -> Option<usize> {
+        unit.debug_info()?
+            .functions
+            .values()
+            .find(|di| matches!(di.path.last(), Some(ComponentRef::Str(n)) if n == name))
+            .map(|di| match &di.args {
+                DebugArgs::EmptyArgs => 0,
+                DebugArgs::TupleArgs(n) => *n,
+                DebugArgs::Named(names) => names.len(),
+            })
+    }
+
+    /// Compile `sourcecode` against the shared context, reusing the unit from
+    /// a previous compilation of the same source if we have one.
+    fn compile(sourcecode: &SourceCode) -> Result<Arc<Unit>, RuneError> {
+        let source = sourcecode.source()?;
+        if let Some(unit) = UNITS.lock().expect("unit cache poisoned").get(&source) {
+            log::debug!("Reusing compiled unit");
+            return Ok(Arc::clone(unit));
+        }
 
+        let shared = shared_runtime()?;
         let options = Options::default();
         let mut sources = Sources::new();
-        sources.insert(Source::new("main", &sourcecode.to_string())?)?;
+        sources.insert(Source::new("main", &source)?)?;
 
         let mut diagnostics = Diagnostics::new();
+        let mut loader = LibraryLoader;
         let result = rune::prepare(&mut sources)
-            .with_context(&context)
+            .with_context(&shared.context)
             .with_options(&options)
             .with_diagnostics(&mut diagnostics)
+            .with_source_loader(&mut loader)
             .build();
 
         if !diagnostics.is_empty() {
             let mut writer = StandardStream::stderr(rune::termcolor::ColorChoice::Always);
             diagnostics.emit(&mut writer, &sources)?;
-            // Ideally we won't get here, and I can just not make this more awesome.
-            return Err(RuneError::Compilation("Error compiling".into()).into());
+        }
+        if diagnostics.has_error() {
+            return Err(RuneError::Diagnostics(ScriptDiagnostic::from_diagnostics(
+                &diagnostics,
+                &sources,
+            )));
         }
 
         let unit = result?;
         Self::validate_source(&unit)?;
-
         let unit = Arc::new(unit);
-        let context = Arc::new(context);
-        let runtime = Arc::new(context.runtime()?);
 
-        let mut vm = Vm::new(Arc::clone(&runtime), Arc::clone(&unit));
-        let private_data = vm.call(&["init"], ())?;
-        log::info!("Loaded private_data: {:?}", private_data);
-        // log::info!("private_data info: {:#?}", private_data.type_info());
-        if let Value::Object(ref o) = private_data {
-            log::info!("private_data info: {:#?}", o);
+        let mut units = UNITS.lock().expect("unit cache poisoned");
+        if units.len() >= MAX_CACHED_UNITS {
+            units.clear();
         }
-        let private_data = private_data;
-
-        Ok(Self {
-            unit,
-            runtime,
-            private_data,
-
-            sourcecode,
-        })
+        units.insert(source, Arc::clone(&unit));
+        Ok(unit)
     }
 
     fn validate_source(unit: &Unit) -> Result<(), RuneError> {
@@ -165,17 +358,17 @@ impl RuneScript {
                 missing_fns
             )));
         }
-        let broken_fns = debug_info
-            .functions
-            .values()
-            .filter_map(|di| {
-                REQUIRED_FNS
-                    .get(di.path.as_vec().ok()?.last().unwrap())
-                    .map(|v| (v, di))
-            })
-            .filter(|(v, di)| format!("{:?}", v) != format!("{:?}", di.args))
-            .collect::<Vec<_>>();
-        log::info!("broken: {:#?}", broken_fns);
+        for (name, arities, signature) in ACCEPTED_ARITIES {
+            match Self::arity(unit, name) {
+                Some(arity) if !arities.contains(&arity) => {
+                    return Err(RuneError::Compilation(format!(
+                        "`{}` takes {} arguments, it should be `fn {}`",
+                        name, arity, signature
+                    )));
+                }
+                _ => {}
+            }
+        }
         Ok(())
     }
 }
@@ -189,7 +382,7 @@ impl Default for RuneScript {
 impl PartialEq for RuneScript {
     fn eq(&self, other: &Self) -> bool {
         // We really only care about sourcecode, the other values can change between runs.
-        self.sourcecode == other.sourcecode
+        self.sourcecode == other.sourcecode && self.params == other.params
     }
 }
 
@@ -203,8 +396,13 @@ impl Effect for RuneScript {
         let state = &self.private_data;
         let mut scrixels: types::Scrixels = pixels.into();
 
-        let dur = vm
-            .call(&["render"], (state, &mut scrixels, t.timestamp_millis()))
+        let t = t.timestamp_millis();
+        let dur = if self.render_takes_params {
+            budgeted(|| vm.call(&["render"], (state, &mut scrixels, t, &self.params_value)))
+        } else {
+            budgeted(|| vm.call(&["render"], (state, &mut scrixels, t)))
+        };
+        let dur = dur
             .map_err(RuneError::from)
             .map_err(Error::from)?
             .into_integer()
@@ -220,8 +418,7 @@ impl Effect for RuneScript {
     fn is_ready(&self, t: Instant) -> Result<bool> {
         let mut vm = Vm::new(Arc::clone(&self.runtime), Arc::clone(&self.unit));
         let state = &self.private_data;
-        let ready = vm
-            .call(&["is_ready"], (state, t.timestamp_millis()))
+        let ready = budgeted(|| vm.call(&["is_ready"], (state, t.timestamp_millis())))
             .map_err(RuneError::from)
             .map_err(Error::from)?
             .as_bool()
@@ -232,40 +429,169 @@ impl Effect for RuneScript {
     }
 
     fn to_cloned_type(&self) -> EffectType {
-        EffectType::RuneScript(self.sourcecode.clone())
+        EffectType::RuneScript(self.sourcecode.clone(), self.params.clone())
     }
 }
 
+/// Where the main module of a script comes from.
+///
+/// Either kind of script can pull in modules from the script library with
+/// `mod name;`, and then `use name::thing;` as usual.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum SourceCode {
     Source(String),
+    /// A script stored in the library under this name.
+    Library(String),
+}
+
+impl SourceCode {
+    /// The text of the main module, looking it up in the library if needed.
+    pub fn source(&self) -> Result<String, RuneError> {
+        match self {
+            SourceCode::Source(code) => Ok(code.clone()),
+            SourceCode::Library(name) => library::resolve(name),
+        }
+    }
 }
 
 impl Display for SourceCode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             SourceCode::Source(code) => write!(f, "{}", code),
+            SourceCode::Library(name) => write!(f, "library:{}", name),
         }
     }
 }
 
+impl SourceCode {
+    /// Starting points for new scripts, by name.
+    pub fn templates() -> impl Iterator<Item = (&'static str, SourceCode)> {
+        [
+            ("Blank", SourceCode::default()),
+            (
+                "Solid color",
+                SourceCode::Source(
+                    r#"
+pub fn params() {
+    [
+        #{ name: "color", type: "color", default: (255, 0, 0) },
+    ]
+}
+
+pub fn init(params) {
+    #{}
+}
+
+pub fn is_ready(state, t) {
+    true
+}
+
+pub fn render(state, pixels, t, params) {
+    for i in 0..pixels.len() {
+        pixels.set(i, params.color);
+    }
+    100
+}
+"#
+                    .to_owned(),
+                ),
+            ),
+            (
+                "Moving dot",
+                SourceCode::Source(
+                    r#"
+pub fn params() {
+    [
+        #{ name: "color", type: "color", default: (0, 0, 255) },
+        #{ name: "delay", type: "int", min: 10, max: 1000, default: 50 },
+        #{ name: "bounce", type: "bool", default: false },
+    ]
+}
+
+pub fn init(params) {
+    #{ position: 0, direction: 1 }
+}
+
+pub fn is_ready(state, t) {
+    true
+}
+
+pub fn render(state, pixels, t, params) {
+    let next = state.position + state.direction;
+    if next < 0 || next >= pixels.len() {
+        if params.bounce {
+            state.direction = -state.direction;
+            next = state.position + state.direction;
+        } else {
+            next = (next + pixels.len()) % pixels.len();
+        }
+    }
+    state.position = next;
+    pixels.set(state.position, params.color);
+    params.delay
+}
+"#
+                    .to_owned(),
+                ),
+            ),
+            (
+                "Audio level",
+                SourceCode::Source(
+                    r#"
+pub fn params() {
+    [
+        #{ name: "color", type: "color", default: (0, 255, 0) },
+    ]
+}
+
+pub fn init(params) {
+    #{}
+}
+
+pub fn is_ready(state, t) {
+    true
+}
+
+pub fn render(state, pixels, t, params) {
+    // audio::bands() and audio::last_beat() are there too.
+    let lit = (audio::level() * pixels.len().to_float()).to_integer();
+    for i in 0..lit {
+        pixels.set(i, params.color);
+    }
+    20
+}
+"#
+                    .to_owned(),
+                ),
+            ),
+        ]
+        .into_iter()
+    }
+}
+
 impl Default for SourceCode {
     fn default() -> Self {
         Self::Source(
             r#"
             struct Nothing;
 
-            pub fn render(state, pixels, t) {
+            pub fn params() {
+                [
+                    #{ name: "delay", type: "int", min: 10, max: 5000, default: 1000 },
+                ]
+            }
+
+            pub fn render(state, pixels, t, params) {
                 println(`Running ${pixels.len()}`);
-                //  Run again in 1 second
-                1000
+                //  Run again after `delay` milliseconds
+                params.delay
             }
 
             pub fn is_ready(state, t) {
                 true
             }
 
-            pub fn init() {
+            pub fn init(params) {
                 Nothing
             }
             "#
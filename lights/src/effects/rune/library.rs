@@ -0,0 +1,79 @@
+use std::{collections::BTreeMap, path::Path, sync::RwLock};
+
+use once_cell::sync::Lazy;
+use rune::{
+    ast::Spanned,
+    compile::{self, ComponentRef, Item, SourceLoader},
+    Source,
+};
+
+use crate::error::RuneError;
+
+/// Named Rune modules shared between scripts.
+///
+/// The backend keeps the real copy in its database and mirrors it in here, so
+/// that compiling a script never has to reach back out of this crate.
+static LIBRARY: Lazy<RwLock<BTreeMap<String, String>>> = Lazy::new(Default::default);
+
+pub fn library_module(name: &str) -> Option<String> {
+    LIBRARY
+        .read()
+        .expect("script library poisoned")
+        .get(name)
+        .cloned()
+}
+
+pub fn library_names() -> Vec<String> {
+    LIBRARY
+        .read()
+        .expect("script library poisoned")
+        .keys()
+        .cloned()
+        .collect()
+}
+
+pub fn set_library_module(name: impl Into<String>, source: impl Into<String>) {
+    LIBRARY
+        .write()
+        .expect("script library poisoned")
+        .insert(name.into(), source.into());
+    // Any cached unit could have pulled in the old version of this module.
+    super::clear_unit_cache();
+}
+
+pub fn remove_library_module(name: &str) {
+    LIBRARY
+        .write()
+        .expect("script library poisoned")
+        .remove(name);
+    super::clear_unit_cache();
+}
+
+pub(crate) fn resolve(name: &str) -> Result<String, RuneError> {
+    library_module(name).ok_or_else(|| RuneError::MissingModule(name.to_owned()))
+}
+
+/// Resolves `mod helpers;` in a script to the library module named `helpers`
+/// instead of a file next to it. Nested modules use their full path, so
+/// `mod colors;` inside `helpers` loads `helpers::colors`.
+pub(crate) struct LibraryLoader;
+
+impl SourceLoader for LibraryLoader {
+    fn load(&mut self, _root: &Path, item: &Item, span: &dyn Spanned) -> compile::Result<Source> {
+        let name = item
+            .iter()
+            .filter_map(|c| match c {
+                ComponentRef::Str(s) => Some(s),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("::");
+        let source = library_module(&name).ok_or_else(|| {
+            compile::Error::msg(
+                span,
+                format!("No module named `{name}` in the script library"),
+            )
+        })?;
+        Ok(Source::new(&name, source)?)
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+
+use palette::LinSrgb;
+use rune::{FromValue, ToValue, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::error::RuneError;
+
+/// What kind of control a script parameter wants, along with its limits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamKind {
+    Color,
+    Int { min: i64, max: i64 },
+    Float { min: f64, max: f64 },
+    Bool,
+    Enum(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamValue {
+    Color(LinSrgb<u8>),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Enum(String),
+}
+
+impl ParamValue {
+    fn to_value(&self) -> Result<Value, RuneError> {
+        let value = match self {
+            ParamValue::Color(c) => c.into_components().to_value(),
+            ParamValue::Int(i) => i.to_value(),
+            ParamValue::Float(f) => f.to_value(),
+            ParamValue::Bool(b) => b.to_value(),
+            ParamValue::Enum(e) => e.clone().to_value(),
+        };
+        Ok(value.into_result()?)
+    }
+}
+
+/// A single tunable exported by a script's `params()` function.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamSpec {
+    pub name: String,
+    pub kind: ParamKind,
+    pub default: ParamValue,
+}
+
+impl ParamSpec {
+    /// Does `value` fit in this parameter, ignoring limits?
+    pub fn accepts(&self, value: &ParamValue) -> bool {
+        match (&self.kind, value) {
+            (ParamKind::Color, ParamValue::Color(_))
+            | (ParamKind::Int { .. }, ParamValue::Int(_))
+            | (ParamKind::Bool, ParamValue::Bool(_)) => true,
+            (ParamKind::Float { .. }, ParamValue::Float(f)) => !f.is_nan(),
+            (ParamKind::Enum(options), ParamValue::Enum(e)) => options.contains(e),
+            _ => false,
+        }
+    }
+
+    /// `value` moved inside this parameter's limits.
+    pub fn clamp(&self, value: &ParamValue) -> ParamValue {
+        // Not `clamp`, which panics on limits that were never checked, like
+        // ones from a deserialized spec.
+        match (&self.kind, value) {
+            (ParamKind::Int { min, max }, ParamValue::Int(i)) => {
+                ParamValue::Int((*i).max(*min).min(*max))
+            }
+            (ParamKind::Float { min, max }, ParamValue::Float(f)) => {
+                ParamValue::Float(f.max(*min).min(*max))
+            }
+            _ => value.clone(),
+        }
+    }
+
+    /// Parse the object a script returns for one parameter, e.g.
+    /// `#{ name: "speed", type: "float", min: 0.0, max: 10.0, default: 1.0 }`.
+    fn from_object(spec: HashMap<String, Value>) -> Result<Self, RuneError> {
+        let name: String = required(&spec, "name")?;
+        let ty: String = required(&spec, "type")?;
+        let (kind, default) = match ty.as_str() {
+            "color" => {
+                let (r, g, b) = optional(&spec, "default")?.unwrap_or((255, 255, 255));
+                (ParamKind::Color, ParamValue::Color(LinSrgb::new(r, g, b)))
+            }
+            "int" => {
+                let min = optional(&spec, "min")?.unwrap_or(0);
+                let max = optional(&spec, "max")?.unwrap_or(100);
+                let default = optional(&spec, "default")?.unwrap_or(min);
+                if min > max {
+                    return Err(RuneError::Params(format!("`{name}` has min above max")));
+                }
+                if !(min..=max).contains(&default) {
+                    return Err(RuneError::Params(format!(
+                        "`{name}` has a default outside {min}..={max}"
+                    )));
+                }
+                (ParamKind::Int { min, max }, ParamValue::Int(default))
+            }
+            "float" => {
+                let min = optional(&spec, "min")?.unwrap_or(0.0);
+                let max = optional(&spec, "max")?.unwrap_or(1.0);
+                let default = optional(&spec, "default")?.unwrap_or(min);
+                if min > max {
+                    return Err(RuneError::Params(format!("`{name}` has min above max")));
+                }
+                if !(min..=max).contains(&default) {
+                    return Err(RuneError::Params(format!(
+                        "`{name}` has a default outside {min}..={max}"
+                    )));
+                }
+                (ParamKind::Float { min, max }, ParamValue::Float(default))
+            }
+            "bool" => {
+                let default = optional(&spec, "default")?.unwrap_or(false);
+                (ParamKind::Bool, ParamValue::Bool(default))
+            }
+            "enum" => {
+                let options: Vec<String> = required(&spec, "options")?;
+                let default = match optional(&spec, "default")? {
+                    Some(d) => d,
+                    None => options
+                        .first()
+                        .cloned()
+                        .ok_or_else(|| RuneError::Params(format!("`{name}` has no options")))?,
+                };
+                (ParamKind::Enum(options), ParamValue::Enum(default))
+            }
+            other => {
+                return Err(RuneError::Params(format!(
+                    "`{name}` has unknown type `{other}`"
+                )))
+            }
+        };
+
+        let spec = Self {
+            name,
+            kind,
+            default,
+        };
+        if !spec.accepts(&spec.default) {
+            return Err(RuneError::Params(format!(
+                "`{}` has a default that isn't one of its options",
+                spec.name
+            )));
+        }
+        Ok(spec)
+    }
+}
+
+fn optional<T: FromValue>(
+    spec: &HashMap<String, Value>,
+    field: &str,
+) -> Result<Option<T>, RuneError> {
+    spec.get(field)
+        .cloned()
+        .map(|v| T::from_value(v).into_result())
+        .transpose()
+        .map_err(|e| RuneError::Params(format!("bad `{field}`: {e}")))
+}
+
+fn required<T: FromValue>(spec: &HashMap<String, Value>, field: &str) -> Result<T, RuneError> {
+    optional(spec, field)?.ok_or_else(|| RuneError::Params(format!("missing `{field}`")))
+}
+
+/// Turn the value returned by a script's `params()` function into a schema.
+pub(crate) fn schema_from_value(value: Value) -> Result<Vec<ParamSpec>, RuneError> {
+    let specs: Vec<HashMap<String, Value>> = FromValue::from_value(value).into_result()?;
+    specs.into_iter().map(ParamSpec::from_object).collect()
+}
+
+/// The values a user picked for a script's parameters, by name.
+///
+/// This is stored alongside the `SourceCode`, so it may hold values for
+/// parameters the script no longer declares, or be missing newer ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParamValues(BTreeMap<String, ParamValue>);
+
+impl ParamValues {
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.0.get(name)
+    }
+
+    pub fn set(&mut self, name: impl Into<String>, value: ParamValue) {
+        self.0.insert(name.into(), value);
+    }
+
+    /// The value the script will see for `spec`, falling back to its default
+    /// when nothing usable was stored, and kept within its limits.
+    pub fn value_for(&self, spec: &ParamSpec) -> ParamValue {
+        let value = self
+            .get(&spec.name)
+            .filter(|v| spec.accepts(v))
+            .unwrap_or(&spec.default);
+        spec.clamp(value)
+    }
+
+    /// Build the object that gets passed to the script's `init` and `render`.
+    pub(crate) fn to_value(&self, schema: &[ParamSpec]) -> Result<Value, RuneError> {
+        let object = schema
+            .iter()
+            .map(|spec| Ok((spec.name.clone(), self.value_for(spec).to_value()?)))
+            .collect::<Result<HashMap<String, Value>, RuneError>>()?;
+        Ok(object.to_value().into_result()?)
+    }
+}
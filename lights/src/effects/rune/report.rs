@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+use rune::{
+    ast::Spanned,
+    diagnostics::{Diagnostic, FatalDiagnosticKind},
+    Diagnostics, Sources,
+};
+use serde::{Deserialize, Serialize};
+
+use super::ParamSpec;
+
+/// One compile error, placed in the script so an editor can mark it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptDiagnostic {
+    /// The source the error is in, `main` for the script itself or the name
+    /// of a library module it uses.
+    #[serde(default)]
+    pub source: String,
+    /// 1-based, or 0 if Rune didn't tell us where the error was.
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ScriptDiagnostic {
+    /// The errors in `diagnostics`, placed by looking their spans up in the
+    /// `sources` they were compiled from.
+    pub(crate) fn from_diagnostics(diagnostics: &Diagnostics, sources: &Sources) -> Vec<Self> {
+        diagnostics
+            .diagnostics()
+            .iter()
+            .filter_map(|diagnostic| match diagnostic {
+                Diagnostic::Fatal(fatal) => Some(fatal),
+                _ => None,
+            })
+            .map(|fatal| {
+                let source = sources.get(fatal.source_id());
+                let name = source.map(|s| s.name().to_owned()).unwrap_or_default();
+                match (fatal.kind(), source) {
+                    (FatalDiagnosticKind::CompileError(error), Some(source)) => {
+                        let start = error.span().start.into_usize();
+                        let (line, column) = source.pos_to_utf8_linecol(start);
+                        Self {
+                            source: name,
+                            line: line + 1,
+                            column: column + 1,
+                            message: error.to_string(),
+                        }
+                    }
+                    _ => Self {
+                        source: name,
+                        line: 0,
+                        column: 0,
+                        message: fatal.to_string(),
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+impl Display for ScriptDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.source, self.line, self.column, self.message
+        )
+    }
+}
+
+/// What the editor gets back when it asks for a script to be compiled.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CompileReport {
+    pub schema: Vec<ParamSpec>,
+    pub diagnostics: Vec<ScriptDiagnostic>,
+    /// Set for failures that aren't tied to a place in the source, like a
+    /// missing `render` function or `init` blowing up.
+    pub error: Option<String>,
+}
+
+impl CompileReport {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty() && self.error.is_none()
+    }
+}
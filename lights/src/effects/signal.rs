@@ -0,0 +1,54 @@
+use chrono::Duration;
+use palette::LinSrgb;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DurationMilliSeconds};
+
+use super::{Effect, EffectType, Instant};
+use crate::{error::Result, signals};
+
+/// Lights the whole strip whenever `signal` is set to something truthy, and
+/// fades it out over `decay`. A doorbell, or `audio.beat`.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SignalFlash {
+    pub signal: String,
+    pub color: LinSrgb<u8>,
+
+    #[serde_as(as = "DurationMilliSeconds<i64>")]
+    pub decay: Duration,
+}
+
+impl Default for SignalFlash {
+    fn default() -> Self {
+        Self {
+            signal: "doorbell".to_owned(),
+            color: LinSrgb::new(255, 255, 255),
+            decay: Duration::milliseconds(1000),
+        }
+    }
+}
+
+impl Effect for SignalFlash {
+    fn render(&mut self, pixels: &mut [LinSrgb<u8>], t: Instant) -> Result<Duration> {
+        let decay = self.decay.num_milliseconds().max(1) as f64;
+        let brightness = signals::signal(&self.signal)
+            .filter(|s| s.value.as_bool())
+            .map(|s| 1.0 - t.signed_duration_since(s.updated).num_milliseconds() as f64 / decay)
+            .unwrap_or(0.0);
+        if brightness > 0.0 && brightness <= 1.0 {
+            let color = signals::Signal::Float(brightness).tint(self.color);
+            for pixel in pixels {
+                *pixel = color;
+            }
+        }
+        Ok(Duration::milliseconds(20))
+    }
+
+    fn is_ready(&self, _t: Instant) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn to_cloned_type(&self) -> EffectType {
+        EffectType::SignalFlash(self.clone())
+    }
+}
@@ -1,3 +1,5 @@
+use itertools::Itertools;
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug, thiserror::Error)]
@@ -19,8 +21,20 @@ pub enum Error {
     #[error(transparent)]
     RuneError(#[from] RuneError),
 
+    #[error("Plugin Error: {0}")]
+    Plugin(String),
+
     #[error("Scripts not allowed in composite effects")]
     CompositeScriptError,
+
+    #[error("Effect can't be drawn at an arbitrary time")]
+    NotSeekable,
+
+    #[error("Couldn't migrate stored details: {0}")]
+    Migration(String),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -28,6 +42,15 @@ pub enum RuneError {
     #[error("Compilation Error: {0}")]
     Compilation(String),
 
+    #[error("Compilation Error: {}", .0.iter().join("; "))]
+    Diagnostics(Vec<crate::effects::ScriptDiagnostic>),
+
+    #[error("Bad parameter declaration: {0}")]
+    Params(String),
+
+    #[error("No module named `{0}` in the script library")]
+    MissingModule(String),
+
     #[error("No debug information")]
     NoDebugInfo,
 
@@ -1,6 +1,10 @@
+pub mod bundle;
 pub mod details;
 pub mod effects;
 pub mod error;
+pub mod migrations;
+pub mod palettes;
+pub mod signals;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,176 @@
+//! Stored `Details` carry the version of the schema they were written with,
+//! so changes to effects don't lose saved presets.
+//!
+//! Anything older than `VERSION` goes through the `MIGRATIONS` after it, each
+//! taking the details as a JSON value from one version to the next. The
+//! details from before there were versions are version 0.
+//!
+//! When a change to an effect would stop old details from deserializing, add
+//! a migration to the end of `MIGRATIONS`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    details::Details,
+    effects::{Ball, Glow, Rainbow},
+    error::{Error, Result},
+};
+
+type Migration = fn(&mut Value) -> Result<()>;
+
+/// `MIGRATIONS[n]` takes details from version `n` to `n + 1`.
+const MIGRATIONS: &[Migration] = &[delays_to_speeds, script_params];
+
+/// The version details are written with.
+pub const VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Details as they are stored, with the version they were written at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned {
+    pub version: u32,
+    pub details: Details,
+}
+
+impl Versioned {
+    pub fn new(details: Details) -> Self {
+        Self {
+            version: VERSION,
+            details,
+        }
+    }
+}
+
+/// The version of some stored details, `Versioned` or from before there was
+/// a version.
+pub fn version_of(value: &Value) -> Result<u32> {
+    match value.get("version") {
+        Some(version) if value.get("details").is_some() => version
+            .as_u64()
+            .and_then(|v| v.try_into().ok())
+            .ok_or_else(|| Error::Migration(format!("bad version {}", version))),
+        _ => Ok(0),
+    }
+}
+
+/// Bring stored details of any version up to date, ready to deserialize as
+/// `Details`.
+///
+/// This gives back a value rather than `Details` because formats differ in
+/// how they write some things, MsgPack has unit structs as empty arrays, so
+/// it's best deserialized by the format it came from.
+pub fn migrate(mut value: Value) -> Result<Value> {
+    let version = version_of(&value)?;
+    if version > VERSION {
+        return Err(Error::Migration(format!(
+            "version {} is newer than this build knows about ({})",
+            version, VERSION
+        )));
+    }
+    let mut details = match value.get_mut("details") {
+        Some(details) if version > 0 => details.take(),
+        _ => value,
+    };
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut details)
+            .map_err(|e| Error::Migration(format!("from version {}: {}", from, e)))?;
+    }
+    Ok(details)
+}
+
+/// Call `f` with the tag and body of every effect in `effect`, including the
+/// ones inside composites.
+fn visit_effects(effect: &mut Value, f: &mut impl FnMut(&str, &mut Value)) {
+    let Some(map) = effect.as_object_mut() else {
+        return;
+    };
+    for (tag, body) in map.iter_mut() {
+        if tag == "Composite" {
+            for inner in body.as_array_mut().into_iter().flatten() {
+                visit_effects(inner, f);
+            }
+        } else {
+            f(tag, body);
+        }
+    }
+}
+
+/// Take out a `delay` in milliseconds. The state that went with it is left
+/// for serde to ignore.
+fn take_delay(body: &mut Map<String, Value>) -> Option<f64> {
+    body.remove("delay")?.as_f64()
+}
+
+/// Ball, Glow and Rainbow used to move a step every `delay`, now they move
+/// with the time since they started.
+fn delays_to_speeds(details: &mut Value) -> Result<()> {
+    let Some(effect) = details.get_mut("effect") else {
+        return Ok(());
+    };
+    visit_effects(effect, &mut |tag, body| match tag {
+        "Ball" => ball_delay_to_speed(body),
+        "Balls" => body
+            .as_array_mut()
+            .into_iter()
+            .flatten()
+            .for_each(ball_delay_to_speed),
+        "Rainbow" => {
+            if let Some(body) = body.as_object_mut() {
+                if let Some(delay) = take_delay(body) {
+                    let speed = per_second(delay).unwrap_or(Rainbow::default().speed);
+                    body.insert("speed".to_owned(), speed.into());
+                }
+            }
+        }
+        "Glow" => {
+            if let Some(body) = body.as_object_mut() {
+                if let Some(delay) = take_delay(body) {
+                    let steps = body.get("steps").and_then(Value::as_u64).unwrap_or(1);
+                    // A step each delay, `steps` of them between each pair of
+                    // colors. Glows from back then had no palette.
+                    let colors = body
+                        .get("colors")
+                        .and_then(Value::as_array)
+                        .map_or(1, Vec::len);
+                    let period = (delay * steps as f64 * colors.max(1) as f64).round() as i64;
+                    let period = if period > 0 {
+                        period
+                    } else {
+                        Glow::default().period.num_milliseconds()
+                    };
+                    body.insert("period".to_owned(), period.into());
+                }
+            }
+        }
+        _ => {}
+    });
+    Ok(())
+}
+
+/// Rune scripts used to be just their source, now they come with the values
+/// of their parameters.
+fn script_params(details: &mut Value) -> Result<()> {
+    let Some(effect) = details.get_mut("effect") else {
+        return Ok(());
+    };
+    visit_effects(effect, &mut |tag, body| {
+        if tag == "RuneScript" && !body.is_array() {
+            *body = Value::Array(vec![body.take(), Value::Object(Map::new())]);
+        }
+    });
+    Ok(())
+}
+
+fn ball_delay_to_speed(body: &mut Value) {
+    if let Some(body) = body.as_object_mut() {
+        if let Some(delay) = take_delay(body) {
+            let speed = per_second(delay).unwrap_or(Ball::default().speed);
+            body.insert("speed".to_owned(), speed.into());
+        }
+    }
+}
+
+/// Steps per second for a step every `delay` milliseconds.
+fn per_second(delay: f64) -> Option<f32> {
+    (delay > 0.0).then(|| (1000.0 / delay) as f32)
+}
@@ -0,0 +1,384 @@
+//! Named color gradients that effects can share.
+//!
+//! A `Palette` is a list of stops along a gradient from 0 to 1. Effects hold a
+//! `PaletteRef`, either the name of a palette or a palette of their own, and
+//! sample it while rendering, so editing a named palette changes every effect
+//! that uses it.
+//!
+//! The built-in palettes are always here. The backend keeps user palettes in
+//! its database and mirrors them in, and the frontend does the same with what
+//! it fetches from `/palettes` so the preview matches the strip.
+
+use std::{collections::BTreeMap, sync::RwLock};
+
+use once_cell::sync::Lazy;
+use palette::{convert::FromColor, Hsv, LinSrgb, Mix, Oklab, Srgb};
+use serde::{Deserialize, Serialize};
+
+static USER_PALETTES: Lazy<RwLock<BTreeMap<String, Palette>>> = Lazy::new(Default::default);
+
+/// Which color space to blend neighbouring stops in.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Straight between the two colors, like the LEDs mix light.
+    #[default]
+    LinearRgb,
+    /// Around the color wheel the short way, which keeps blends saturated.
+    Hsv,
+    /// Perceptually even steps, with no muddy middle.
+    Oklab,
+}
+
+impl Interpolation {
+    pub fn iter_names() -> impl Iterator<Item = &'static str> {
+        ["Linear RGB", "HSV", "OKLab"].into_iter()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::LinearRgb => "Linear RGB",
+            Interpolation::Hsv => "HSV",
+            Interpolation::Oklab => "OKLab",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Linear RGB" => Some(Interpolation::LinearRgb),
+            "HSV" => Some(Interpolation::Hsv),
+            "OKLab" => Some(Interpolation::Oklab),
+            _ => None,
+        }
+    }
+
+    fn mix(&self, a: LinSrgb<u8>, b: LinSrgb<u8>, t: f32) -> LinSrgb<u8> {
+        let (a, b) = (a.into_format::<f32>(), b.into_format::<f32>());
+        let mixed = match self {
+            Interpolation::LinearRgb => a.mix(b, t),
+            Interpolation::Hsv => {
+                let a = Hsv::from_color(Srgb::from_linear(a));
+                let b = Hsv::from_color(Srgb::from_linear(b));
+                Srgb::from_color(a.mix(b, t)).into_linear()
+            }
+            Interpolation::Oklab => {
+                LinSrgb::from_color(Oklab::from_color(a).mix(Oklab::from_color(b), t))
+            }
+        };
+        mixed.into_format()
+    }
+}
+
+/// A color at a point along a palette.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Stop {
+    /// From 0 at the start of the palette to 1 at the end.
+    pub position: f32,
+    pub color: LinSrgb<u8>,
+}
+
+impl Stop {
+    pub fn new(position: f32, color: LinSrgb<u8>) -> Self {
+        Self { position, color }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "PaletteParts")]
+pub struct Palette {
+    /// Kept in order of position.
+    stops: Vec<Stop>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+/// How a `Palette` is stored, sorted and checked on the way in since stored
+/// or posted stops can be in any order.
+#[derive(Deserialize)]
+struct PaletteParts {
+    stops: Vec<Stop>,
+    #[serde(default)]
+    interpolation: Interpolation,
+}
+
+impl TryFrom<PaletteParts> for Palette {
+    type Error = String;
+
+    fn try_from(parts: PaletteParts) -> Result<Self, String> {
+        if let Some(stop) = parts.stops.iter().find(|s| !s.position.is_finite()) {
+            return Err(format!(
+                "Palette stop at {}, it needs to be a number",
+                stop.position
+            ));
+        }
+        Ok(Self::new(parts.stops, parts.interpolation))
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::equidistant(
+            &[LinSrgb::new(255, 0, 0), LinSrgb::new(0, 0, 255)],
+            Interpolation::LinearRgb,
+        )
+    }
+}
+
+impl Palette {
+    pub fn new(mut stops: Vec<Stop>, interpolation: Interpolation) -> Self {
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self {
+            stops,
+            interpolation,
+        }
+    }
+
+    /// Spread `colors` evenly from 0 to 1.
+    pub fn equidistant(colors: &[LinSrgb<u8>], interpolation: Interpolation) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        Self::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, c)| Stop::new(i as f32 / last, *c))
+                .collect(),
+            interpolation,
+        )
+    }
+
+    pub fn stops(&self) -> &[Stop] {
+        &self.stops[..]
+    }
+
+    pub fn add_stop(&mut self, stop: Stop) {
+        self.stops.push(stop);
+        self.sort();
+    }
+
+    /// Replace the stop at `idx`. Moving a stop past its neighbours reorders
+    /// the stops, so indices can change.
+    pub fn set_stop(&mut self, idx: usize, stop: Stop) {
+        self.stops[idx] = stop;
+        self.sort();
+    }
+
+    pub fn remove_stop(&mut self, idx: usize) {
+        self.stops.remove(idx);
+    }
+
+    fn sort(&mut self) {
+        self.stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+    }
+
+    /// The color at `x`, with anything outside 0..1 getting the end colors.
+    pub fn sample(&self, x: f32) -> LinSrgb<u8> {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return LinSrgb::new(0, 0, 0),
+        };
+        // NaN would fall through both ends and find no stop at or below it.
+        if x.is_nan() || x <= first.position {
+            return first.color;
+        }
+        if x >= last.position {
+            return last.color;
+        }
+        let next = self.stops.partition_point(|s| s.position <= x);
+        let (a, b) = (&self.stops[next - 1], &self.stops[next]);
+        self.between(a, b, x)
+    }
+
+    /// The color at `x` wrapped into 0..1, blending from the last stop back
+    /// round to the first, for effects that loop through a palette.
+    pub fn sample_cyclic(&self, x: f32) -> LinSrgb<u8> {
+        let x = x.rem_euclid(1.0);
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return LinSrgb::new(0, 0, 0),
+        };
+        if x < first.position {
+            self.between(&Stop::new(last.position - 1.0, last.color), &first, x)
+        } else if x > last.position {
+            self.between(&last, &Stop::new(first.position + 1.0, first.color), x)
+        } else {
+            self.sample(x)
+        }
+    }
+
+    /// `n` colors spread evenly across the palette, ends included.
+    pub fn colors(&self, n: usize) -> Vec<LinSrgb<u8>> {
+        let last = n.saturating_sub(1).max(1) as f32;
+        (0..n).map(|i| self.sample(i as f32 / last)).collect()
+    }
+
+    fn between(&self, a: &Stop, b: &Stop, x: f32) -> LinSrgb<u8> {
+        let width = b.position - a.position;
+        if width <= f32::EPSILON {
+            return b.color;
+        }
+        self.interpolation
+            .mix(a.color, b.color, (x - a.position) / width)
+    }
+}
+
+/// How an effect says which palette it wants.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum PaletteRef {
+    /// A built-in or user palette, looked up each time it is used.
+    Named(String),
+    Inline(Palette),
+}
+
+impl Default for PaletteRef {
+    fn default() -> Self {
+        PaletteRef::Named("Rainbow".to_owned())
+    }
+}
+
+impl PaletteRef {
+    /// The palette this refers to. A name that doesn't exist (anymore) gets
+    /// the default palette rather than an error, so deleting a palette
+    /// doesn't break effects that use it.
+    pub fn resolve(&self) -> Palette {
+        match self {
+            PaletteRef::Named(name) => palette(name).unwrap_or_default(),
+            PaletteRef::Inline(palette) => palette.clone(),
+        }
+    }
+}
+
+/// Names of the palettes that are always available.
+pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+    [
+        "Rainbow", "Fire", "Lava", "Ocean", "Forest", "Sunset", "Ice", "Party", "Heat",
+    ]
+    .into_iter()
+}
+
+pub fn builtin(name: &str) -> Option<Palette> {
+    let rgb = LinSrgb::new;
+    let palette = match name {
+        "Rainbow" => Palette::equidistant(
+            &[
+                rgb(255, 0, 0),
+                rgb(255, 255, 0),
+                rgb(0, 255, 0),
+                rgb(0, 255, 255),
+                rgb(0, 0, 255),
+                rgb(255, 0, 255),
+                rgb(255, 0, 0),
+            ],
+            Interpolation::Hsv,
+        ),
+        "Fire" => Palette::new(
+            vec![
+                Stop::new(0.0, rgb(0, 0, 0)),
+                Stop::new(0.35, rgb(160, 0, 0)),
+                Stop::new(0.7, rgb(255, 110, 0)),
+                Stop::new(1.0, rgb(255, 255, 160)),
+            ],
+            Interpolation::LinearRgb,
+        ),
+        "Lava" => Palette::new(
+            vec![
+                Stop::new(0.0, rgb(20, 0, 0)),
+                Stop::new(0.4, rgb(140, 0, 0)),
+                Stop::new(0.75, rgb(255, 40, 0)),
+                Stop::new(1.0, rgb(255, 160, 20)),
+            ],
+            Interpolation::Oklab,
+        ),
+        "Ocean" => Palette::equidistant(
+            &[
+                rgb(0, 0, 40),
+                rgb(0, 40, 160),
+                rgb(0, 160, 200),
+                rgb(120, 255, 255),
+            ],
+            Interpolation::Oklab,
+        ),
+        "Forest" => Palette::equidistant(
+            &[
+                rgb(0, 30, 0),
+                rgb(20, 120, 0),
+                rgb(100, 200, 20),
+                rgb(40, 90, 0),
+            ],
+            Interpolation::Oklab,
+        ),
+        "Sunset" => Palette::equidistant(
+            &[
+                rgb(40, 0, 80),
+                rgb(200, 0, 80),
+                rgb(255, 90, 0),
+                rgb(255, 200, 40),
+            ],
+            Interpolation::Oklab,
+        ),
+        "Ice" => Palette::equidistant(
+            &[rgb(0, 0, 60), rgb(60, 120, 255), rgb(255, 255, 255)],
+            Interpolation::LinearRgb,
+        ),
+        "Party" => Palette::equidistant(
+            &[
+                rgb(90, 0, 255),
+                rgb(255, 0, 120),
+                rgb(255, 120, 0),
+                rgb(255, 255, 0),
+                rgb(0, 200, 255),
+            ],
+            Interpolation::Hsv,
+        ),
+        "Heat" => Palette::new(
+            vec![
+                Stop::new(0.0, rgb(0, 0, 255)),
+                Stop::new(0.5, rgb(0, 255, 0)),
+                Stop::new(1.0, rgb(255, 0, 0)),
+            ],
+            Interpolation::Hsv,
+        ),
+        _ => return None,
+    };
+    Some(palette)
+}
+
+pub fn is_builtin(name: &str) -> bool {
+    builtin_names().any(|n| n == name)
+}
+
+/// Add a user palette, replacing any with the same name.
+pub fn set_user_palette(name: impl Into<String>, palette: Palette) {
+    USER_PALETTES
+        .write()
+        .expect("palettes poisoned")
+        .insert(name.into(), palette);
+}
+
+pub fn remove_user_palette(name: &str) -> Option<Palette> {
+    USER_PALETTES
+        .write()
+        .expect("palettes poisoned")
+        .remove(name)
+}
+
+pub fn user_palettes() -> BTreeMap<String, Palette> {
+    USER_PALETTES.read().expect("palettes poisoned").clone()
+}
+
+/// A palette by name, user palettes first.
+pub fn palette(name: &str) -> Option<Palette> {
+    USER_PALETTES
+        .read()
+        .expect("palettes poisoned")
+        .get(name)
+        .cloned()
+        .or_else(|| builtin(name))
+}
+
+/// Every palette name, built-in ones first.
+pub fn palette_names() -> Vec<String> {
+    let users = USER_PALETTES.read().expect("palettes poisoned");
+    builtin_names()
+        .map(str::to_owned)
+        .chain(users.keys().filter(|n| !is_builtin(n)).cloned())
+        .collect()
+}
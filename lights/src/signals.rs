@@ -0,0 +1,200 @@
+//! Named values from outside the strip that effects and scripts can react to.
+//!
+//! Anything that knows something about the world (the HTTP API, MQTT, the
+//! audio analysis) sets signals here, and effects read them while rendering,
+//! so a "temperature" sensor can tint a `Glow` or a "doorbell" can fire a
+//! `SignalFlash` without either knowing where the value came from.
+//!
+//! A `Scheduled` signal is set at the same time every day, so "night" can be
+//! turned on at 22:00 and off again at 07:00.
+
+use std::{collections::BTreeMap, sync::RwLock};
+
+use chrono::{serde::ts_milliseconds, DateTime, Duration, NaiveTime, Utc};
+use once_cell::sync::Lazy;
+use palette::LinSrgb;
+use rune::{ContextError, Module};
+use serde::{Deserialize, Serialize};
+
+type Instant = DateTime<Utc>;
+
+static SIGNALS: Lazy<RwLock<BTreeMap<String, SignalState>>> = Lazy::new(Default::default);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Signal {
+    Bool(bool),
+    Float(f64),
+    Color(LinSrgb<u8>),
+}
+
+impl Signal {
+    /// The signal as a number, with `true` as 1 and colors as their
+    /// brightness from 0 to 1.
+    pub fn as_float(&self) -> f64 {
+        match self {
+            Signal::Bool(b) => *b as u8 as f64,
+            Signal::Float(f) => *f,
+            Signal::Color(c) => c.red.max(c.green).max(c.blue) as f64 / 255.0,
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Signal::Bool(b) => *b,
+            Signal::Float(f) => *f != 0.0,
+            Signal::Color(c) => c.into_components() != (0, 0, 0),
+        }
+    }
+
+    /// Tint `color` by the signal: colors filter it, anything else dims it.
+    pub fn tint(&self, color: LinSrgb<u8>) -> LinSrgb<u8> {
+        match self {
+            Signal::Color(c) => {
+                let (c, color) = (c.into_format::<f32>(), color.into_format::<f32>());
+                (c * color).into_format()
+            }
+            other => (color.into_format::<f32>() * other.as_float().clamp(0.0, 1.0) as f32)
+                .into_format(),
+        }
+    }
+
+    /// The signal as a color, with other kinds as shades of white.
+    pub fn as_color(&self) -> LinSrgb<u8> {
+        match self {
+            Signal::Color(c) => *c,
+            other => {
+                let v = (other.as_float().clamp(0.0, 1.0) * 255.0).round() as u8;
+                LinSrgb::new(v, v, v)
+            }
+        }
+    }
+}
+
+/// A signal's value, and when it was last set.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SignalState {
+    pub value: Signal,
+    #[serde(with = "ts_milliseconds")]
+    pub updated: Instant,
+}
+
+/// Set `name` to `value` as of `t`. Setting a signal to the value it already
+/// has still counts as an update, which is how triggers like a doorbell work.
+pub fn set_signal(name: impl Into<String>, value: Signal, t: Instant) {
+    SIGNALS
+        .write()
+        .expect("signals poisoned")
+        .insert(name.into(), SignalState { value, updated: t });
+}
+
+pub fn remove_signal(name: &str) -> Option<SignalState> {
+    SIGNALS.write().expect("signals poisoned").remove(name)
+}
+
+pub fn signal(name: &str) -> Option<SignalState> {
+    SIGNALS.read().expect("signals poisoned").get(name).copied()
+}
+
+pub fn signals() -> BTreeMap<String, SignalState> {
+    SIGNALS.read().expect("signals poisoned").clone()
+}
+
+/// A signal set at `at` every day.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Scheduled {
+    pub signal: String,
+    /// Time of day, `HH:MM` or `HH:MM:SS`, in whatever time zone the
+    /// schedule is run in.
+    #[serde(with = "time_of_day")]
+    pub at: NaiveTime,
+    /// A trigger if it's left out, like an empty MQTT message.
+    #[serde(default = "trigger")]
+    pub value: Signal,
+}
+
+fn trigger() -> Signal {
+    Signal::Bool(true)
+}
+
+mod time_of_day {
+    use chrono::NaiveTime;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(at: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&at.format("%H:%M:%S"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let at = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&at, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(&at, "%H:%M"))
+            .map_err(|_| D::Error::custom(format!("{:?} isn't a time like 22:30", at)))
+    }
+}
+
+/// The entries of `schedule` that come up after `from` and by `to`, going
+/// round midnight if `to` is before `from`.
+pub fn due(
+    schedule: &[Scheduled],
+    from: NaiveTime,
+    to: NaiveTime,
+) -> impl Iterator<Item = &Scheduled> {
+    schedule.iter().filter(move |entry| {
+        if from <= to {
+            from < entry.at && entry.at <= to
+        } else {
+            from < entry.at || entry.at <= to
+        }
+    })
+}
+
+/// For each signal in `schedule`, the entry that came up most recently
+/// before `now`, so a restart puts signals back how the schedule left them.
+pub fn current(schedule: &[Scheduled], now: NaiveTime) -> Vec<&Scheduled> {
+    let mut latest: BTreeMap<&str, (Duration, &Scheduled)> = BTreeMap::new();
+    for entry in schedule {
+        let mut ago = now - entry.at;
+        if ago < Duration::zero() {
+            ago += Duration::days(1);
+        }
+        let newer = latest
+            .get(entry.signal.as_str())
+            .is_none_or(|(other, _)| ago < *other);
+        if newer {
+            latest.insert(&entry.signal, (ago, entry));
+        }
+    }
+    latest.into_values().map(|(_, entry)| entry).collect()
+}
+
+#[rune::function]
+fn get_float(name: &str) -> Option<f64> {
+    signal(name).map(|s| s.value.as_float())
+}
+
+#[rune::function]
+fn get_bool(name: &str) -> Option<bool> {
+    signal(name).map(|s| s.value.as_bool())
+}
+
+#[rune::function]
+fn get_color(name: &str) -> Option<(u8, u8, u8)> {
+    signal(name).map(|s| s.value.as_color().into_components())
+}
+
+/// When `name` was last set, in the same milliseconds as the `t` scripts get.
+#[rune::function]
+fn last_set(name: &str) -> Option<i64> {
+    signal(name).map(|s| s.updated.timestamp_millis())
+}
+
+/// The `signals` module for scripts.
+pub(crate) fn rune_module() -> Result<Module, ContextError> {
+    let mut module = Module::with_crate("signals")?;
+    module.function_meta(get_float)?;
+    module.function_meta(get_bool)?;
+    module.function_meta(get_color)?;
+    module.function_meta(last_set)?;
+    Ok(module)
+}
@@ -0,0 +1,49 @@
+//! The analysis has to stay quiet through long silences, instead of slowly
+//! turning the noise that follows them up to full scale.
+
+use chrono::{Duration, TimeZone, Utc};
+use lights::effects::{AudioAnalyzer, AudioLevels};
+
+const READ: usize = 512;
+
+/// Noise of about `amplitude`, the same every run.
+fn noise(seed: &mut u32, amplitude: f32) -> Vec<f32> {
+    (0..READ)
+        .map(|_| {
+            *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (*seed as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+        })
+        .collect()
+}
+
+fn assert_quiet(levels: &AudioLevels) {
+    assert!(
+        levels.level.is_finite() && levels.level < 0.05,
+        "{:?}",
+        levels
+    );
+    for band in levels.bands {
+        assert!(band.is_finite() && band < 0.05, "{:?}", levels);
+    }
+}
+
+#[test]
+fn silence_then_noise_stays_quiet() {
+    let mut analyzer = AudioAnalyzer::new(44_100);
+    let mut t = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let step = Duration::milliseconds(12);
+    // Long enough for the peaks to decay far below anything audible.
+    for _ in 0..4000 {
+        if let Some(levels) = analyzer.push(&[0.0; READ], t) {
+            assert_quiet(&levels);
+        }
+        t += step;
+    }
+    let mut seed = 1;
+    for _ in 0..50 {
+        let levels = analyzer.push(&noise(&mut seed, 1e-6), t).unwrap();
+        assert_quiet(&levels);
+        assert_eq!(levels.last_beat, None);
+        t += step;
+    }
+}
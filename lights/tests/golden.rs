@@ -0,0 +1,153 @@
+//! Renders every effect at fixed times and compares with the frames in
+//! `tests/golden/`, so changes to how an effect looks don't go unnoticed.
+//!
+//! After changing an effect on purpose, bless the new frames and check the
+//! diff before committing them:
+//!
+//! ```sh
+//! BLESS=1 cargo test -p lights --test golden
+//! ```
+//!
+//! A golden that doesn't exist is a failure too, so new effects and cases get
+//! blessed the same way.
+
+use std::{env, fs, path::PathBuf};
+
+use chrono::{Duration, TimeZone, Utc};
+use lights::effects::{Ball, Composite, EffectType, Glow, Rainbow};
+use palette::LinSrgb;
+use serde::{Deserialize, Serialize};
+
+const LENGTH: usize = 30;
+const FRAMES: i32 = 40;
+const FRAME_MILLIS: i64 = 50;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Golden {
+    /// Each frame as `rrggbb` per pixel, so a diff shows which frame changed.
+    frames: Vec<String>,
+}
+
+/// Every effect with its defaults, plus the settings that take different
+/// paths through the code.
+fn cases() -> Vec<(String, EffectType)> {
+    let red = LinSrgb::new(255, 0, 0);
+    let blue = LinSrgb::new(0, 0, 255);
+    let mut cases: Vec<_> = EffectType::iter_names()
+        // Plugins need a module from the store.
+        .filter(|name| *name != "Wasm Plugin")
+        .map(|name| (name.to_owned(), EffectType::default_from_name(name)))
+        .collect();
+
+    let mut smooth_ball = Ball::bounce(red, 45.0, LENGTH);
+    smooth_ball.smooth = true;
+    let mut smooth_rainbow = Rainbow::new(vec![red, blue], 3, 1, -1, 12.0);
+    smooth_rainbow.smooth = true;
+    let mut smooth_glow = Glow::new(vec![red, blue], 10, Duration::seconds(1));
+    smooth_glow.smooth = true;
+    let mut palette_glow = Glow::new(vec![], 10, Duration::seconds(1));
+    palette_glow.palette = Some(Default::default());
+    cases.extend([
+        (
+            "Ball wrapping".to_owned(),
+            Ball::wrap(red, 45.0, LENGTH).into(),
+        ),
+        (
+            "Ball wrapping backward".to_owned(),
+            Ball::wrap_backward(red, 45.0, LENGTH).into(),
+        ),
+        (
+            "Ball bouncing".to_owned(),
+            Ball::bounce(red, 45.0, LENGTH).into(),
+        ),
+        (
+            "Ball bouncing backward".to_owned(),
+            Ball::bounce_backward(red, 45.0, LENGTH).into(),
+        ),
+        ("Ball smooth".to_owned(), smooth_ball.into()),
+        (
+            "Rainbow backward".to_owned(),
+            Rainbow::new(vec![red, blue], 3, 1, -1, 12.0).into(),
+        ),
+        ("Rainbow smooth".to_owned(), smooth_rainbow.into()),
+        ("Glow smooth".to_owned(), smooth_glow.into()),
+        ("Glow palette".to_owned(), palette_glow.into()),
+        (
+            "Composite ball over glow".to_owned(),
+            Composite::new(
+                Ball::wrap(red, 45.0, LENGTH).into(),
+                Glow::new(vec![blue, red], 5, Duration::seconds(1)).into(),
+            )
+            .unwrap()
+            .into(),
+        ),
+    ]);
+    cases
+}
+
+fn render(effect: EffectType) -> Golden {
+    let mut effect = effect.try_into_inner().unwrap();
+    let start = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+    let mut pixels = vec![LinSrgb::new(0, 0, 0); LENGTH];
+    let frames = (0..FRAMES)
+        .map(|i| {
+            pixels.fill(LinSrgb::new(0, 0, 0));
+            effect
+                .render(
+                    &mut pixels,
+                    start + Duration::milliseconds(FRAME_MILLIS) * i,
+                )
+                .unwrap();
+            pixels
+                .iter()
+                .map(|p| {
+                    let (r, g, b) = p.into_components();
+                    format!("{:02x}{:02x}{:02x}", r, g, b)
+                })
+                .collect()
+        })
+        .collect();
+    Golden { frames }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    let file = name.to_lowercase().replace(' ', "_");
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(file)
+        .with_extension("json")
+}
+
+#[test]
+fn effects_match_golden_frames() {
+    let bless = env::var_os("BLESS").is_some();
+    let mut failures = vec![];
+    for (name, effect) in cases() {
+        let path = golden_path(&name);
+        let actual = render(effect);
+        let expected = fs::read_to_string(&path)
+            .ok()
+            .map(|json| serde_json::from_str::<Golden>(&json).unwrap());
+        match expected {
+            Some(expected) if !bless => {
+                if let Some(frame) = (0..actual.frames.len())
+                    .find(|i| expected.frames.get(*i) != actual.frames.get(*i))
+                {
+                    failures.push(format!("{}: frame {} differs", name, frame));
+                }
+            }
+            None if !bless => failures.push(format!("{}: no golden frames", name)),
+            _ => {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                let json = serde_json::to_string_pretty(&actual).unwrap();
+                fs::write(&path, json + "\n").unwrap();
+                eprintln!("Wrote {}", path.display());
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nIf this is on purpose, rerun with BLESS=1 and commit the goldens",
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,173 @@
+//! Details saved by older builds, as the backend would find them in its
+//! database, have to come out of `migrate` as current `Details`.
+//!
+//! The MsgPack in `tests/migrations/` is what version 0 wrote with
+//! `rmp_serde::to_vec_named`.
+
+use lights::{
+    details::Details,
+    effects::{EffectType, ParamValues, SourceCode},
+    error::Error,
+    migrations::{self, Versioned},
+};
+use serde_json::{json, Value};
+
+/// Decode the way the backend's storage does, through a `Value` and back.
+fn from_msgpack(bytes: &[u8]) -> Details {
+    let value: Value = rmp_serde::from_slice(bytes).unwrap();
+    assert_eq!(migrations::version_of(&value).unwrap(), 0);
+    let migrated = rmp_serde::to_vec_named(&migrations::migrate(value).unwrap()).unwrap();
+    rmp_serde::from_slice(&migrated).unwrap()
+}
+
+fn from_json(value: Value) -> Details {
+    serde_json::from_value(migrations::migrate(value).unwrap()).unwrap()
+}
+
+fn red() -> Value {
+    json!({ "red": 255, "green": 0, "blue": 0 })
+}
+
+fn blue() -> Value {
+    json!({ "red": 0, "green": 0, "blue": 255 })
+}
+
+fn v0_ball(delay: i64) -> Value {
+    json!({
+        "color": red(),
+        "position": 0,
+        "count": 60,
+        "direction": 1,
+        "bounce": false,
+        "delay": delay,
+        "next_update": null,
+    })
+}
+
+#[test]
+fn version_0_json_delays_become_speeds() {
+    let details = from_json(json!({
+        "effect": { "Composite": [
+            { "Balls": [v0_ball(100), v0_ball(250)] },
+            { "Rainbow": {
+                "colors": [red(), blue()],
+                "color_width": 3,
+                "spacing": 1,
+                "direction": -1,
+                "step": 4,
+                "color_strip": [],
+                "delay": 80,
+                "next_update": null,
+            } },
+        ] },
+        "length": 60,
+        "brightness": 120,
+        "name": "Old",
+    }));
+    assert_eq!(details.name, "Old");
+    assert_eq!(details.length, 60);
+    let EffectType::Composite(composite) = details.effect else {
+        panic!("not a composite: {:?}", details.effect);
+    };
+    let EffectType::Balls(balls) = composite.first() else {
+        panic!("not balls: {:?}", composite.first());
+    };
+    let speeds: Vec<_> = balls.balls().iter().map(|b| b.speed).collect();
+    assert_eq!(speeds, [10.0, 4.0]);
+    let EffectType::Rainbow(rainbow) = composite.second() else {
+        panic!("not a rainbow: {:?}", composite.second());
+    };
+    assert_eq!(rainbow.speed, 12.5);
+}
+
+#[test]
+fn version_0_json_glow_delay_becomes_a_period() {
+    let details = from_json(json!({
+        "effect": { "Glow": {
+            "colors": [red(), blue()],
+            "color_idx": 1,
+            "cur_color": blue(),
+            "step": 2,
+            "steps": 10,
+            "delay": 100,
+            "next_update": null,
+        } },
+        "length": 60,
+        "brightness": 120,
+        "name": "Old",
+    }));
+    let EffectType::Glow(glow) = details.effect else {
+        panic!("not a glow: {:?}", details.effect);
+    };
+    // A step every 100ms, 10 steps from each of the two colors.
+    assert_eq!(glow.period.num_milliseconds(), 2000);
+}
+
+#[test]
+fn version_0_json_script_gets_params() {
+    let details = from_json(json!({
+        "effect": { "RuneScript": { "Source": "pub fn render(pixels, t) { }" } },
+        "length": 60,
+        "brightness": 120,
+        "name": "Script",
+    }));
+    assert_eq!(
+        details.effect,
+        EffectType::RuneScript(
+            SourceCode::Source("pub fn render(pixels, t) { }".to_owned()),
+            ParamValues::default()
+        )
+    );
+}
+
+#[test]
+fn version_0_msgpack_composite() {
+    let details = from_msgpack(include_bytes!("migrations/v0_composite.msgpack"));
+    assert_eq!(details.name, "Old");
+    assert_eq!(details.brightness, 120);
+    let EffectType::Composite(composite) = details.effect else {
+        panic!("not a composite: {:?}", details.effect);
+    };
+    let EffectType::Ball(ball) = composite.first() else {
+        panic!("not a ball: {:?}", composite.first());
+    };
+    assert_eq!(ball.speed, 20.0);
+    assert!(ball.bounce);
+    let EffectType::Glow(glow) = composite.second() else {
+        panic!("not a glow: {:?}", composite.second());
+    };
+    assert_eq!(glow.period.num_milliseconds(), 2000);
+}
+
+#[test]
+fn version_0_msgpack_script() {
+    let details = from_msgpack(include_bytes!("migrations/v0_script.msgpack"));
+    assert_eq!(
+        details.effect,
+        EffectType::RuneScript(
+            SourceCode::Source("pub fn render(pixels, t) { }".to_owned()),
+            ParamValues::default()
+        )
+    );
+}
+
+#[test]
+fn current_details_pass_through() {
+    let details = Details {
+        name: "New".to_owned(),
+        ..Default::default()
+    };
+    let value = serde_json::to_value(Versioned::new(details.clone())).unwrap();
+    assert_eq!(migrations::version_of(&value).unwrap(), migrations::VERSION);
+    assert_eq!(from_json(value), details);
+}
+
+#[test]
+fn newer_versions_are_refused() {
+    let mut value = serde_json::to_value(Versioned::new(Details::default())).unwrap();
+    value["version"] = json!(migrations::VERSION + 1);
+    match migrations::migrate(value) {
+        Err(Error::Migration(message)) => assert!(message.contains("newer"), "{}", message),
+        other => panic!("expected a migration error, got {:?}", other),
+    }
+}
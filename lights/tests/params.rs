@@ -0,0 +1,93 @@
+//! Script parameters stay inside the limits the script declared, and
+//! declarations that can't be satisfied are refused.
+
+use lights::{
+    effects::{ParamKind, ParamSpec, ParamValue, ParamValues, RuneScript, SourceCode},
+    error::RuneError,
+};
+
+fn spec(kind: ParamKind, default: ParamValue) -> ParamSpec {
+    ParamSpec {
+        name: "speed".to_owned(),
+        kind,
+        default,
+    }
+}
+
+fn with_value(value: ParamValue) -> ParamValues {
+    let mut values = ParamValues::default();
+    values.set("speed", value);
+    values
+}
+
+fn script(params: &str) -> Result<RuneScript, RuneError> {
+    RuneScript::from_source(SourceCode::Source(format!(
+        "pub fn params() {{ [{params}] }}\n\
+         pub fn init(params) {{ #{{}} }}\n\
+         pub fn render(state, pixels, t, params) {{ 100 }}\n"
+    )))
+}
+
+#[test]
+fn stored_values_are_clamped() {
+    let int = spec(ParamKind::Int { min: 1, max: 10 }, ParamValue::Int(5));
+    assert_eq!(
+        with_value(ParamValue::Int(50)).value_for(&int),
+        ParamValue::Int(10)
+    );
+    assert_eq!(
+        with_value(ParamValue::Int(-3)).value_for(&int),
+        ParamValue::Int(1)
+    );
+    assert_eq!(
+        with_value(ParamValue::Int(7)).value_for(&int),
+        ParamValue::Int(7)
+    );
+
+    let float = spec(
+        ParamKind::Float { min: 0.0, max: 2.0 },
+        ParamValue::Float(1.0),
+    );
+    assert_eq!(
+        with_value(ParamValue::Float(2.5)).value_for(&float),
+        ParamValue::Float(2.0)
+    );
+    assert_eq!(
+        with_value(ParamValue::Float(-1.0)).value_for(&float),
+        ParamValue::Float(0.0)
+    );
+    assert_eq!(
+        with_value(ParamValue::Float(f64::NAN)).value_for(&float),
+        ParamValue::Float(1.0)
+    );
+    assert_eq!(
+        with_value(ParamValue::Bool(true)).value_for(&float),
+        ParamValue::Float(1.0)
+    );
+}
+
+#[test]
+fn good_specs_are_accepted() {
+    let script = script(
+        r#"#{ name: "speed", type: "float", min: 0.5, max: 2.0, default: 1.0 },
+           #{ name: "count", type: "int", min: 1, max: 1 }"#,
+    )
+    .unwrap();
+    assert_eq!(script.schema().len(), 2);
+}
+
+#[test]
+fn bad_specs_are_refused() {
+    for params in [
+        r#"#{ name: "count", type: "int", min: 10, max: 1 }"#,
+        r#"#{ name: "count", type: "int", min: 1, max: 10, default: 11 }"#,
+        r#"#{ name: "speed", type: "float", min: 2.0, max: 1.0 }"#,
+        r#"#{ name: "speed", type: "float", min: 0.0, max: 1.0, default: -0.5 }"#,
+    ] {
+        assert!(
+            matches!(script(params), Err(RuneError::Params(_))),
+            "{} was accepted",
+            params
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Scheduled signals come up once a day, including across midnight.
+
+use chrono::NaiveTime;
+use lights::signals::{self, Scheduled, Signal};
+
+fn at(h: u32, m: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(h, m, 0).unwrap()
+}
+
+fn night() -> Vec<Scheduled> {
+    vec![
+        Scheduled {
+            signal: "night".to_owned(),
+            at: at(22, 0),
+            value: Signal::Bool(true),
+        },
+        Scheduled {
+            signal: "night".to_owned(),
+            at: at(7, 0),
+            value: Signal::Bool(false),
+        },
+    ]
+}
+
+fn due(from: NaiveTime, to: NaiveTime) -> Vec<Signal> {
+    signals::due(&night(), from, to).map(|e| e.value).collect()
+}
+
+#[test]
+fn entries_are_due_once() {
+    assert_eq!(due(at(21, 59), at(22, 0)), [Signal::Bool(true)]);
+    assert!(due(at(22, 0), at(22, 1)).is_empty());
+    assert_eq!(due(at(6, 59), at(7, 0)), [Signal::Bool(false)]);
+}
+
+#[test]
+fn entries_are_due_across_midnight() {
+    assert!(due(at(23, 59), at(0, 1)).is_empty());
+    assert_eq!(
+        due(at(21, 0), at(7, 30)),
+        [Signal::Bool(true), Signal::Bool(false)]
+    );
+}
+
+#[test]
+fn current_is_the_latest_entry() {
+    let schedule = night();
+    let current = |now| {
+        signals::current(&schedule, now)
+            .into_iter()
+            .map(|e| e.value)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(current(at(23, 0)), [Signal::Bool(true)]);
+    assert_eq!(current(at(3, 0)), [Signal::Bool(true)]);
+    assert_eq!(current(at(12, 0)), [Signal::Bool(false)]);
+}
+
+#[test]
+fn times_parse_with_or_without_seconds() {
+    let entry: Scheduled =
+        serde_json::from_str(r#"{ "signal": "doorbell", "at": "08:30" }"#).unwrap();
+    assert_eq!(entry.at, at(8, 30));
+    assert_eq!(entry.value, Signal::Bool(true));
+    let entry: Scheduled =
+        serde_json::from_str(r#"{ "signal": "doorbell", "at": "08:30:15" }"#).unwrap();
+    assert_eq!(entry.at, NaiveTime::from_hms_opt(8, 30, 15).unwrap());
+    assert!(serde_json::from_str::<Scheduled>(r#"{ "signal": "x", "at": "late" }"#).is_err());
+}
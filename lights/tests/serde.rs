@@ -0,0 +1,95 @@
+//! Effects are stored as MsgPack by the backend and sent as JSON to the
+//! frontend, so every one of them has to come back the same from both.
+
+use chrono::Duration;
+use lights::{
+    details::Details,
+    effects::{Ball, Balls, Composite, EffectType, Glow, Rainbow},
+    palettes::{Interpolation, Palette, PaletteRef, Stop},
+};
+use palette::LinSrgb;
+
+fn effects() -> Vec<EffectType> {
+    let red = LinSrgb::new(255, 0, 0);
+    let blue = LinSrgb::new(0, 0, 255);
+    let mut glow = Glow::new(vec![red, blue], 7, Duration::milliseconds(1500));
+    glow.palette = Some(PaletteRef::Inline(Palette::new(
+        vec![Stop::new(0.0, red), Stop::new(0.6, blue)],
+        Interpolation::Oklab,
+    )));
+    let mut rainbow = Rainbow::new(vec![red, blue], 2, 3, -1, 8.5);
+    rainbow.palette = Some(PaletteRef::Named("Ocean".to_owned()));
+    rainbow.smooth = true;
+    let mut effects: Vec<_> = EffectType::iter_names()
+        .map(EffectType::default_from_name)
+        .collect();
+    effects.extend([
+        Ball::bounce_backward(red, 12.5, 40).into(),
+        Balls::new(&[Ball::wrap(red, 3.0, 40), Ball::bounce(blue, 7.0, 40)]).into(),
+        glow.into(),
+        rainbow.into(),
+        Composite::new(Ball::wrap(red, 3.0, 40).into(), Glow::default().into())
+            .unwrap()
+            .into(),
+    ]);
+    effects
+}
+
+#[test]
+fn effects_round_trip_through_json() {
+    for effect in effects() {
+        let json = serde_json::to_string(&effect).unwrap();
+        let back: EffectType = serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("{}: {}\n{}", effect.name(), e, json));
+        assert_eq!(effect, back, "{}", effect.name());
+    }
+}
+
+#[test]
+fn effects_round_trip_through_msgpack() {
+    for effect in effects() {
+        let bytes = rmp_serde::to_vec_named(&effect).unwrap();
+        let back: EffectType =
+            rmp_serde::from_slice(&bytes).unwrap_or_else(|e| panic!("{}: {}", effect.name(), e));
+        assert_eq!(effect, back, "{}", effect.name());
+    }
+}
+
+#[test]
+fn details_round_trip() {
+    let details = Details {
+        effect: Glow::default().into(),
+        name: "Evening".to_owned(),
+        ..Default::default()
+    };
+    let json = serde_json::to_string(&details).unwrap();
+    assert_eq!(details, serde_json::from_str(&json).unwrap());
+    let bytes = rmp_serde::to_vec_named(&details).unwrap();
+    assert_eq!(details, rmp_serde::from_slice(&bytes).unwrap());
+}
+
+#[test]
+fn palette_stops_are_sorted_when_loaded() {
+    let palette: Palette = serde_json::from_value(serde_json::json!({
+        "stops": [
+            { "position": 1.0, "color": { "red": 0, "green": 0, "blue": 255 } },
+            { "position": 0.0, "color": { "red": 255, "green": 0, "blue": 0 } },
+        ],
+    }))
+    .unwrap();
+    let positions: Vec<_> = palette.stops().iter().map(|s| s.position).collect();
+    assert_eq!(positions, [0.0, 1.0]);
+    assert_eq!(palette.sample(0.0), LinSrgb::new(255, 0, 0));
+}
+
+#[test]
+fn palette_nan_stops_are_refused() {
+    let red = LinSrgb::new(255, 0, 0);
+    // Built directly, since `Palette::new` only sorts.
+    let palette = Palette::new(
+        vec![Stop::new(0.0, red), Stop::new(f32::NAN, red)],
+        Interpolation::LinearRgb,
+    );
+    let bytes = rmp_serde::to_vec_named(&palette).unwrap();
+    assert!(rmp_serde::from_slice::<Palette>(&bytes).is_err());
+}
@@ -0,0 +1,165 @@
+//! Run an effect against a strip that only exists in the terminal.
+//!
+//! The effect gets a made up clock that starts at the epoch and moves on by
+//! however long the effect asks to wait, just like the backend's render loop,
+//! so two runs of the same effect draw the same frames. `--speed` only changes
+//! how long we sleep between them.
+//!
+//! There's no database here, so effects that use library scripts or user
+//! palettes by name need them from a bundle, like `/presets/export` gives,
+//! with `--bundle`.
+
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    thread,
+};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use clap::{Parser, ValueEnum};
+use lights::{
+    bundle::Bundle,
+    details::Details,
+    effects::{drain_script_output, set_library_module},
+    palettes::set_user_palette,
+};
+use palette::LinSrgb;
+
+#[derive(Debug, Parser)]
+#[command(about = "Run a lights effect in the terminal")]
+struct Args {
+    /// JSON with the details, like `/details` gives, or just an effect. `-`
+    /// for stdin.
+    input: PathBuf,
+    /// A bundle, JSON like `/presets/export` gives, to take library scripts
+    /// and user palettes from.
+    #[arg(short, long)]
+    bundle: Option<PathBuf>,
+    /// Pixels in the strip, defaults to the length in the details.
+    #[arg(short, long)]
+    length: Option<usize>,
+    /// How many seconds of the effect to run.
+    #[arg(short, long, default_value_t = 10.0)]
+    duration: f64,
+    /// How much faster than real time to go, 0 for as fast as possible.
+    #[arg(short, long, default_value_t = 1.0)]
+    speed: f64,
+    /// Draw a frame every `1 / fps` seconds instead of when the effect asks.
+    #[arg(long)]
+    fps: Option<u32>,
+    /// Write the frames out instead of drawing them.
+    #[arg(long)]
+    dump: Option<Dump>,
+    /// Where to write the dump, defaults to stdout.
+    #[arg(short, long, requires = "dump")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Dump {
+    /// A JSON object per frame, one per line.
+    Json,
+    /// A `frame,ms,pixel,red,green,blue` row per pixel per frame.
+    Csv,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.duration < 0.0 || args.speed < 0.0 || args.fps == Some(0) {
+        bail!("Duration, speed and fps can't be negative or 0 fps");
+    }
+
+    if let Some(path) = &args.bundle {
+        // Only its scripts and palettes are used, so its presets needn't be
+        // migrated.
+        let bundle: Bundle<serde_json::Value> = serde_json::from_str(&fs::read_to_string(path)?)?;
+        for (name, source) in bundle.scripts {
+            set_library_module(name, source);
+        }
+        for (name, palette) in bundle.palettes {
+            set_user_palette(name, palette);
+        }
+    }
+    let (effect, length) = Details::read_effect(&args.input)?;
+    let length = args.length.or(length).unwrap_or(Details::default().length);
+    let mut effect = effect.try_into_inner()?;
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    if let Some(Dump::Csv) = args.dump {
+        writeln!(out, "frame,ms,pixel,red,green,blue")?;
+    }
+
+    let start = Utc.timestamp_opt(0, 0).unwrap();
+    let end = start + Duration::microseconds((args.duration * 1_000_000.0) as i64);
+    let mut pixels = vec![LinSrgb::new(0, 0, 0); length];
+    let mut t = start;
+    let mut frame = 0;
+    while t <= end {
+        pixels.fill(LinSrgb::new(0, 0, 0));
+        let wait = effect.render(&mut pixels, t)?;
+        for line in drain_script_output().lines() {
+            eprintln!("script: {}", line);
+        }
+        let ms = (t - start).num_milliseconds();
+        match args.dump {
+            Some(Dump::Json) => {
+                let pixels: Vec<_> = pixels.iter().map(|p| p.into_components()).collect();
+                writeln!(
+                    out,
+                    "{}",
+                    serde_json::json!({ "frame": frame, "ms": ms, "pixels": pixels })
+                )?;
+            }
+            Some(Dump::Csv) => {
+                for (i, p) in pixels.iter().enumerate() {
+                    let (r, g, b) = p.into_components();
+                    writeln!(out, "{},{},{},{},{},{}", frame, ms, i, r, g, b)?;
+                }
+            }
+            None => draw(&mut out, &pixels, t, start)?,
+        }
+
+        let step = match args.fps {
+            Some(fps) => Duration::microseconds(1_000_000 / fps as i64),
+            // However short a frame an effect asks for, make progress.
+            None => wait.max(Duration::milliseconds(1)),
+        };
+        if args.dump.is_none() && args.speed > 0.0 {
+            if let Ok(sleep) = step.to_std() {
+                thread::sleep(sleep.div_f64(args.speed));
+            }
+        }
+        t += step;
+        frame += 1;
+    }
+    if args.dump.is_none() {
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Redraw the strip over the last one, a truecolor block per pixel.
+fn draw(
+    out: &mut impl Write,
+    pixels: &[LinSrgb<u8>],
+    t: DateTime<Utc>,
+    start: DateTime<Utc>,
+) -> Result<()> {
+    write!(
+        out,
+        "\r{:>8.2}s ",
+        (t - start).num_milliseconds() as f64 / 1000.0
+    )?;
+    for p in pixels {
+        let (r, g, b) = p.into_components();
+        write!(out, "\x1b[38;2;{};{};{}m█", r, g, b)?;
+    }
+    write!(out, "\x1b[0m")?;
+    Ok(out.flush()?)
+}